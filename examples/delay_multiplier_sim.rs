@@ -41,11 +41,14 @@ fn main() {
 
     sender_slot.fill(LossySender::new(
         sender_address,
-        link1_slot.address().cast(),
+        vec![link1_slot.address().cast()],
         receiver_slot.address().cast(),
         &mut flow_meter,
         cca_template.with(&dna),
         false,
+        false,
+        false,
+        packets(1),
         rng.create_child(),
         table.logger(1),
     ));
@@ -72,7 +72,7 @@ pub fn main() {
     let utility = AlphaFairness::PROPORTIONAL_THROUGHPUT_DELAY_FAIRNESS;
     match args.mode {
         Mode::Remy => {
-            let dna: RemyDna<false> = RemyDna::load(&args.dna).unwrap();
+            let dna: RemyDna<false> = <RemyDna as Config<Custom>>::load(&args.dna).unwrap();
             let policy = TimerWrapper {
                 dna: &dna,
                 durations: &durations,
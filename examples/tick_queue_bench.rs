@@ -0,0 +1,143 @@
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use flowforge::{
+    quantities::{milliseconds, Time},
+    simulation::TickQueue,
+    util::rand::{ContinuousDistribution, DiscreteDistribution, Rng},
+};
+use vec_map::VecMap;
+
+#[derive(Parser, Debug)]
+struct Args {
+    /// Number of ticks to simulate for each queue size
+    #[arg(long, default_value_t = 1_000_000)]
+    ticks: u32,
+
+    /// Component counts to benchmark
+    #[arg(long, value_delimiter = ',', default_values_t = [16, 64, 256, 1024])]
+    components: Vec<usize>,
+}
+
+/// The original linear-scan tick queue, kept for comparison.
+struct LinearTickQueue {
+    current_time: Time,
+    waiting: VecMap<Time>,
+}
+
+impl LinearTickQueue {
+    fn with_capacity(capacity: usize) -> LinearTickQueue {
+        LinearTickQueue {
+            current_time: Time::MIN,
+            waiting: VecMap::with_capacity(capacity),
+        }
+    }
+
+    fn update(&mut self, id: usize, time: Option<Time>) {
+        if let Some(time) = time {
+            assert!(time >= self.current_time);
+            self.waiting.insert(id, time);
+        } else {
+            self.waiting.remove(id);
+        }
+    }
+
+    fn pop_next(&mut self) -> (Time, Option<usize>) {
+        if let Some((idx, time)) = self
+            .waiting
+            .iter()
+            .min_by_key(|&(_, time)| time)
+            .map(|(x, t)| (x, *t))
+        {
+            self.current_time = time;
+            self.waiting.remove(idx);
+            (time, Some(idx))
+        } else {
+            (Time::MAX, None)
+        }
+    }
+}
+
+trait Queue {
+    fn update(&mut self, id: usize, time: Option<Time>);
+    fn pop_next(&mut self) -> (Time, Option<usize>);
+}
+
+impl Queue for LinearTickQueue {
+    fn update(&mut self, id: usize, time: Option<Time>) {
+        LinearTickQueue::update(self, id, time);
+    }
+
+    fn pop_next(&mut self) -> (Time, Option<usize>) {
+        LinearTickQueue::pop_next(self)
+    }
+}
+
+impl Queue for TickQueue {
+    fn update(&mut self, id: usize, time: Option<Time>) {
+        TickQueue::update(self, id, time);
+    }
+
+    fn pop_next(&mut self) -> (Time, Option<usize>) {
+        TickQueue::pop_next(self)
+    }
+}
+
+/// Mimics the simulator: pop the next component, reschedule it, and wake up another one
+/// as if it had been sent a message.
+fn run(queue: &mut impl Queue, components: usize, ticks: u32, seed: u64) -> (Duration, u64) {
+    let mut rng = Rng::from_seed(seed);
+    let delay = ContinuousDistribution::Exponential {
+        mean: milliseconds(1.),
+    };
+    let component = DiscreteDistribution::Uniform {
+        min: 0,
+        max: components as u32 - 1,
+    };
+    for id in 0..components {
+        queue.update(id, Some(Time::SIM_START + rng.sample(&delay)));
+    }
+    let mut checksum: u64 = 0;
+    let start = Instant::now();
+    for _ in 0..ticks {
+        let (time, id) = queue.pop_next();
+        let id = id.unwrap();
+        checksum = checksum.wrapping_mul(31).wrapping_add(id as u64);
+        queue.update(id, Some(time + rng.sample(&delay)));
+        queue.update(
+            rng.sample(&component) as usize,
+            Some(time + rng.sample(&delay)),
+        );
+    }
+    (Instant::now() - start, checksum)
+}
+
+fn main() {
+    let args = Args::parse();
+    println!("{:>10} {:>12} {:>12} {:>8}", "components", "linear", "heap", "speedup");
+    for &components in &args.components {
+        let (linear, linear_checksum) = run(
+            &mut LinearTickQueue::with_capacity(components),
+            components,
+            args.ticks,
+            8_237_491,
+        );
+        let (heap, heap_checksum) = run(
+            &mut TickQueue::with_capacity(components),
+            components,
+            args.ticks,
+            8_237_491,
+        );
+        assert_eq!(
+            linear_checksum, heap_checksum,
+            "Queues ticked components in different orders!"
+        );
+        println!(
+            "{:>10} {:>12.2?} {:>12.2?} {:>7.1}x",
+            components,
+            linear,
+            heap,
+            linear.as_secs_f64() / heap.as_secs_f64()
+        );
+    }
+}
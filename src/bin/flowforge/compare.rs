@@ -0,0 +1,130 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use flowforge::{
+    eval::{Evaluation, EvaluationConfig, PairedComparison, SampledNetworks},
+    flow::{NoActiveFlows, UtilityConfig},
+    networks::{DefaultNetworkBuilder, DefaultNetworkConfig},
+    trainers::DefaultEffect,
+    util::rand::Rng,
+    Cca, Config,
+};
+use serde::Serialize;
+
+use crate::{FlowAdders, UseCca};
+
+/// A flow mode to compare, and the DNA file to use if it is a learned mode.
+pub type ComparedCca = (FlowAdders, Option<PathBuf>);
+
+/// Parses `MODE` or `MODE=DNA`.
+pub fn parse_compared_cca(value: &str) -> Result<ComparedCca, String> {
+    let (mode, dna) = value.split_once('=').map_or((value, None), |(mode, dna)| {
+        (mode, Some(PathBuf::from(dna)))
+    });
+    Ok((FlowAdders::from_str(mode, true)?, dna))
+}
+
+fn label((mode, dna): &ComparedCca) -> String {
+    dna.as_ref().map_or_else(
+        || format!("{mode:?}"),
+        |dna| format!("{mode:?}={}", dna.display()),
+    )
+}
+
+struct EvaluateOn<'a> {
+    evaluation_config: &'a EvaluationConfig,
+    networks: &'a SampledNetworks<DefaultNetworkBuilder>,
+    utility_config: &'a UtilityConfig,
+}
+
+impl UseCca for EvaluateOn<'_> {
+    type Output = Result<Evaluation<DefaultNetworkBuilder>, NoActiveFlows>;
+
+    fn call<C: Cca>(self, new_cca: impl Fn() -> C + Sync) -> Self::Output {
+        self.evaluation_config.evaluate_on::<_, DefaultEffect, _>(
+            new_cca,
+            self.networks,
+            self.utility_config,
+        )
+    }
+}
+
+#[derive(Serialize)]
+struct ComparisonOutput {
+    baseline: String,
+    other: String,
+    comparison: PairedComparison,
+}
+
+pub fn compare(
+    evaluation_config: &Path,
+    network_config: &Path,
+    utility_config: &Path,
+    ccas: &[ComparedCca],
+    output: Option<&Path>,
+    eval_seed: u64,
+) -> Result<()> {
+    if ccas.len() < 2 {
+        return Err(anyhow!("At least two CCAs are needed for a comparison"));
+    }
+    let mut rng = Rng::from_seed(eval_seed);
+    let evaluation_config = EvaluationConfig::load(evaluation_config)?;
+    let network_config = DefaultNetworkConfig::load(network_config)?;
+    let utility_config = UtilityConfig::load(utility_config)?;
+    let networks = evaluation_config.sample_networks::<DefaultEffect, _>(&network_config, &mut rng);
+
+    let evaluations = ccas
+        .iter()
+        .map(|(mode, dna)| {
+            let evaluation = mode.use_cca(
+                dna.as_deref(),
+                EvaluateOn {
+                    evaluation_config: &evaluation_config,
+                    networks: &networks,
+                    utility_config: &utility_config,
+                },
+            )?;
+            evaluation.map_err(|_| anyhow!("Expected active flows!"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    for (cca, evaluation) in ccas.iter().zip(&evaluations) {
+        println!(
+            "{} achieved expected utility {} with {}",
+            label(cca),
+            evaluation.utility,
+            evaluation.flow_properties
+        );
+    }
+
+    let mut outputs = Vec::new();
+    for (cca, evaluation) in ccas.iter().zip(&evaluations).skip(1) {
+        let comparison =
+            PairedComparison::new(&evaluations[0], evaluation, &mut rng.create_child())
+                .map_err(|_| anyhow!("No network had active flows for both CCAs"))?;
+        println!(
+            "{} vs {}: mean difference {:.4} (95% CI {:.4} to {:.4}) over {} networks, p = {:.4}",
+            label(cca),
+            label(&ccas[0]),
+            comparison.difference.mean,
+            comparison.difference.ci_low,
+            comparison.difference.ci_high,
+            comparison.networks.len(),
+            comparison.p_value
+        );
+        outputs.push(ComparisonOutput {
+            baseline: label(&ccas[0]),
+            other: label(cca),
+            comparison,
+        });
+    }
+
+    if let Some(output) = output {
+        serde_json::to_writer_pretty(BufWriter::new(File::create(output)?), &outputs)?;
+    }
+    Ok(())
+}
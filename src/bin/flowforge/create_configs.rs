@@ -3,11 +3,19 @@ use std::{fs::create_dir_all, path::Path};
 use anyhow::Result;
 use flowforge::{
     eval::EvaluationConfig,
-    flow::{AlphaFairness, UtilityConfig},
-    networks::{remy::RemyNetworkDistribution, DefaultNetworkConfig},
-    quantities::seconds,
+    flow::{
+        AlphaFairness, FairnessPenalty, FlowCompletionTime, Slowdown, TailLatency, UtilityConfig,
+    },
+    networks::{
+        dumbbell::DumbbellNetworkDistribution,
+        parking_lot::ParkingLotNetworkDistribution,
+        remy::{RemyNetworkDistribution, RemyNetworkSweep, RemySweepDimension},
+        DefaultNetworkConfig,
+    },
+    quantities::{bits_per_second, milliseconds, seconds},
     trainers::{
-        delay_multiplier::DelayMultiplierTrainer, remy::RemyTrainer, remyr::RemyrTrainer,
+        delay_multiplier::DelayMultiplierTrainer, remy::RemyTrainer,
+        remy_genetic::RemyGeneticTrainer, remyr::RemyrTrainer, remyr_es::RemyrEsTrainer,
         TrainerConfig,
     },
     Config,
@@ -16,9 +24,14 @@ use flowforge::{
 pub fn create_all_configs(folder: &Path) -> Result<()> {
     create_dir_all(folder.join("eval"))?;
     create_dir_all(folder.join("network/remy"))?;
+    create_dir_all(folder.join("network/dumbbell"))?;
+    create_dir_all(folder.join("network/parking_lot"))?;
+    create_dir_all(folder.join("sweep"))?;
     create_dir_all(folder.join("trainer/remy"))?;
     create_dir_all(folder.join("trainer/remyr"))?;
+    create_dir_all(folder.join("trainer/remyr_es"))?;
     create_dir_all(folder.join("trainer/delay_multiplier"))?;
+    create_dir_all(folder.join("trainer/remy_genetic"))?;
     create_dir_all(folder.join("utility"))?;
 
     EvaluationConfig::default().save(&folder.join("eval/default.json"))?;
@@ -35,16 +48,56 @@ pub fn create_all_configs(folder: &Path) -> Result<()> {
 
     DefaultNetworkConfig::Remy(RemyNetworkDistribution::default())
         .save(&folder.join("network/remy/default.json"))?;
+    DefaultNetworkConfig::Dumbbell(DumbbellNetworkDistribution::default())
+        .save(&folder.join("network/dumbbell/default.json"))?;
+    DefaultNetworkConfig::ParkingLot(ParkingLotNetworkDistribution::default())
+        .save(&folder.join("network/parking_lot/default.json"))?;
+
+    RemyNetworkSweep {
+        dimensions: vec![RemySweepDimension::PacketRate {
+            values: [5., 10., 15., 20., 25., 30., 40., 50.]
+                .map(|x| bits_per_second(x * 1_000_000.))
+                .to_vec(),
+        }],
+    }
+    .save(&folder.join("sweep/bandwidth.json"))?;
+    RemyNetworkSweep {
+        dimensions: vec![
+            RemySweepDimension::Rtt {
+                values: [50., 100., 150., 200., 300.].map(milliseconds).to_vec(),
+            },
+            RemySweepDimension::NumSenders {
+                values: vec![1, 2, 4, 8, 16],
+            },
+        ],
+    }
+    .save(&folder.join("sweep/rtt_senders.json"))?;
 
     TrainerConfig::Remy(RemyTrainer::default()).save(&folder.join("trainer/remy/default.json"))?;
     TrainerConfig::Remyr(RemyrTrainer::default())
         .save(&folder.join("trainer/remyr/default.json"))?;
+    TrainerConfig::RemyrEs(RemyrEsTrainer::default())
+        .save(&folder.join("trainer/remyr_es/default.json"))?;
     TrainerConfig::DelayMultiplier(DelayMultiplierTrainer::default())
         .save(&folder.join("trainer/delay_multiplier/default.json"))?;
+    TrainerConfig::RemyGenetic(RemyGeneticTrainer::default())
+        .save(&folder.join("trainer/remy_genetic/default.json"))?;
 
     UtilityConfig::AlphaFairness(AlphaFairness::MINIMISE_FIXED_LENGTH_FILE_TRANSFER)
         .save(&folder.join("utility/mflft_default.json"))?;
     UtilityConfig::AlphaFairness(AlphaFairness::PROPORTIONAL_THROUGHPUT_DELAY_FAIRNESS)
         .save(&folder.join("utility/ptdf_default.json"))?;
+    UtilityConfig::TailLatency(TailLatency::PROPORTIONAL_THROUGHPUT_P99_DELAY_FAIRNESS)
+        .save(&folder.join("utility/ptdf_p99.json"))?;
+    UtilityConfig::FairnessPenalty(FairnessPenalty {
+        utility: Box::new(UtilityConfig::AlphaFairness(
+            AlphaFairness::PROPORTIONAL_THROUGHPUT_DELAY_FAIRNESS,
+        )),
+        weight: 10.,
+    })
+    .save(&folder.join("utility/ptdf_fairness_penalty.json"))?;
+    UtilityConfig::FlowCompletionTime(FlowCompletionTime::MEAN)
+        .save(&folder.join("utility/fct_mean.json"))?;
+    UtilityConfig::Slowdown(Slowdown::P99).save(&folder.join("utility/slowdown_p99.json"))?;
     Ok(())
 }
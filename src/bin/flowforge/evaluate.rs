@@ -1,86 +1,195 @@
-use std::path::Path;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use flowforge::{
-    eval::EvaluationConfig,
-    flow::{FlowProperties, UtilityConfig},
-    networks::DefaultNetworkConfig,
+    eval::{Evaluation, EvaluationConfig, EvaluationReport, SenderMix},
+    flow::UtilityConfig,
+    networks::{DefaultNetworkBuilder, DefaultNetworkConfig},
     quantities::Float,
-    trainers::{
-        delay_multiplier::DelayMultiplierTrainer, remy::RemyTrainer, remyr::RemyrTrainer,
-        DefaultEffect,
-    },
+    trainers::DefaultEffect,
     util::rand::Rng,
-    CcaTemplate, Config, NetworkDistribution, Trainer,
+    util::stats::Estimate,
+    Cca, Config,
 };
 
-use crate::FlowAdders;
+use crate::{FlowAdders, UseCca};
+
+struct Evaluate<'a> {
+    evaluation_config: &'a EvaluationConfig,
+    network_config: &'a DefaultNetworkConfig,
+    utility_config: &'a UtilityConfig,
+    rng: &'a mut Rng,
+}
+
+impl UseCca for Evaluate<'_> {
+    type Output = Evaluation<DefaultNetworkBuilder>;
+
+    fn call<C: Cca>(self, new_cca: impl Fn() -> C + Sync) -> Self::Output {
+        self.evaluation_config
+            .evaluate_detailed::<_, DefaultEffect, _>(
+                new_cca,
+                self.network_config,
+                self.utility_config,
+                &mut self.rng.identical_child_factory()(),
+            )
+            .expect("Expected active flows!")
+    }
+}
 
-pub fn _evaluate<T>(
-    evaluation_config: &EvaluationConfig,
-    network_config: &impl NetworkDistribution<DefaultEffect<'static>>,
-    utility_config: &UtilityConfig,
-    input_path: &Path,
-    rng: &mut Rng,
-) -> (Float, FlowProperties)
+struct EvaluateAgainst<'a, F> {
+    evaluate: Evaluate<'a>,
+    new_cca: F,
+    mix: SenderMix,
+}
+
+impl<F, D> UseCca for EvaluateAgainst<'_, F>
 where
-    T: Trainer,
+    F: Fn() -> D + Sync,
+    D: Cca,
 {
-    let dna = T::Dna::load(input_path).unwrap();
+    type Output = ();
 
-    let x = evaluation_config
-        .evaluate(
-            T::CcaTemplate::default().with(&dna),
+    fn call<C: Cca>(self, new_other_cca: impl Fn() -> C + Sync) -> Self::Output {
+        let Evaluate {
+            evaluation_config,
             network_config,
             utility_config,
-            &mut rng.identical_child_factory()(),
+            rng,
+        } = self.evaluate;
+        let result = evaluation_config
+            .evaluate_against::<_, _, DefaultEffect, _>(
+                self.new_cca,
+                new_other_cca,
+                self.mix,
+                network_config,
+                utility_config,
+                &mut rng.identical_child_factory()(),
+            )
+            .expect("Expected active flows!");
+        println!(
+            "Achieved expected utility {} with {}",
+            result.utility, result.flow_properties
+        );
+        println!("Fairness between all flows: {}", result.fairness);
+        for (name, properties) in [("MODE", result.first), ("AGAINST", result.second)] {
+            match properties {
+                Some(properties) => println!("{name} flows achieved {properties}"),
+                None => println!("{name} flows were never active"),
+            }
+        }
+    }
+}
+
+struct Against<'a> {
+    evaluate: Evaluate<'a>,
+    against: &'a FlowAdders,
+    dna: Option<&'a Path>,
+    mix: SenderMix,
+}
+
+impl UseCca for Against<'_> {
+    type Output = Result<()>;
+
+    fn call<C: Cca>(self, new_cca: impl Fn() -> C + Sync) -> Self::Output {
+        self.against.use_cca(
+            self.dna,
+            EvaluateAgainst {
+                evaluate: self.evaluate,
+                new_cca,
+                mix: self.mix,
+            },
         )
-        .expect("Expected active flows!");
-    #[allow(clippy::let_and_return)]
-    x
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn evaluate(
     mode: &FlowAdders,
     evaluation_config: &Path,
     network_config: &Path,
     utility_config: &Path,
-    input_path: &Path,
+    input_path: Option<&Path>,
+    against: Option<(&FlowAdders, Option<&Path>, Option<u32>)>,
+    output: Option<&Path>,
     eval_seed: u64,
 ) -> Result<()> {
     let mut rng = Rng::from_seed(eval_seed);
     let evaluation_config = EvaluationConfig::load(evaluation_config)?;
     let network_config = DefaultNetworkConfig::load(network_config)?;
     let utility_config = UtilityConfig::load(utility_config)?;
+    let evaluate = Evaluate {
+        evaluation_config: &evaluation_config,
+        network_config: &network_config,
+        utility_config: &utility_config,
+        rng: &mut rng,
+    };
 
-    let (score, flow_properties) = match mode {
-        FlowAdders::Remy => _evaluate::<RemyTrainer>(
-            &evaluation_config,
-            &network_config,
-            &utility_config,
+    if let Some((against, against_dna, senders)) = against {
+        return mode.use_cca(
             input_path,
-            &mut rng,
-        ),
-        FlowAdders::DelayMultiplier => _evaluate::<DelayMultiplierTrainer>(
-            &evaluation_config,
-            &network_config,
-            &utility_config,
-            input_path,
-            &mut rng,
-        ),
-        FlowAdders::Remyr => _evaluate::<RemyrTrainer>(
-            &evaluation_config,
-            &network_config,
-            &utility_config,
-            input_path,
-            &mut rng,
-        ),
-    };
+            Against {
+                evaluate,
+                against,
+                dna: against_dna,
+                mix: senders.map_or(SenderMix::Alternate, SenderMix::First),
+            },
+        )?;
+    }
+
+    let evaluation = mode.use_cca(input_path, evaluate)?;
 
     println!(
         "Achieved expected utility {} with {}",
-        score, flow_properties
+        evaluation.utility, evaluation.flow_properties
     );
+    println!("Fairness between flows: {}", evaluation.fairness);
+
+    if let Some(output) = output {
+        let report = EvaluationReport::new(evaluation, &mut rng.create_child());
+        let file = BufWriter::new(File::create(output)?);
+        match output.extension().and_then(|x| x.to_str()) {
+            Some("json") => serde_json::to_writer_pretty(file, &report)?,
+            Some("csv") => write_csv(file, &report)?,
+            _ => return Err(anyhow!("Output file must end in .json or .csv")),
+        }
+    }
+
+    Ok(())
+}
 
+/// Writes a row for each network, followed by rows for the mean and the bounds of the confidence
+/// interval, with the parameters of each network as a JSON string.
+fn write_csv(mut file: impl Write, report: &EvaluationReport<DefaultNetworkBuilder>) -> Result<()> {
+    writeln!(file, "network,utility,throughput,rtt,jain_index,parameters")?;
+    let optional = |x: Option<Float>| x.map_or_else(String::new, |x| x.to_string());
+    for (i, network) in report.networks.iter().enumerate() {
+        writeln!(
+            file,
+            "{i},{},{},{},{},\"{}\"",
+            network.utility,
+            network.throughput,
+            optional(network.rtt),
+            network.jain_index,
+            serde_json::to_string(&network.network)?.replace('"', "\"\"")
+        )?;
+    }
+    let estimates = [
+        Some(report.utility),
+        Some(report.throughput),
+        report.rtt,
+        Some(report.jain_index),
+    ];
+    for (name, value) in [
+        ("mean", (|e: Estimate| e.mean) as fn(Estimate) -> Float),
+        ("ci_low", |e| e.ci_low),
+        ("ci_high", |e| e.ci_high),
+    ] {
+        let values = estimates.map(|e| optional(e.map(value)));
+        writeln!(file, "{name},{},", values.join(","))?;
+    }
     Ok(())
 }
@@ -48,6 +48,7 @@ fn inspect_rule_tree(rule_tree: &(impl RemyPolicy + Sync)) -> PolicySummary {
             rtt_ratio,
             ack_ewma: send_ewma,
             send_ewma,
+            mark_ewma: 0.,
         })
         .collect_vec();
     points
@@ -75,7 +76,7 @@ fn inspect_rule_tree(rule_tree: &(impl RemyPolicy + Sync)) -> PolicySummary {
 }
 
 fn inspect_remy(dna: &Path) -> String {
-    let dna = RemyDna::load(dna).unwrap();
+    let dna = <RemyDna as Config<Custom>>::load(dna).unwrap();
     serde_json::to_string(&RemyInspection {
         maximum_depth: dna.0.num_parents() as u64,
         policy: inspect_rule_tree(&dna),
@@ -1,17 +1,31 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::{Parser, Subcommand, ValueEnum};
+use flowforge::{
+    ccas::{
+        bbr::BbrCcaTemplate, cubic::CubicCcaTemplate, new_reno::NewRenoCcaTemplate,
+        vegas::VegasCcaTemplate,
+    },
+    trainers::{delay_multiplier::DelayMultiplierTrainer, remy::RemyTrainer, remyr::RemyrTrainer},
+    Cca, CcaTemplate, Config, Trainer,
+};
 
+use compare::{compare, parse_compared_cca, ComparedCca};
 use create_configs::create_all_configs;
 use evaluate::evaluate;
 use inspect::inspect;
+use replay::replay;
+use sweep::sweep;
 use trace::trace;
 use train::train;
 
+mod compare;
 mod create_configs;
 mod evaluate;
 mod inspect;
+mod replay;
+mod sweep;
 mod trace;
 mod train;
 
@@ -53,6 +67,14 @@ enum Command {
         #[arg(long)]
         progress: Option<PathBuf>,
 
+        /// OPTIONAL File (JSON) to save a checkpoint to whenever training can be resumed
+        #[arg(long)]
+        checkpoint: Option<PathBuf>,
+
+        /// OPTIONAL, REQUIRES CHECKPOINT Continue training from the checkpoint
+        #[arg(long)]
+        resume: bool,
+
         /// OPTIONAL Force overwrite the DNA file if it exists
         #[arg(short, long)]
         force: bool,
@@ -83,9 +105,91 @@ enum Command {
         #[arg(long)]
         util: PathBuf,
 
-        /// File to read congestion control algorithm DNA from
+        /// IF LEARNED MODE File to read congestion control algorithm DNA from
         #[arg(short, long)]
-        dna: PathBuf,
+        dna: Option<PathBuf>,
+
+        /// OPTIONAL Flow mode to compete against in the same networks
+        #[arg(long)]
+        against: Option<FlowAdders>,
+
+        /// IF AGAINST IS LEARNED MODE File to read the competing DNA from
+        #[arg(long, requires = "against")]
+        against_dna: Option<PathBuf>,
+
+        /// OPTIONAL, REQUIRES AGAINST Number of senders in each network to use MODE, with the rest
+        /// using AGAINST (alternates between the two if not given)
+        #[arg(long, requires = "against")]
+        senders: Option<u32>,
+
+        /// OPTIONAL File to write a report of the results of each network to, with confidence
+        /// intervals (JSON or CSV, depending on the extension)
+        #[arg(short, long, conflicts_with = "against")]
+        output: Option<PathBuf>,
+
+        /// OPTIONAL Seed for evaluation RNG
+        #[arg(long, default_value_t = 534522)]
+        eval_seed: u64,
+    },
+    /// Evaluate several congestion control algorithms on exactly the same networks, and compare
+    /// each with the first
+    Compare {
+        /// Evaluation config file (JSON)
+        #[arg(short, long)]
+        config: PathBuf,
+
+        /// Network config file (JSON)
+        #[arg(long)]
+        net: PathBuf,
+
+        /// Utility function config file (JSON)
+        #[arg(long)]
+        util: PathBuf,
+
+        /// Flow mode to compare, followed by =DNA_FILE for learned modes (e.g. remy=dna.json).
+        /// Repeat for each mode, starting with the baseline
+        #[arg(long = "cca", required = true, value_parser = parse_compared_cca)]
+        ccas: Vec<ComparedCca>,
+
+        /// OPTIONAL File to write the per-network differences to (JSON)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// OPTIONAL Seed for evaluation RNG
+        #[arg(long, default_value_t = 534522)]
+        eval_seed: u64,
+    },
+    /// Evaluate a congestion control algorithm at each point of a grid over the parameters of a
+    /// Remy network
+    Sweep {
+        /// Evaluation config file (JSON)
+        #[arg(short, long)]
+        config: PathBuf,
+
+        /// Flow mode
+        #[arg(long)]
+        mode: FlowAdders,
+
+        /// Remy network config file (JSON), giving the parameters that are not swept
+        #[arg(long)]
+        net: PathBuf,
+
+        /// Sweep config file (JSON), giving the values of each swept parameter
+        #[arg(long)]
+        sweep: PathBuf,
+
+        /// Utility function config file (JSON)
+        #[arg(long)]
+        util: PathBuf,
+
+        /// IF LEARNED MODE File to read congestion control algorithm DNA from
+        #[arg(short, long)]
+        dna: Option<PathBuf>,
+
+        /// File to write the results of each point to, with confidence intervals (JSON or CSV,
+        /// depending on the extension)
+        #[arg(short, long)]
+        output: PathBuf,
 
         /// OPTIONAL Seed for evaluation RNG
         #[arg(long, default_value_t = 534522)]
@@ -105,9 +209,9 @@ enum Command {
         #[arg(long)]
         utility: PathBuf,
 
-        /// File to read congestion control algorithm DNA from
+        /// IF LEARNED MODE File to read congestion control algorithm DNA from
         #[arg(short, long)]
-        input: PathBuf,
+        input: Option<PathBuf>,
 
         /// OPTIONAL File to output trace to (JSON)
         #[arg(short, long)]
@@ -117,6 +221,37 @@ enum Command {
         #[arg(long, default_value_t = 534522)]
         seed: u64,
     },
+    /// Record every message delivered while simulating a single network, or check that a new
+    /// run delivers exactly the same messages as a recording
+    Replay {
+        /// Flow mode
+        #[arg(long)]
+        mode: FlowAdders,
+
+        /// Network config file (JSON)
+        #[arg(long)]
+        net: PathBuf,
+
+        /// IF LEARNED MODE File to read congestion control algorithm DNA from
+        #[arg(short, long)]
+        dna: Option<PathBuf>,
+
+        /// File to save the recording to
+        #[arg(long, required_unless_present = "verify")]
+        record: Option<PathBuf>,
+
+        /// File to read a previous recording from, to compare against
+        #[arg(long)]
+        verify: Option<PathBuf>,
+
+        /// OPTIONAL Number of seconds to simulate
+        #[arg(long, default_value_t = 60.)]
+        seconds: f64,
+
+        /// OPTIONAL Random seed
+        #[arg(long, default_value_t = 534522)]
+        seed: u64,
+    },
     Inspect {
         /// Flow mode
         #[arg(long)]
@@ -137,6 +272,47 @@ pub enum FlowAdders {
     Remy,
     Remyr,
     DelayMultiplier,
+    NewReno,
+    Cubic,
+    Vegas,
+    Bbr,
+}
+
+/// Something to do with the CCA of a flow mode, which has a different type for each mode.
+pub trait UseCca {
+    type Output;
+
+    fn call<C: Cca>(self, new_cca: impl Fn() -> C + Sync) -> Self::Output;
+}
+
+impl FlowAdders {
+    /// Returns the DNA file, which is required for every mode except the baseline CCAs.
+    fn dna<'a>(&self, path: Option<&'a Path>) -> Result<&'a Path> {
+        path.ok_or_else(|| anyhow!("A DNA file is required for {:?} mode", self))
+    }
+
+    /// Calls `f` with this mode's CCA, loading the DNA from `dna` if it is a learned mode.
+    pub fn use_cca<F: UseCca>(&self, dna: Option<&Path>, f: F) -> Result<F::Output> {
+        fn learned<T: Trainer, F: UseCca>(dna: &Path, f: F) -> Result<F::Output> {
+            let dna = T::Dna::load(dna)?;
+            let cca_template = T::CcaTemplate::default();
+            Ok(f.call(cca_template.with(&dna)))
+        }
+
+        fn baseline<T: CcaTemplate<'static, Policy = ()>, F: UseCca>(f: F) -> Result<F::Output> {
+            Ok(f.call(T::default().with(())))
+        }
+
+        match self {
+            FlowAdders::Remy => learned::<RemyTrainer, _>(self.dna(dna)?, f),
+            FlowAdders::Remyr => learned::<RemyrTrainer, _>(self.dna(dna)?, f),
+            FlowAdders::DelayMultiplier => learned::<DelayMultiplierTrainer, _>(self.dna(dna)?, f),
+            FlowAdders::NewReno => baseline::<NewRenoCcaTemplate, _>(f),
+            FlowAdders::Cubic => baseline::<CubicCcaTemplate, _>(f),
+            FlowAdders::Vegas => baseline::<VegasCcaTemplate, _>(f),
+            FlowAdders::Bbr => baseline::<BbrCcaTemplate, _>(f),
+        }
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -167,6 +343,8 @@ fn main() -> Result<()> {
             util,
             dna,
             progress,
+            checkpoint,
+            resume,
             eval_times,
             force,
             training_seed,
@@ -179,6 +357,8 @@ fn main() -> Result<()> {
             eval_times,
             eval.as_deref(),
             progress.as_deref(),
+            checkpoint.as_deref(),
+            resume,
             force,
             training_seed,
             eval_seed,
@@ -189,8 +369,50 @@ fn main() -> Result<()> {
             util,
             dna,
             mode,
+            against,
+            against_dna,
+            senders,
+            output,
             eval_seed,
-        } => evaluate(&mode, &config, &net, &util, &dna, eval_seed),
+        } => evaluate(
+            &mode,
+            &config,
+            &net,
+            &util,
+            dna.as_deref(),
+            against
+                .as_ref()
+                .map(|against| (against, against_dna.as_deref(), senders)),
+            output.as_deref(),
+            eval_seed,
+        ),
+        Command::Compare {
+            config,
+            net,
+            util,
+            ccas,
+            output,
+            eval_seed,
+        } => compare(&config, &net, &util, &ccas, output.as_deref(), eval_seed),
+        Command::Sweep {
+            config,
+            mode,
+            net,
+            sweep: sweep_config,
+            util,
+            dna,
+            output,
+            eval_seed,
+        } => sweep(
+            &mode,
+            &config,
+            &net,
+            &sweep_config,
+            &util,
+            dna.as_deref(),
+            &output,
+            eval_seed,
+        ),
         Command::Trace {
             mode,
             network,
@@ -198,7 +420,31 @@ fn main() -> Result<()> {
             input,
             output,
             seed,
-        } => trace(&mode, &network, &utility, &input, output.as_deref(), seed),
+        } => trace(
+            &mode,
+            &network,
+            &utility,
+            input.as_deref(),
+            output.as_deref(),
+            seed,
+        ),
+        Command::Replay {
+            mode,
+            net,
+            dna,
+            record,
+            verify,
+            seconds,
+            seed,
+        } => replay(
+            &mode,
+            &net,
+            dna.as_deref(),
+            record.as_deref(),
+            verify.as_deref(),
+            seconds,
+            seed,
+        ),
         Command::Inspect { mode, dna, output } => {
             inspect(&dna, &mode, output.as_deref());
             Ok(())
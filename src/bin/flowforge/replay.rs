@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use flowforge::{
+    networks::DefaultNetworkConfig,
+    quantities::{seconds, Float},
+    trainers::DefaultEffect,
+    util::{
+        rand::Rng,
+        replay::{record, ReplayTrace},
+    },
+    Cca, Config,
+};
+
+use crate::{FlowAdders, UseCca};
+
+struct Record<'a> {
+    network_config: &'a DefaultNetworkConfig,
+    run_for: Float,
+    rng: &'a mut Rng,
+}
+
+impl UseCca for Record<'_> {
+    type Output = ReplayTrace;
+
+    fn call<C: Cca>(self, new_cca: impl Fn() -> C + Sync) -> Self::Output {
+        let network = self.rng.sample(self.network_config);
+        record::<DefaultEffect, _>(&network, new_cca, self.rng, seconds(self.run_for))
+    }
+}
+
+/// Simulates a network sampled with `seed`, and either saves every message delivered to
+/// `record_path`, or checks that they are identical to those saved in `verify_path`.
+pub fn replay(
+    mode: &FlowAdders,
+    network_config: &Path,
+    input_path: Option<&Path>,
+    record_path: Option<&Path>,
+    verify_path: Option<&Path>,
+    run_for: Float,
+    seed: u64,
+) -> Result<()> {
+    let mut rng = Rng::from_seed(seed);
+    let network_config = DefaultNetworkConfig::load(network_config)?;
+    let trace = mode.use_cca(
+        input_path,
+        Record {
+            network_config: &network_config,
+            run_for,
+            rng: &mut rng,
+        },
+    )?;
+
+    if let Some(record_path) = record_path {
+        trace.save(record_path)?;
+        println!(
+            "Recorded {} deliveries with digest {:016x}",
+            trace.len(),
+            trace.digest()
+        );
+    }
+    if let Some(verify_path) = verify_path {
+        let expected = ReplayTrace::load(verify_path)?;
+        if let Some(divergence) = expected.first_divergence(&trace) {
+            return Err(anyhow!("{divergence}"));
+        }
+        println!(
+            "Verified {} deliveries with digest {:016x}",
+            trace.len(),
+            trace.digest()
+        );
+    }
+    Ok(())
+}
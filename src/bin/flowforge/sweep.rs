@@ -0,0 +1,143 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, Result};
+use flowforge::{
+    eval::{EvaluationConfig, SweepPoint},
+    flow::{NoActiveFlows, UtilityConfig},
+    networks::{
+        remy::{RemyNetworkDistribution, RemyNetworkSweep},
+        DefaultNetworkConfig,
+    },
+    quantities::Float,
+    trainers::DefaultEffect,
+    util::{rand::Rng, stats::Estimate},
+    Cca, Config,
+};
+
+use crate::{FlowAdders, UseCca};
+
+struct Sweep<'a> {
+    evaluation_config: &'a EvaluationConfig,
+    network_config: &'a RemyNetworkDistribution,
+    sweep: &'a RemyNetworkSweep,
+    utility_config: &'a UtilityConfig,
+    rng: &'a mut Rng,
+}
+
+impl UseCca for Sweep<'_> {
+    type Output = Result<Vec<SweepPoint>, NoActiveFlows>;
+
+    fn call<C: Cca>(self, new_cca: impl Fn() -> C + Sync) -> Self::Output {
+        self.evaluation_config.sweep::<_, DefaultEffect>(
+            new_cca,
+            self.network_config,
+            self.sweep,
+            self.utility_config,
+            self.rng,
+        )
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn sweep(
+    mode: &FlowAdders,
+    evaluation_config: &Path,
+    network_config: &Path,
+    sweep_config: &Path,
+    utility_config: &Path,
+    input_path: Option<&Path>,
+    output: &Path,
+    eval_seed: u64,
+) -> Result<()> {
+    let mut rng = Rng::from_seed(eval_seed);
+    let evaluation_config = EvaluationConfig::load(evaluation_config)?;
+    let DefaultNetworkConfig::Remy(network_config) = DefaultNetworkConfig::load(network_config)?
+    else {
+        return Err(anyhow!("Sweeps are only supported for Remy networks"));
+    };
+    let sweep_config = RemyNetworkSweep::load(sweep_config)?;
+    if sweep_config.dimensions.is_empty() {
+        return Err(anyhow!("A sweep needs at least one dimension"));
+    }
+    let utility_config = UtilityConfig::load(utility_config)?;
+    let file = BufWriter::new(File::create(output)?);
+
+    let points = mode
+        .use_cca(
+            input_path,
+            Sweep {
+                evaluation_config: &evaluation_config,
+                network_config: &network_config,
+                sweep: &sweep_config,
+                utility_config: &utility_config,
+                rng: &mut rng,
+            },
+        )?
+        .map_err(|_| anyhow!("Expected active flows!"))?;
+
+    for point in &points {
+        let values = sweep_config
+            .dimensions
+            .iter()
+            .zip(&point.values)
+            .map(|(dimension, value)| format!("{} = {value}", dimension.name()))
+            .collect::<Vec<_>>();
+        println!(
+            "{}: expected utility {} ({} to {})",
+            values.join(", "),
+            point.utility.mean,
+            point.utility.ci_low,
+            point.utility.ci_high
+        );
+    }
+
+    match output.extension().and_then(|x| x.to_str()) {
+        Some("json") => serde_json::to_writer_pretty(file, &points)?,
+        Some("csv") => write_csv(file, &sweep_config, &points)?,
+        _ => return Err(anyhow!("Output file must end in .json or .csv")),
+    }
+    Ok(())
+}
+
+/// Writes a row for each point of the sweep, with the mean of each metric followed by the bounds
+/// of its confidence interval.
+fn write_csv(mut file: impl Write, sweep: &RemyNetworkSweep, points: &[SweepPoint]) -> Result<()> {
+    let metrics = ["utility", "throughput", "rtt", "jain_index"];
+    let header = sweep
+        .dimensions
+        .iter()
+        .map(|dimension| dimension.name().to_string())
+        .chain(metrics.iter().flat_map(|metric| {
+            ["_mean", "_ci_low", "_ci_high"].map(|suffix| format!("{metric}{suffix}"))
+        }))
+        .collect::<Vec<_>>();
+    writeln!(file, "{}", header.join(","))?;
+    let optional = |x: Option<Float>| x.map_or_else(String::new, |x| x.to_string());
+    for point in points {
+        let estimates = [
+            Some(point.utility),
+            Some(point.throughput),
+            point.rtt,
+            Some(point.jain_index),
+        ];
+        let row = point
+            .values
+            .iter()
+            .map(ToString::to_string)
+            .chain(estimates.iter().flat_map(|estimate| {
+                [
+                    (|e: Estimate| e.mean) as fn(Estimate) -> Float,
+                    |e| e.ci_low,
+                    |e| e.ci_high,
+                ]
+                .map(|value| optional(estimate.map(value)))
+            }))
+            .collect::<Vec<_>>();
+        writeln!(file, "{}", row.join(","))?;
+    }
+    Ok(())
+}
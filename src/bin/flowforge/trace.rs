@@ -3,22 +3,19 @@ use append_only_vec::AppendOnlyVec;
 use flowforge::{
     components::ticker::Ticker,
     flow::{UtilityConfig, UtilityFunction},
-    networks::DefaultNetworkConfig,
+    networks::{DefaultNetworkBuilder, DefaultNetworkConfig},
     quantities::{milliseconds, seconds, Float, InformationRate, Time, TimeSpan},
     simulation::SimulatorBuilder,
-    trainers::{
-        delay_multiplier::DelayMultiplierTrainer, remy::RemyTrainer, remyr::RemyrTrainer,
-        DefaultEffect,
-    },
+    trainers::DefaultEffect,
     util::{logging::NothingLogger, meters::CurrentFlowMeter, rand::Rng},
-    CcaTemplate, Config, Network, NetworkDistribution, Trainer,
+    Cca, Config, Network, NetworkDistribution,
 };
 use generativity::make_guard;
 use itertools::Itertools;
 use serde::Serialize;
 use std::{cell::RefCell, fs::File, path::Path};
 
-use crate::FlowAdders;
+use crate::{FlowAdders, UseCca};
 
 #[derive(Serialize, Default, Clone)]
 struct FlowTrace {
@@ -45,17 +42,16 @@ struct TraceResult<N> {
     flows: Vec<FlowTrace>,
 }
 
-fn _trace<T, N>(
+fn trace_cca<C, N>(
     network_config: &impl NetworkDistribution<DefaultEffect<'static>, Network = N>,
     utility_config: &UtilityConfig,
-    input_path: &Path,
+    cca_gen: impl Fn() -> C + Sync,
     rng: &mut Rng,
 ) -> TraceResult<N>
 where
+    C: Cca,
     N: Network<DefaultEffect<'static>>,
-    T: Trainer,
 {
-    let dna = T::Dna::load(input_path).unwrap();
     let n = rng.sample(network_config);
     let mut active_senders = Vec::new();
     let mut timestamps = Vec::new();
@@ -63,8 +59,6 @@ where
     let result_flows = RefCell::new(Vec::<FlowTrace>::new());
     make_guard!(guard);
     let flows = AppendOnlyVec::<RefCell<CurrentFlowMeter>>::new();
-    let cca_template = T::CcaTemplate::default();
-    let cca_gen = cca_template.with(&dna);
     let builder = SimulatorBuilder::new(guard);
     builder.insert(Ticker::new(milliseconds(1.), |time| {
         timestamps.push((time - Time::SIM_START).seconds());
@@ -106,7 +100,7 @@ where
         result_flows.borrow_mut().push(FlowTrace::default());
         &flows[index]
     };
-    n.populate_sim(&builder, &cca_gen, rng, new_flow);
+    n.populate_sim(&builder, || &cca_gen, rng, new_flow);
     let mut sim = builder.build(NothingLogger).unwrap();
     while sim.time() < Time::from_sim_start(seconds(100.)) && sim.tick() {}
     drop(sim);
@@ -119,11 +113,25 @@ where
     }
 }
 
+struct Trace<'a> {
+    network_config: &'a DefaultNetworkConfig,
+    utility_config: &'a UtilityConfig,
+    rng: &'a mut Rng,
+}
+
+impl UseCca for Trace<'_> {
+    type Output = TraceResult<DefaultNetworkBuilder>;
+
+    fn call<C: Cca>(self, new_cca: impl Fn() -> C + Sync) -> Self::Output {
+        trace_cca(self.network_config, self.utility_config, new_cca, self.rng)
+    }
+}
+
 pub fn trace(
     mode: &FlowAdders,
     network_config: &Path,
     utility_config: &Path,
-    input_path: &Path,
+    input_path: Option<&Path>,
     output_path: Option<&Path>,
     seed: u64,
 ) -> Result<()> {
@@ -131,20 +139,14 @@ pub fn trace(
     let network_config = DefaultNetworkConfig::load(network_config)?;
     let utility_config = UtilityConfig::load(utility_config)?;
 
-    let result = match mode {
-        FlowAdders::Remy => {
-            _trace::<RemyTrainer, _>(&network_config, &utility_config, input_path, &mut rng)
-        }
-        FlowAdders::DelayMultiplier => _trace::<DelayMultiplierTrainer, _>(
-            &network_config,
-            &utility_config,
-            input_path,
-            &mut rng,
-        ),
-        FlowAdders::Remyr => {
-            _trace::<RemyrTrainer, _>(&network_config, &utility_config, input_path, &mut rng)
-        }
-    };
+    let result = mode.use_cca(
+        input_path,
+        Trace {
+            network_config: &network_config,
+            utility_config: &utility_config,
+            rng: &mut rng,
+        },
+    )?;
 
     if let Some(output_path) = output_path {
         let file = File::create(output_path).unwrap();
@@ -1,50 +1,118 @@
 use std::{
-    fs::File,
-    io::{self, Seek, Write},
+    fs::{self, File},
+    io::{self, BufWriter, Seek, Write},
     path::Path,
     time::{Duration, Instant},
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use flowforge::{
     eval::EvaluationConfig,
     flow::{FlowProperties, UtilityConfig},
     networks::DefaultNetworkConfig,
     quantities::Float,
     trainers::{
-        delay_multiplier::DelayMultiplierTrainer, remy::RemyTrainer, remyr::RemyrTrainer,
+        delay_multiplier::DelayMultiplierTrainer, remy::RemyTrainer,
+        remy_genetic::RemyGeneticTrainer, remyr::RemyrTrainer, remyr_es::RemyrEsTrainer,
         DefaultEffect, TrainerConfig,
     },
     util::rand::Rng,
-    CcaTemplate, Config, NetworkDistribution, Trainer,
+    CcaTemplate, Config, NetworkDistribution, ProgressHandler, Trainer,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Serialize)]
-struct TrainResult<'a, T, N> {
+/// Evaluations of the DNA at points during training.
+#[derive(Serialize, Deserialize, Default)]
+struct TrainHistory {
     timestamps: Vec<Float>,
     bandwidth: Vec<Float>,
     rtt: Vec<Float>,
     utility: Vec<Float>,
+}
+
+#[derive(Serialize)]
+struct TrainResult<'a, T, N> {
+    #[serde(flatten)]
+    history: &'a TrainHistory,
     trainer_config: &'a T,
     network_config: &'a N,
     utility_config: &'a UtilityConfig,
 }
 
-impl<'a, T, N> TrainResult<'a, T, N> {
-    pub fn new(
-        trainer_config: &'a T,
-        network_config: &'a N,
-        utility_config: &'a UtilityConfig,
-    ) -> TrainResult<'a, T, N> {
-        TrainResult {
-            timestamps: Vec::new(),
-            bandwidth: Vec::new(),
-            rtt: Vec::new(),
-            utility: Vec::new(),
-            trainer_config,
-            network_config,
-            utility_config,
+/// Progress through training, which is saved with each checkpoint so that resumed training
+/// carries on from it rather than starting the history and timings again.
+#[derive(Serialize, Deserialize)]
+struct TrainingState {
+    history: TrainHistory,
+    /// Time spent training, excluding evaluations and saving checkpoints.
+    training_time: Duration,
+    #[serde(skip, default = "Instant::now")]
+    last_resumed: Instant,
+    last_percent: i32,
+    best_score: Float,
+}
+
+impl TrainingState {
+    fn new() -> TrainingState {
+        TrainingState {
+            history: TrainHistory::default(),
+            training_time: Duration::ZERO,
+            last_resumed: Instant::now(),
+            last_percent: -1,
+            best_score: Float::MIN,
+        }
+    }
+
+    fn pause(&mut self) {
+        self.training_time += self.last_resumed.elapsed();
+    }
+
+    fn resume(&mut self) {
+        self.last_resumed = Instant::now();
+    }
+}
+
+/// A trainer's checkpoint, along with the configs training was started with, which must be the
+/// same to resume from it.
+#[derive(Serialize, Deserialize)]
+struct SavedCheckpoint<S, C> {
+    configs: serde_json::Value,
+    state: S,
+    checkpoint: C,
+}
+
+/// Passes progress updates to `update`, and saves every checkpoint to `checkpoint_path`.
+struct TrainingProgress<'a, F> {
+    update: F,
+    state: TrainingState,
+    checkpoint_path: Option<&'a Path>,
+    configs: &'a serde_json::Value,
+}
+
+impl<P, C, F> ProgressHandler<P, C> for TrainingProgress<'_, F>
+where
+    C: Serialize,
+    F: FnMut(Float, &P, &mut TrainingState) + Send,
+{
+    fn update_progress(&mut self, frac_complete: Float, current: &P) {
+        (self.update)(frac_complete, current, &mut self.state);
+    }
+
+    /// Writes to a temporary file which then replaces the previous checkpoint, so that a crash
+    /// while saving can't leave a partial checkpoint behind.
+    fn save_checkpoint(&mut self, checkpoint: &C) {
+        if let Some(checkpoint_path) = self.checkpoint_path {
+            self.state.pause();
+            let temp_path = checkpoint_path.with_extension("json.tmp");
+            let saved = SavedCheckpoint {
+                configs: self.configs.clone(),
+                state: &self.state,
+                checkpoint,
+            };
+            serde_json::to_writer(BufWriter::new(File::create(&temp_path).unwrap()), &saved)
+                .unwrap();
+            fs::rename(temp_path, checkpoint_path).unwrap();
+            self.state.resume();
         }
     }
 }
@@ -56,14 +124,39 @@ pub fn _train<T>(
     network_config: &(impl NetworkDistribution<DefaultEffect<'static>> + Serialize),
     utility_config: &UtilityConfig,
     dna_path: &Path,
+    checkpoint_path: Option<&Path>,
+    resume: bool,
     training_rng: &mut Rng,
     eval_rng: &mut Rng,
     force: bool,
-) where
+) -> Result<()>
+where
     T: Trainer + Serialize + Sync,
 {
     assert!(T::Dna::valid_path(dna_path));
-    if dna_path.exists() && !force {
+    if let Some(checkpoint_path) = checkpoint_path {
+        assert!(SavedCheckpoint::<TrainingState, T::Checkpoint>::valid_path(
+            checkpoint_path
+        ));
+    }
+    let configs = serde_json::json!({
+        "trainer": trainer,
+        "network": network_config,
+        "utility": utility_config,
+    });
+    let (state, checkpoint) = if resume {
+        let saved =
+            SavedCheckpoint::<TrainingState, T::Checkpoint>::load(checkpoint_path.unwrap())?;
+        if saved.configs != configs {
+            return Err(anyhow!(
+                "The checkpoint was saved while training with different configs!"
+            ));
+        }
+        (saved.state, Some(saved.checkpoint))
+    } else {
+        (TrainingState::new(), None)
+    };
+    if dna_path.exists() && !force && !resume {
         loop {
             let mut buf = String::new();
             println!("There is already DNA in the output path. Are you sure you want to overwrite it? y/n");
@@ -71,75 +164,86 @@ pub fn _train<T>(
             if buf.to_lowercase().trim() == "y" {
                 break;
             } else if buf.to_lowercase().trim() == "n" {
-                return;
+                return Ok(());
             }
         }
     }
+    let write_result = |output_file: &mut File, history: &TrainHistory| {
+        let result = TrainResult {
+            history,
+            trainer_config: trainer,
+            network_config,
+            utility_config,
+        };
+        output_file.rewind().unwrap();
+        serde_json::to_writer(output_file, &result).unwrap();
+    };
     let mut output_file = evaluation_config
         .as_ref()
         .and_then(|x| x.2)
         .map(|x| File::create(x).unwrap());
-    let mut result = TrainResult::new(trainer, network_config, utility_config);
-
-    let mut last_resumed = Instant::now();
-    let mut total_training_time = Duration::ZERO;
+    if let (Some(output_file), true) = (&mut output_file, resume) {
+        write_result(output_file, &state.history);
+    }
 
     let new_eval_rng = eval_rng.identical_child_factory();
-    let mut last_percent = -1;
-    let mut best_score: Float = Float::MIN;
     trainer
         .train(
             network_config,
             utility_config,
-            &mut |frac: Float, dna: &T::Dna| {
-                println!("{frac}");
-                if let Some((eval_times, evaluation_config, _)) = evaluation_config.as_ref() {
-                    let percent_completed = (frac * *eval_times as f64).floor() as i32;
-                    if percent_completed <= last_percent {
-                        return;
-                    }
-                    last_percent = percent_completed;
-
-                    let now = Instant::now();
-                    total_training_time += now - last_resumed;
-
-                    print!("Evaluating... ");
-                    io::stdout().flush().unwrap();
-                    let (utility, props) = evaluation_config
-                        .evaluate::<_, DefaultEffect, _>(
-                            &T::CcaTemplate::default().with(dna),
-                            network_config,
-                            utility_config,
-                            &mut new_eval_rng(),
-                        )
-                        .expect("Simulation to have active flows");
-                    let FlowProperties {
-                        throughput: average_throughput,
-                        rtt: average_rtt,
-                    } = props.clone();
-                    if let Some(output_file) = &mut output_file {
-                        result.timestamps.push(total_training_time.as_secs_f64());
-                        result.bandwidth.push(average_throughput.bits_per_second());
-                        result.rtt.push(average_rtt.unwrap().seconds());
-                        result.utility.push(utility);
-                        output_file.rewind().unwrap();
-                        serde_json::to_writer(output_file, &result).unwrap();
-                    }
-                    dna.save(dna_path).unwrap();
-                    if utility >= best_score {
-                        best_score = utility;
-                        println!("Achieved eval score {utility:.2} with {props}. Best so far.");
-                    } else {
-                        println!("Achieved eval score {utility:.2} with {props}.");
-                    }
+            &mut TrainingProgress {
+                update: |frac: Float, dna: &T::Dna, state: &mut TrainingState| {
+                    println!("{frac}");
+                    if let Some((eval_times, evaluation_config, _)) = evaluation_config.as_ref() {
+                        let percent_completed = (frac * *eval_times as f64).floor() as i32;
+                        if percent_completed <= state.last_percent {
+                            return;
+                        }
+                        state.last_percent = percent_completed;
+                        state.pause();
 
-                    last_resumed = Instant::now();
-                }
+                        print!("Evaluating... ");
+                        io::stdout().flush().unwrap();
+                        let (utility, props) = evaluation_config
+                            .evaluate::<_, DefaultEffect, _>(
+                                &T::CcaTemplate::default().with(dna),
+                                network_config,
+                                utility_config,
+                                &mut new_eval_rng(),
+                            )
+                            .expect("Simulation to have active flows");
+                        let FlowProperties {
+                            throughput: average_throughput,
+                            rtt: average_rtt,
+                            ..
+                        } = props.clone();
+                        if let Some(output_file) = &mut output_file {
+                            let history = &mut state.history;
+                            history.timestamps.push(state.training_time.as_secs_f64());
+                            history.bandwidth.push(average_throughput.bits_per_second());
+                            history.rtt.push(average_rtt.unwrap().seconds());
+                            history.utility.push(utility);
+                            write_result(output_file, history);
+                        }
+                        dna.save(dna_path).unwrap();
+                        if utility >= state.best_score {
+                            state.best_score = utility;
+                            println!("Achieved eval score {utility:.2} with {props}. Best so far.");
+                        } else {
+                            println!("Achieved eval score {utility:.2} with {props}.");
+                        }
+
+                        state.resume();
+                    }
+                },
+                state,
+                checkpoint_path,
+                configs: &configs,
             },
+            checkpoint,
             training_rng,
         )
         .save(dna_path)
-        .unwrap();
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -151,6 +255,8 @@ pub fn train(
     eval_times: Option<u32>,
     evaluation_config: Option<&Path>,
     output_path: Option<&Path>,
+    checkpoint_path: Option<&Path>,
+    resume: bool,
     force: bool,
     training_seed: u64,
     eval_seed: u64,
@@ -158,6 +264,9 @@ pub fn train(
     if output_path.is_some() {
         assert!(evaluation_config.is_some());
     }
+    if resume {
+        assert!(checkpoint_path.is_some());
+    }
     if evaluation_config.is_some() {
         assert!(eval_times.is_some());
     }
@@ -181,6 +290,8 @@ pub fn train(
             &network_config,
             &utility_config,
             dna_path,
+            checkpoint_path,
+            resume,
             &mut training_rng,
             &mut eval_rng,
             force,
@@ -191,6 +302,8 @@ pub fn train(
             &network_config,
             &utility_config,
             dna_path,
+            checkpoint_path,
+            resume,
             &mut training_rng,
             &mut eval_rng,
             force,
@@ -201,11 +314,35 @@ pub fn train(
             &network_config,
             &utility_config,
             dna_path,
+            checkpoint_path,
+            resume,
             &mut training_rng,
             &mut eval_rng,
             force,
         ),
-    };
-
-    Ok(())
+        TrainerConfig::RemyGenetic(cfg) => _train::<RemyGeneticTrainer>(
+            &cfg,
+            evaluation_config,
+            &network_config,
+            &utility_config,
+            dna_path,
+            checkpoint_path,
+            resume,
+            &mut training_rng,
+            &mut eval_rng,
+            force,
+        ),
+        TrainerConfig::RemyrEs(cfg) => _train::<RemyrEsTrainer>(
+            &cfg,
+            evaluation_config,
+            &network_config,
+            &utility_config,
+            dna_path,
+            checkpoint_path,
+            resume,
+            &mut training_rng,
+            &mut eval_rng,
+            force,
+        ),
+    }
 }
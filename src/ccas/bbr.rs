@@ -0,0 +1,316 @@
+use std::collections::VecDeque;
+
+use crate::{
+    quantities::{latest, seconds, Float, Time, TimeSpan},
+    util::{logging::Logger, rand::Rng},
+    AckReceived, Cca, CcaTemplate, PacketSent,
+};
+
+const STARTUP_GAIN: Float = 2.885;
+const PROBE_BW_CWND_GAIN: Float = 2.;
+const PROBE_BW_PACING_GAINS: [Float; 8] = [1.25, 0.75, 1., 1., 1., 1., 1., 1.];
+const BANDWIDTH_WINDOW_ROUNDS: u64 = 10;
+const MIN_RTT_WINDOW: TimeSpan = seconds(10.);
+const PROBE_RTT_DURATION: TimeSpan = seconds(0.2);
+const MIN_CWND: Float = 4.;
+
+#[derive(Debug, Clone)]
+enum Mode {
+    Startup,
+    Drain,
+    ProbeBw {
+        phase: usize,
+        phase_start: Time,
+    },
+    /// Stays in `ProbeRtt` until `done`, which is set once the flight size has dropped.
+    ProbeRtt {
+        done: Option<Time>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct SentPacket {
+    sent_time: Time,
+    delivered: u64,
+    delivered_time: Time,
+    /// When the most recently delivered packet was sent.
+    first_sent_time: Time,
+}
+
+/// A simplified version of BBR (v1), which paces packets at its estimate of the bottleneck
+/// bandwidth and caps the flight size at a multiple of the bandwidth-delay product. Losses are
+/// ignored.
+///
+/// ACKs are matched to packets by their sent time, and any earlier packets that are still
+/// unacknowledged are assumed to have left the network.
+#[derive(Debug)]
+pub struct BbrCca {
+    initial_cwnd: u32,
+    mode: Mode,
+    filled_pipe: bool,
+    full_bandwidth: Float,
+    full_bandwidth_rounds: u32,
+    /// Packets that haven't been acknowledged, in the order they were sent.
+    sent: VecDeque<SentPacket>,
+    delivered: u64,
+    delivered_time: Option<Time>,
+    first_sent_time: Option<Time>,
+    round: u64,
+    next_round_delivered: u64,
+    /// Delivery rate samples in packets per second, decreasing so the front is the maximum.
+    bandwidth_samples: VecDeque<(u64, Float)>,
+    min_rtt: Option<(TimeSpan, Time)>,
+    last_send: Option<Time>,
+}
+
+impl BbrCca {
+    #[must_use]
+    pub const fn new(initial_cwnd: u32) -> BbrCca {
+        BbrCca {
+            initial_cwnd,
+            mode: Mode::Startup,
+            filled_pipe: false,
+            full_bandwidth: 0.,
+            full_bandwidth_rounds: 0,
+            sent: VecDeque::new(),
+            delivered: 0,
+            delivered_time: None,
+            first_sent_time: None,
+            round: 0,
+            next_round_delivered: 0,
+            bandwidth_samples: VecDeque::new(),
+            min_rtt: None,
+            last_send: None,
+        }
+    }
+
+    fn bandwidth(&self) -> Option<Float> {
+        self.bandwidth_samples.front().map(|&(_, bw)| bw)
+    }
+
+    fn bdp(&self) -> Option<Float> {
+        Some(self.bandwidth()? * self.min_rtt?.0.seconds())
+    }
+
+    const fn gains(&self) -> (Float, Float) {
+        match self.mode {
+            Mode::Startup => (STARTUP_GAIN, STARTUP_GAIN),
+            Mode::Drain => (1. / STARTUP_GAIN, STARTUP_GAIN),
+            Mode::ProbeBw { phase, .. } => (PROBE_BW_PACING_GAINS[phase], PROBE_BW_CWND_GAIN),
+            Mode::ProbeRtt { .. } => (1., 1.),
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn in_flight(&self) -> Float {
+        self.sent.len() as Float
+    }
+
+    fn intersend_delay(&self) -> Option<TimeSpan> {
+        let (pacing_gain, _) = self.gains();
+        self.bandwidth().map(|bw| seconds(1. / (pacing_gain * bw)))
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    fn cwnd(&self) -> u32 {
+        if matches!(self.mode, Mode::ProbeRtt { .. }) {
+            return MIN_CWND as u32;
+        }
+        let (_, cwnd_gain) = self.gains();
+        self.bdp().map_or(self.initial_cwnd, |bdp| {
+            (cwnd_gain * bdp).max(MIN_CWND) as u32
+        })
+    }
+
+    fn record_bandwidth(&mut self, bw: Float) {
+        while self
+            .bandwidth_samples
+            .back()
+            .is_some_and(|&(_, sample)| sample <= bw)
+        {
+            self.bandwidth_samples.pop_back();
+        }
+        self.bandwidth_samples.push_back((self.round, bw));
+        while self
+            .bandwidth_samples
+            .front()
+            .is_some_and(|&(round, _)| round + BANDWIDTH_WINDOW_ROUNDS <= self.round)
+        {
+            self.bandwidth_samples.pop_front();
+        }
+    }
+
+    fn check_full_pipe(&mut self, logger: &mut impl Logger) {
+        let Some(bw) = self.bandwidth() else {
+            return;
+        };
+        if bw >= 1.25 * self.full_bandwidth {
+            self.full_bandwidth = bw;
+            self.full_bandwidth_rounds = 0;
+            return;
+        }
+        self.full_bandwidth_rounds += 1;
+        if self.full_bandwidth_rounds >= 3 {
+            log!(logger, "Pipe filled at {} packets per second", bw);
+            self.filled_pipe = true;
+        }
+    }
+
+    fn update_mode(
+        &mut self,
+        time: Time,
+        round_start: bool,
+        min_rtt_expired: bool,
+        logger: &mut impl Logger,
+    ) {
+        let (min_rtt, _) = self.min_rtt.expect("RTT to have been measured");
+        match self.mode {
+            Mode::Startup if round_start => {
+                self.check_full_pipe(logger);
+                if self.filled_pipe {
+                    self.mode = Mode::Drain;
+                }
+            }
+            Mode::Drain if self.bdp().is_some_and(|bdp| self.in_flight() <= bdp) => {
+                self.mode = Mode::ProbeBw {
+                    phase: 2,
+                    phase_start: time,
+                };
+            }
+            Mode::ProbeBw { phase, phase_start } if time - phase_start > min_rtt => {
+                self.mode = Mode::ProbeBw {
+                    phase: (phase + 1) % PROBE_BW_PACING_GAINS.len(),
+                    phase_start: time,
+                };
+            }
+            Mode::ProbeRtt { done: None } if self.in_flight() <= MIN_CWND => {
+                self.mode = Mode::ProbeRtt {
+                    done: Some(time + PROBE_RTT_DURATION),
+                };
+            }
+            Mode::ProbeRtt { done: Some(done) } if time >= done => {
+                self.min_rtt = Some((min_rtt, time));
+                self.mode = if self.filled_pipe {
+                    Mode::ProbeBw {
+                        phase: 0,
+                        phase_start: time,
+                    }
+                } else {
+                    Mode::Startup
+                };
+            }
+            _ => {}
+        }
+        if min_rtt_expired && !matches!(self.mode, Mode::ProbeRtt { .. }) {
+            log!(logger, "Probing RTT");
+            self.mode = Mode::ProbeRtt { done: None };
+        }
+    }
+}
+
+impl Cca for BbrCca {
+    fn initial_cwnd(&self, _time: Time) -> u32 {
+        self.initial_cwnd
+    }
+
+    fn next_tick(&self, time: Time) -> Option<Time> {
+        let last_send = self.last_send?;
+        Some(latest(&[time, last_send + self.intersend_delay()?]))
+    }
+
+    fn tick(&mut self, _rng: &mut Rng, _logger: &mut impl Logger) -> u32 {
+        self.last_send = None;
+        self.cwnd()
+    }
+
+    fn packet_sent(
+        &mut self,
+        packet: PacketSent,
+        _rng: &mut Rng,
+        _logger: &mut impl Logger,
+    ) -> u32 {
+        let delivered_time = *self.delivered_time.get_or_insert(packet.sent_time);
+        let first_sent_time = *self.first_sent_time.get_or_insert(packet.sent_time);
+        self.sent.push_back(SentPacket {
+            sent_time: packet.sent_time,
+            delivered: self.delivered,
+            delivered_time,
+            first_sent_time,
+        });
+        if self.bandwidth().is_some() {
+            // Wait to be ticked before sending the next packet
+            self.last_send = Some(packet.sent_time);
+            0
+        } else {
+            self.cwnd()
+        }
+    }
+
+    fn ack_received(&mut self, ack: AckReceived, _rng: &mut Rng, logger: &mut impl Logger) -> u32 {
+        let time = ack.received_time;
+        let rtt = time - ack.sent_time;
+        let mut sample = None;
+        while self
+            .sent
+            .front()
+            .is_some_and(|p| p.sent_time <= ack.sent_time)
+        {
+            sample = self.sent.pop_front();
+        }
+        self.delivered += ack.delivered;
+        self.delivered_time = Some(time);
+
+        let mut round_start = false;
+        if let Some(packet) = sample {
+            if packet.delivered >= self.next_round_delivered {
+                self.next_round_delivered = self.delivered;
+                self.round += 1;
+                round_start = true;
+            }
+            self.first_sent_time = Some(packet.sent_time);
+            // Use the longer of the send and ACK intervals, so that compressed ACKs don't inflate
+            // the estimate
+            let interval =
+                (time - packet.delivered_time).max(packet.sent_time - packet.first_sent_time);
+            if interval > TimeSpan::ZERO {
+                #[allow(clippy::cast_precision_loss)]
+                let bw = (self.delivered - packet.delivered) as Float / interval.seconds();
+                self.record_bandwidth(bw);
+            }
+        }
+        let min_rtt_expired = self
+            .min_rtt
+            .is_some_and(|(_, stamp)| time > stamp + MIN_RTT_WINDOW);
+        if min_rtt_expired || self.min_rtt.is_none_or(|(min_rtt, _)| rtt <= min_rtt) {
+            self.min_rtt = Some((rtt, time));
+        }
+        self.update_mode(time, round_start, min_rtt_expired, logger);
+
+        if self.last_send.is_some() {
+            0
+        } else {
+            self.cwnd()
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BbrCcaTemplate {
+    pub initial_cwnd: u32,
+}
+
+impl Default for BbrCcaTemplate {
+    fn default() -> Self {
+        BbrCcaTemplate { initial_cwnd: 10 }
+    }
+}
+
+impl CcaTemplate<'_> for BbrCcaTemplate {
+    type Policy = ();
+    type Cca = BbrCca;
+
+    fn with(&self, (): ()) -> impl Fn() -> BbrCca + Sync {
+        let initial_cwnd = self.initial_cwnd;
+        move || BbrCca::new(initial_cwnd)
+    }
+}
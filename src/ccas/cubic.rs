@@ -0,0 +1,162 @@
+use crate::{
+    quantities::{Float, Time, TimeSpan},
+    util::{logging::Logger, meters::EWMA, rand::Rng},
+    AckReceived, Cca, CcaTemplate, LossCause, LossDetected, PacketSent,
+};
+
+use super::window::LossWindow;
+
+#[derive(Debug, Clone)]
+struct Epoch {
+    start: Time,
+    /// Time taken to grow back to `origin`, in seconds.
+    k: Float,
+    origin: Float,
+    /// Window that Reno would have reached during this epoch.
+    reno_cwnd: Float,
+}
+
+/// CUBIC, as in RFC 8312, including fast convergence and the TCP-friendly region.
+#[derive(Debug)]
+pub struct CubicCca {
+    c: Float,
+    beta: Float,
+    window: LossWindow,
+    w_max: Float,
+    epoch: Option<Epoch>,
+    srtt: EWMA<TimeSpan>,
+}
+
+impl CubicCca {
+    #[must_use]
+    pub fn new(initial_cwnd: u32, c: Float, beta: Float) -> CubicCca {
+        CubicCca {
+            c,
+            beta,
+            window: LossWindow::new(Float::from(initial_cwnd)),
+            w_max: 0.,
+            epoch: None,
+            srtt: EWMA::new(1. / 8.),
+        }
+    }
+
+    fn congestion_avoidance(&mut self, time: Time, rtt: TimeSpan) {
+        let cwnd = self.window.cwnd;
+        let Epoch {
+            start,
+            k,
+            origin,
+            reno_cwnd,
+        } = self.epoch.get_or_insert_with(|| Epoch {
+            start: time,
+            k: if cwnd < self.w_max {
+                ((self.w_max - cwnd) / self.c).cbrt()
+            } else {
+                0.
+            },
+            origin: self.w_max.max(cwnd),
+            reno_cwnd: cwnd,
+        });
+        let t = (time - *start + rtt).seconds();
+        *reno_cwnd += 3. * (1. - self.beta) / (1. + self.beta) / cwnd;
+        let target = (*origin + self.c * (t - *k).powi(3)).max(*reno_cwnd);
+        self.window.cwnd += if target > cwnd {
+            (target - cwnd) / cwnd
+        } else {
+            0.01 / cwnd
+        };
+    }
+}
+
+impl Cca for CubicCca {
+    fn initial_cwnd(&self, _time: Time) -> u32 {
+        self.window.packets()
+    }
+
+    fn next_tick(&self, _time: Time) -> Option<Time> {
+        None
+    }
+
+    fn tick(&mut self, _rng: &mut Rng, _logger: &mut impl Logger) -> u32 {
+        panic!("Cubic never ticks!")
+    }
+
+    fn packet_sent(
+        &mut self,
+        _packet: PacketSent,
+        _rng: &mut Rng,
+        _logger: &mut impl Logger,
+    ) -> u32 {
+        self.window.packets()
+    }
+
+    fn ack_received(&mut self, ack: AckReceived, rng: &mut Rng, logger: &mut impl Logger) -> u32 {
+        let rtt = self.srtt.update(ack.received_time - ack.sent_time);
+        let may_grow = self.window.ack_received(&ack);
+        if let Some(cwnd) =
+            LossWindow::marked_loss(&ack).and_then(|loss| self.loss_detected(loss, rng, logger))
+        {
+            return cwnd;
+        }
+        if may_grow {
+            if self.window.in_slow_start() {
+                self.window.cwnd += 1.;
+            } else {
+                self.congestion_avoidance(ack.received_time, rtt);
+            }
+        }
+        self.window.packets()
+    }
+
+    fn loss_detected(
+        &mut self,
+        loss: LossDetected,
+        _rng: &mut Rng,
+        logger: &mut impl Logger,
+    ) -> Option<u32> {
+        if self.window.loss_detected(&loss) {
+            let cwnd = self.window.cwnd;
+            // Fast convergence: release bandwidth for new flows if the window keeps shrinking
+            self.w_max = if cwnd < self.w_max {
+                cwnd * (1. + self.beta) / 2.
+            } else {
+                cwnd
+            };
+            self.window.ssthresh = (cwnd * self.beta).max(2.);
+            self.window.cwnd = match loss.cause {
+                LossCause::DuplicateAcks => self.window.ssthresh,
+                LossCause::Timeout => 1.,
+            };
+            self.epoch = None;
+            log!(logger, "Reduced cwnd to {}", self.window.cwnd);
+        }
+        Some(self.window.packets())
+    }
+}
+
+#[derive(Debug)]
+pub struct CubicCcaTemplate {
+    pub initial_cwnd: u32,
+    pub c: Float,
+    pub beta: Float,
+}
+
+impl Default for CubicCcaTemplate {
+    fn default() -> Self {
+        CubicCcaTemplate {
+            initial_cwnd: 10,
+            c: 0.4,
+            beta: 0.7,
+        }
+    }
+}
+
+impl CcaTemplate<'_> for CubicCcaTemplate {
+    type Policy = ();
+    type Cca = CubicCca;
+
+    fn with(&self, (): ()) -> impl Fn() -> CubicCca + Sync {
+        let (initial_cwnd, c, beta) = (self.initial_cwnd, self.c, self.beta);
+        move || CubicCca::new(initial_cwnd, c, beta)
+    }
+}
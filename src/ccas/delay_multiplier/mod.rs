@@ -6,6 +6,7 @@ use crate::{
     AckReceived, Cca, PacketSent,
 };
 
+/// Paces packets, sending each one `multiplier` times the smoothed RTT after the last.
 #[derive(Debug)]
 pub struct DelayMultiplierCca {
     pub multiplier: Float,
@@ -22,24 +23,37 @@ impl DelayMultiplierCca {
             last_send: None,
         }
     }
+
+    fn intersend_delay(&self) -> Option<TimeSpan> {
+        self.rtt.value().map(|rtt| self.multiplier * rtt)
+    }
+
+    /// The window is closed until the intersend delay has passed since the last packet was sent,
+    /// and then places no limit on sending.
+    const fn get_cwnd(&self) -> u32 {
+        match self.last_send {
+            Some(_) => 0,
+            None => u32::MAX,
+        }
+    }
 }
 
 impl Cca for DelayMultiplierCca {
+    /// Only one packet is sent until the RTT has been measured.
     fn initial_cwnd(&self, _time: Time) -> u32 {
         1
     }
 
     fn next_tick(&self, time: Time) -> Option<Time> {
         self.last_send.and_then(|last_send| {
-            self.rtt
-                .value()
-                .map(|rtt| latest(&[time, last_send + self.multiplier * rtt]))
+            self.intersend_delay()
+                .map(|intersend_delay| latest(&[time, last_send + intersend_delay]))
         })
     }
 
     fn tick(&mut self, _rng: &mut Rng, _logger: &mut impl Logger) -> u32 {
         self.last_send = None;
-        1
+        self.get_cwnd()
     }
 
     fn ack_received(
@@ -47,14 +61,18 @@ impl Cca for DelayMultiplierCca {
         AckReceived {
             sent_time,
             received_time,
+            ..
         }: AckReceived,
         _rng: &mut Rng,
         logger: &mut impl Logger,
     ) -> u32 {
-        let rtt = self.rtt.update(received_time - sent_time);
-        let intersend_delay = self.multiplier * rtt;
-        log!(logger, "Updated intersend_delay to {}", intersend_delay);
-        0
+        self.rtt.update(received_time - sent_time);
+        log!(
+            logger,
+            "Updated intersend_delay to {}",
+            self.intersend_delay().unwrap()
+        );
+        self.get_cwnd()
     }
 
     fn packet_sent(
@@ -64,6 +82,6 @@ impl Cca for DelayMultiplierCca {
         _logger: &mut impl Logger,
     ) -> u32 {
         self.last_send = Some(packet.sent_time);
-        0
+        self.get_cwnd()
     }
 }
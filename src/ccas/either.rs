@@ -0,0 +1,65 @@
+use crate::{
+    quantities::Time,
+    util::{logging::Logger, rand::Rng},
+    AckReceived, Cca, LossDetected, PacketSent,
+};
+
+/// One of two CCAs, so that senders in the same simulation can run different algorithms.
+#[derive(Debug)]
+pub enum EitherCca<L, R> {
+    Left(L),
+    Right(R),
+}
+
+impl<L, R> Cca for EitherCca<L, R>
+where
+    L: Cca,
+    R: Cca,
+{
+    fn initial_cwnd(&self, time: Time) -> u32 {
+        match self {
+            EitherCca::Left(cca) => cca.initial_cwnd(time),
+            EitherCca::Right(cca) => cca.initial_cwnd(time),
+        }
+    }
+
+    fn next_tick(&self, time: Time) -> Option<Time> {
+        match self {
+            EitherCca::Left(cca) => cca.next_tick(time),
+            EitherCca::Right(cca) => cca.next_tick(time),
+        }
+    }
+
+    fn tick(&mut self, rng: &mut Rng, logger: &mut impl Logger) -> u32 {
+        match self {
+            EitherCca::Left(cca) => cca.tick(rng, logger),
+            EitherCca::Right(cca) => cca.tick(rng, logger),
+        }
+    }
+
+    fn packet_sent(&mut self, packet: PacketSent, rng: &mut Rng, logger: &mut impl Logger) -> u32 {
+        match self {
+            EitherCca::Left(cca) => cca.packet_sent(packet, rng, logger),
+            EitherCca::Right(cca) => cca.packet_sent(packet, rng, logger),
+        }
+    }
+
+    fn ack_received(&mut self, ack: AckReceived, rng: &mut Rng, logger: &mut impl Logger) -> u32 {
+        match self {
+            EitherCca::Left(cca) => cca.ack_received(ack, rng, logger),
+            EitherCca::Right(cca) => cca.ack_received(ack, rng, logger),
+        }
+    }
+
+    fn loss_detected(
+        &mut self,
+        loss: LossDetected,
+        rng: &mut Rng,
+        logger: &mut impl Logger,
+    ) -> Option<u32> {
+        match self {
+            EitherCca::Left(cca) => cca.loss_detected(loss, rng, logger),
+            EitherCca::Right(cca) => cca.loss_detected(loss, rng, logger),
+        }
+    }
+}
@@ -1,3 +1,9 @@
 pub mod remy;
 pub mod remyr;
 pub mod delay_multiplier;
+pub mod bbr;
+pub mod cubic;
+pub mod either;
+pub mod new_reno;
+pub mod vegas;
+pub mod window;
@@ -0,0 +1,97 @@
+use crate::{
+    quantities::{Float, Time},
+    util::{logging::Logger, rand::Rng},
+    AckReceived, Cca, CcaTemplate, LossCause, LossDetected, PacketSent,
+};
+
+use super::window::LossWindow;
+
+/// TCP `NewReno`, as in RFC 6582. Retransmission is handled by the sender, so this only
+/// implements the congestion window.
+#[derive(Debug)]
+pub struct NewRenoCca {
+    window: LossWindow,
+}
+
+impl NewRenoCca {
+    #[must_use]
+    pub fn new(initial_cwnd: u32) -> NewRenoCca {
+        NewRenoCca {
+            window: LossWindow::new(Float::from(initial_cwnd)),
+        }
+    }
+}
+
+impl Cca for NewRenoCca {
+    fn initial_cwnd(&self, _time: Time) -> u32 {
+        self.window.packets()
+    }
+
+    fn next_tick(&self, _time: Time) -> Option<Time> {
+        None
+    }
+
+    fn tick(&mut self, _rng: &mut Rng, _logger: &mut impl Logger) -> u32 {
+        panic!("NewReno never ticks!")
+    }
+
+    fn packet_sent(
+        &mut self,
+        _packet: PacketSent,
+        _rng: &mut Rng,
+        _logger: &mut impl Logger,
+    ) -> u32 {
+        self.window.packets()
+    }
+
+    fn ack_received(&mut self, ack: AckReceived, rng: &mut Rng, logger: &mut impl Logger) -> u32 {
+        let may_grow = self.window.ack_received(&ack);
+        if let Some(cwnd) =
+            LossWindow::marked_loss(&ack).and_then(|loss| self.loss_detected(loss, rng, logger))
+        {
+            return cwnd;
+        }
+        if may_grow {
+            self.window.reno_increase();
+        }
+        self.window.packets()
+    }
+
+    fn loss_detected(
+        &mut self,
+        loss: LossDetected,
+        _rng: &mut Rng,
+        logger: &mut impl Logger,
+    ) -> Option<u32> {
+        if self.window.loss_detected(&loss) {
+            self.window.ssthresh = (self.window.cwnd / 2.).max(2.);
+            self.window.cwnd = match loss.cause {
+                LossCause::DuplicateAcks => self.window.ssthresh,
+                LossCause::Timeout => 1.,
+            };
+            log!(logger, "Reduced cwnd to {}", self.window.cwnd);
+        }
+        Some(self.window.packets())
+    }
+}
+
+#[derive(Debug)]
+pub struct NewRenoCcaTemplate {
+    pub initial_cwnd: u32,
+}
+
+impl Default for NewRenoCcaTemplate {
+    fn default() -> Self {
+        NewRenoCcaTemplate { initial_cwnd: 10 }
+    }
+}
+
+impl CcaTemplate<'_> for NewRenoCcaTemplate {
+    type Policy = ();
+    type Cca = NewRenoCca;
+
+    fn with(&self, (): ()) -> impl Fn() -> NewRenoCca + Sync {
+        let initial_cwnd = self.initial_cwnd;
+        move || NewRenoCca::new(initial_cwnd)
+    }
+}
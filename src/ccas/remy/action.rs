@@ -68,8 +68,8 @@ impl Whisker {
         max: &Point<TESTING>,
     ) -> Self {
         let mut memory_range = MemoryRange::new();
-        memory_range.lower = MessageField::some(min.to_memory());
-        memory_range.upper = MessageField::some(max.to_memory());
+        memory_range.lower = MessageField::some(min.to_memory(&Point::MIN));
+        memory_range.upper = MessageField::some(max.to_memory(&Point::MAX));
         let mut whisker = Whisker::new();
         whisker.set_intersend(if TESTING {
             value.intersend_delay.seconds()
@@ -1,10 +1,10 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use super::point::Point;
 
 use std::fmt::{Debug, Display};
 
-#[derive(Clone, PartialEq, Debug, Serialize)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Cube<const TESTING: bool = false> {
     pub min: Point<TESTING>,
     pub max: Point<TESTING>,
@@ -14,13 +14,15 @@ impl<const TESTING: bool> Display for Cube<TESTING> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Cube {{ ack_ewma: {}-{}, send_ewma: {}-{}, rtt_ratio: {:.4}-{:.4} }}",
+            "Cube {{ ack_ewma: {}-{}, send_ewma: {}-{}, rtt_ratio: {:.4}-{:.4}, mark_ewma: {:.4}-{:.4} }}",
             self.min.ack_ewma,
             self.max.ack_ewma,
             self.min.send_ewma,
             self.max.send_ewma,
             self.min.rtt_ratio,
-            self.max.rtt_ratio
+            self.max.rtt_ratio,
+            self.min.mark_ewma,
+            self.max.mark_ewma
         )
     }
 }
@@ -47,6 +49,7 @@ impl<const TESTING: bool> Cube<TESTING> {
         within(&self.min.rtt_ratio, &point.rtt_ratio, &self.max.rtt_ratio)
             && within(&self.min.ack_ewma, &point.ack_ewma, &self.max.ack_ewma)
             && within(&self.min.send_ewma, &point.send_ewma, &self.max.send_ewma)
+            && within(&self.min.mark_ewma, &point.mark_ewma, &self.max.mark_ewma)
     }
 
     fn split_ack_ewma(&self) -> Vec<Cube<TESTING>> {
@@ -109,12 +112,42 @@ impl<const TESTING: bool> Cube<TESTING> {
         ]
     }
 
+    fn split_mark_ewma(&self) -> Vec<Cube<TESTING>> {
+        #[allow(clippy::manual_midpoint)]
+        let mark_ewma = (self.max.mark_ewma + self.min.mark_ewma) / 2.;
+        vec![
+            Cube {
+                min: self.min.clone(),
+                max: Point {
+                    mark_ewma,
+                    ..self.max
+                },
+            },
+            Cube {
+                min: Point {
+                    mark_ewma,
+                    ..self.min
+                },
+                max: self.max.clone(),
+            },
+        ]
+    }
+
+    /// Splits the cube in half along each dimension. The marking EWMA is only split if
+    /// `mark_ewma` is set, as it stays at zero in networks without ECN.
     #[must_use]
-    pub fn split(&self) -> Vec<Cube<TESTING>> {
+    pub fn split(&self, mark_ewma: bool) -> Vec<Cube<TESTING>> {
         self.split_ack_ewma()
             .into_iter()
             .flat_map(|x| x.split_send_ewma())
             .flat_map(|x| x.split_rtt_ratio())
+            .flat_map(|x| {
+                if mark_ewma {
+                    x.split_mark_ewma()
+                } else {
+                    vec![x]
+                }
+            })
             .collect()
     }
 }
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use protobuf::Message;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::Dna;
 
@@ -8,7 +8,7 @@ use super::{
     action::Action, autogen::remy_dna::WhiskerTree, point::Point, rule_tree::RuleTree, RemyPolicy,
 };
 
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct RemyDna<const TESTING: bool = false>(pub RuleTree<TESTING>);
 
 impl RemyDna {
@@ -3,9 +3,9 @@ use std::{fmt::Debug, marker::PhantomData};
 use derive_where::derive_where;
 
 use crate::{
-    quantities::{Time, TimeSpan},
+    quantities::{Float, Time, TimeSpan},
     util::{
-        logging::Logger,
+        logging::{Event, Logger},
         meters::EWMA,
         rand::{DiscreteDistribution, Rng},
     },
@@ -37,6 +37,7 @@ pub struct RemyCca<T> {
     last_ack_send: Option<Time>,
     ack_ewma: EWMA<TimeSpan>,
     send_ewma: EWMA<TimeSpan>,
+    mark_ewma: EWMA<Float>,
     rtt: Option<Rtt>,
     next_change: Option<(u32, Action)>,
     repeat_actions: Option<DiscreteDistribution<u32>>,
@@ -52,6 +53,7 @@ impl<T> Debug for RemyCca<T> {
             .field("last_ack_send", &self.last_ack_send)
             .field("ack_ewma", &self.ack_ewma)
             .field("send_ewma", &self.send_ewma)
+            .field("mark_ewma", &self.mark_ewma)
             .field("rtt", &self.rtt)
             .field("next_change", &self.next_change)
             .field("repeat_actions", &self.repeat_actions)
@@ -71,6 +73,7 @@ where
             policy: rule_tree,
             ack_ewma: EWMA::new(1. / 8.),
             send_ewma: EWMA::new(1. / 8.),
+            mark_ewma: EWMA::new(1. / 8.),
             last_ack: None,
             last_ack_send: None,
             rtt: None,
@@ -86,6 +89,7 @@ where
             ack_ewma: self.ack_ewma.value().unwrap_or(TimeSpan::ZERO),
             send_ewma: self.send_ewma.value().unwrap_or(TimeSpan::ZERO),
             rtt_ratio: self.rtt.as_ref().map_or(0., |rtt| rtt.current / rtt.min),
+            mark_ewma: self.mark_ewma.value().unwrap_or(0.),
         }
     }
 
@@ -141,6 +145,9 @@ where
         AckReceived {
             sent_time,
             received_time,
+            delivered,
+            marked,
+            ..
         }: AckReceived,
         rng: &mut Rng,
         logger: &mut impl Logger,
@@ -151,6 +158,9 @@ where
         if let Some(last_ack_send) = self.last_ack_send {
             self.send_ewma.update(sent_time - last_ack_send);
         }
+        #[allow(clippy::cast_precision_loss)]
+        self.mark_ewma
+            .update((marked as Float / delivered.max(1) as Float).min(1.));
         self.last_ack = Some(received_time);
         self.last_ack_send = Some(sent_time);
         let current_rtt = received_time - sent_time;
@@ -186,6 +196,11 @@ where
                 a
             }
         };
+        logger.event(received_time, || Event::ActionChosen {
+            window_multiplier: action.window_multiplier,
+            window_increment: action.window_increment,
+            intersend_delay: action.intersend_delay,
+        });
         self.current_settings = RemyCwndSettings {
             cwnd: action.apply_to(self.current_settings.cwnd),
             intersend_delay: action.intersend_delay,
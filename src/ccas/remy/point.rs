@@ -12,14 +12,17 @@ pub struct Point<const TESTING: bool = false> {
     pub ack_ewma: TimeSpan,
     pub send_ewma: TimeSpan,
     pub rtt_ratio: Float,
+    /// Moving average of the fraction of packets marked with congestion experienced.
+    #[serde(default)]
+    pub mark_ewma: Float,
 }
 
 impl<const TESTING: bool> Display for Point<TESTING> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Point {{ ack_ewma: {}, send_ewma: {}, rtt_ratio: {} }}",
-            self.ack_ewma, self.send_ewma, self.rtt_ratio
+            "Point {{ ack_ewma: {}, send_ewma: {}, rtt_ratio: {}, mark_ewma: {} }}",
+            self.ack_ewma, self.send_ewma, self.rtt_ratio, self.mark_ewma
         )
     }
 }
@@ -29,26 +32,32 @@ impl<const TESTING: bool> Point<TESTING> {
         ack_ewma: seconds(0.),
         send_ewma: seconds(0.),
         rtt_ratio: 0.,
+        mark_ewma: 0.,
     };
     // TODO
     pub const MAX: Self = Point {
         ack_ewma: seconds(600.),
         send_ewma: seconds(600.),
         rtt_ratio: 1000.,
+        // Just above 1, so that a point where every packet is marked is contained
+        mark_ewma: 1f64.next_up(),
     };
 
+    /// DNA from the original Remy implementation doesn't include `mark_ewma`, so it is taken from
+    /// `default` if missing, and only written by `to_memory` if it differs from `default`.
     #[must_use]
-    pub fn from_memory(memory: &MessageField<Memory>) -> Self {
+    pub fn from_memory(memory: &MessageField<Memory>, default: &Self) -> Self {
         let convert = |x| if TESTING { seconds(x) } else { milliseconds(x) };
         Point {
             ack_ewma: convert(memory.rec_rec_ewma()),
             send_ewma: convert(memory.rec_send_ewma()),
             rtt_ratio: memory.rtt_ratio(),
+            mark_ewma: memory.mark_ewma.unwrap_or(default.mark_ewma),
         }
     }
 
     #[must_use]
-    pub fn to_memory(&self) -> Memory {
+    pub fn to_memory(&self, default: &Self) -> Memory {
         let convert = |x: TimeSpan| {
             if TESTING {
                 x.seconds()
@@ -60,6 +69,10 @@ impl<const TESTING: bool> Point<TESTING> {
         memory.set_rec_rec_ewma(convert(self.ack_ewma));
         memory.set_rec_send_ewma(convert(self.send_ewma));
         memory.set_rtt_ratio(self.rtt_ratio);
+        #[allow(clippy::float_cmp)]
+        if self.mark_ewma != default.mark_ewma {
+            memory.set_mark_ewma(self.mark_ewma);
+        }
         memory
     }
 }
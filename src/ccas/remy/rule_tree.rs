@@ -5,9 +5,10 @@ use std::{
 
 use itertools::Itertools;
 use protobuf::MessageField;
-use serde::Serialize;
+use rand::distributions::Uniform;
+use serde::{Deserialize, Serialize};
 
-use crate::quantities::Float;
+use crate::{quantities::Float, util::rand::Rng};
 
 use super::{
     action::Action,
@@ -134,11 +135,11 @@ impl<'a> LeafHandle<'a> {
         }
     }
 
-    pub fn split(self) {
+    pub fn split(self, mark_ewma: bool) {
         let children = match &self.tree.nodes[self.rule] {
             RuleTreeNode::Node { .. } => panic!(),
             RuleTreeNode::Leaf { domain, action, .. } => domain
-                .split()
+                .split(mark_ewma)
                 .into_iter()
                 .map(|domain| RuleTreeNode::Leaf {
                     domain,
@@ -160,7 +161,7 @@ impl<'a> LeafHandle<'a> {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum RuleTreeNode<const TESTING: bool = false> {
     Node {
         domain: Cube<TESTING>,
@@ -224,7 +225,7 @@ impl<const TESTING: bool> RuleTreeNode<TESTING> {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RuleTree<const TESTING: bool = false> {
     root: usize,
     nodes: Vec<RuleTreeNode<TESTING>>,
@@ -235,8 +236,8 @@ fn _push_whisker_tree<const TESTING: bool>(
     value: &WhiskerTree,
 ) -> usize {
     let domain = Cube {
-        min: Point::from_memory(&value.domain.lower),
-        max: Point::from_memory(&value.domain.upper),
+        min: Point::from_memory(&value.domain.lower, &Point::MIN),
+        max: Point::from_memory(&value.domain.upper, &Point::MAX),
     };
     let new_node = if value.leaf.is_some() {
         RuleTreeNode::<TESTING>::Leaf {
@@ -292,8 +293,8 @@ impl<const TESTING: bool> RuleTree<TESTING> {
         let mut tree = WhiskerTree::new();
         let cube = value.domain().clone();
         let domain = tree.domain.mut_or_insert_default();
-        domain.lower = MessageField::some(cube.min.to_memory());
-        domain.upper = MessageField::some(cube.max.to_memory());
+        domain.lower = MessageField::some(cube.min.to_memory(&Point::MIN));
+        domain.upper = MessageField::some(cube.max.to_memory(&Point::MAX));
         match value {
             RuleTreeNode::Node { children, .. } => {
                 tree.children = children.iter().map(|i| self._to_whisker_tree(*i)).collect();
@@ -305,6 +306,41 @@ impl<const TESTING: bool> RuleTree<TESTING> {
         tree
     }
 
+    /// Copies the subtree rooted at `idx` onto the end of `nodes`, with the subtree of `donor`
+    /// rooted at `graft.2` in place of the node at `graft.0`. Returns the index of the new root.
+    fn push_subtree(
+        &self,
+        idx: usize,
+        nodes: &mut Vec<RuleTreeNode<TESTING>>,
+        graft: Option<(usize, &RuleTree<TESTING>, usize)>,
+    ) -> usize {
+        if let Some((at, donor, donor_idx)) = graft {
+            if at == idx {
+                return donor.push_subtree(donor_idx, nodes, None);
+            }
+        }
+        let node = match &self.nodes[idx] {
+            RuleTreeNode::Node { domain, children } => RuleTreeNode::Node {
+                domain: domain.clone(),
+                children: children
+                    .iter()
+                    .map(|child| self.push_subtree(*child, nodes, graft))
+                    .collect(),
+            },
+            leaf @ RuleTreeNode::Leaf { .. } => leaf.clone(),
+        };
+        nodes.push(node);
+        nodes.len() - 1
+    }
+
+    /// Returns a copy without any nodes that are unreachable from the root.
+    #[must_use]
+    fn compacted(&self) -> RuleTree<TESTING> {
+        let mut nodes = Vec::new();
+        let root = self.push_subtree(self.root, &mut nodes, None);
+        RuleTree { root, nodes }
+    }
+
     #[must_use]
     pub fn to_whisker_tree(&self) -> WhiskerTree {
         self._to_whisker_tree(self.root)
@@ -356,6 +392,92 @@ impl RuleTree {
         });
     }
 
+    #[must_use]
+    pub fn num_leaves(&self) -> usize {
+        self.nodes.len() - self.num_parents()
+    }
+
+    pub fn leaf_actions_mut(&mut self) -> impl Iterator<Item = &mut Action> {
+        self.nodes.iter_mut().filter_map(|node| match node {
+            RuleTreeNode::Node { .. } => None,
+            RuleTreeNode::Leaf { action, .. } => Some(action),
+        })
+    }
+
+    /// Splits a rule chosen uniformly at random, as in `LeafHandle::split`.
+    pub fn split_random_leaf(&mut self, mark_ewma: bool, rng: &mut Rng) {
+        let leaves = (0..self.nodes.len())
+            .filter(|&i| matches!(self.nodes[i], RuleTreeNode::Leaf { .. }))
+            .collect_vec();
+        let rule = leaves[rng.sample(&Uniform::new(0, leaves.len()))];
+        LeafHandle { tree: self, rule }.split(mark_ewma);
+    }
+
+    /// Replaces a node whose children are all rules with a single rule, taking the action of one
+    /// of the children at random. Returns `false` if the tree is a single rule.
+    pub fn merge_random_node(&mut self, rng: &mut Rng) -> bool {
+        let is_leaf = |i: &usize| matches!(self.nodes[*i], RuleTreeNode::Leaf { .. });
+        let mergeable = (0..self.nodes.len())
+            .filter(|&i| match &self.nodes[i] {
+                RuleTreeNode::Node { children, .. } => children.iter().all(is_leaf),
+                RuleTreeNode::Leaf { .. } => false,
+            })
+            .collect_vec();
+        if mergeable.is_empty() {
+            return false;
+        }
+        let idx = mergeable[rng.sample(&Uniform::new(0, mergeable.len()))];
+        let RuleTreeNode::Node { domain, children } = &self.nodes[idx] else {
+            unreachable!()
+        };
+        let RuleTreeNode::Leaf { action, .. } =
+            &self.nodes[children[rng.sample(&Uniform::new(0, children.len()))]]
+        else {
+            unreachable!()
+        };
+        self.nodes[idx] = RuleTreeNode::Leaf {
+            domain: domain.clone(),
+            action: action.clone(),
+            optimized: false,
+        };
+        *self = self.compacted();
+        true
+    }
+
+    /// Returns a copy of this tree in which a subtree, chosen at random, is replaced by the
+    /// subtree of `other` with the same domain. The root is only exchanged, giving a copy of
+    /// `other`, if no other domain appears in both trees, and this tree is returned unchanged if
+    /// none does.
+    #[must_use]
+    pub fn crossover(&self, other: &RuleTree, rng: &mut Rng) -> RuleTree {
+        let matching = (0..self.nodes.len())
+            .filter_map(|i| {
+                other
+                    .nodes
+                    .iter()
+                    .position(|node| node.domain() == self.nodes[i].domain())
+                    .map(|j| (i, j))
+            })
+            .collect_vec();
+        let candidates = matching
+            .iter()
+            .filter(|(i, _)| *i != self.root)
+            .copied()
+            .collect_vec();
+        let candidates = if candidates.is_empty() {
+            matching
+        } else {
+            candidates
+        };
+        if candidates.is_empty() {
+            return self.clone();
+        }
+        let (at, donor_idx) = candidates[rng.sample(&Uniform::new(0, candidates.len()))];
+        let mut nodes = Vec::new();
+        let root = self.push_subtree(self.root, &mut nodes, Some((at, other, donor_idx)));
+        RuleTree { root, nodes }
+    }
+
     #[must_use]
     pub fn num_parents(&self) -> usize {
         self.nodes
@@ -401,8 +523,10 @@ mod tests {
     use tempfile::tempdir;
 
     use crate::{
-        ccas::remy::{dna::RemyDna, rule_tree::RuleTree},
-        Config,
+        ccas::remy::{action::Action, dna::RemyDna, point::Point, rule_tree::RuleTree, RemyPolicy},
+        quantities::milliseconds,
+        util::rand::Rng,
+        Config, Custom,
     };
 
     use super::super::autogen::remy_dna::WhiskerTree;
@@ -443,8 +567,8 @@ mod tests {
 
         for original_file in dna_files {
             let tmp_file = tmp_dir.path().join(original_file.file_name().unwrap());
-            let original = RemyDna::<true>::load(&original_file)?;
-            original.save(&tmp_file)?;
+            let original = <RemyDna<true> as Config<Custom>>::load(&original_file)?;
+            Config::<Custom>::save(&original, &tmp_file)?;
             check_to_pb(&original);
             let mut file = File::open(original_file.clone())?;
             let mut buf = Vec::new();
@@ -456,4 +580,40 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn genetic_operators_keep_tree_valid() {
+        let mut rng = Rng::from_seed(0);
+        let action = |window_increment| Action {
+            window_multiplier: 1.,
+            window_increment,
+            intersend_delay: milliseconds(1.),
+        };
+        let mut tree = RuleTree::default(action(0));
+        assert!(!tree.merge_random_node(&mut rng));
+        tree.split_random_leaf(false, &mut rng);
+        tree.split_random_leaf(false, &mut rng);
+        assert_eq!(tree.num_leaves(), 15);
+        assert!(tree.merge_random_node(&mut rng));
+        assert_eq!(tree.num_leaves(), 8);
+
+        let mut other = RuleTree::default(action(1));
+        other.split_random_leaf(false, &mut rng);
+        other.split_random_leaf(false, &mut rng);
+        other.split_random_leaf(false, &mut rng);
+        let mut child = tree.crossover(&other, &mut rng);
+        let whisker_tree = child.to_whisker_tree();
+        assert_eq!(
+            RuleTree::<false>::from_whisker_tree(&whisker_tree).to_whisker_tree(),
+            whisker_tree
+        );
+        let point = Point {
+            ack_ewma: milliseconds(10.),
+            send_ewma: milliseconds(10.),
+            rtt_ratio: 1.5,
+            mark_ewma: 0.,
+        };
+        assert!(child.action(&point).is_some());
+        assert!(child.leaf_actions_mut().any(|a| a.window_increment == 1));
+    }
 }
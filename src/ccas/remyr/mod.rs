@@ -17,6 +17,7 @@ fn point_to_tensor(dev: &Cpu, point: &Point) -> Tensor<(Const<OBSERVATION>,), f3
         point.ack_ewma.to_underlying() as f32,
         point.send_ewma.to_underlying() as f32,
         point.rtt_ratio.to_underlying() as f32,
+        point.mark_ewma.to_underlying() as f32,
     ])
 }
 
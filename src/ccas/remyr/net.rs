@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use super::dna::SerializeTensors;
 
-pub const OBSERVATION: usize = 3;
+pub const OBSERVATION: usize = 4;
 pub const GLOBAL_STATE: usize = 1;
 pub const AGENT_SPECIFIC_GLOBAL_STATE: usize = OBSERVATION + GLOBAL_STATE;
 pub const ACTION: usize = 3;
@@ -55,6 +55,12 @@ impl HiddenLayers {
         )
     }
 
+    /// Builds a policy on the CPU, with the same initial parameters every time.
+    #[must_use]
+    pub fn build_policy(self) -> PolicyNetwork<Cpu> {
+        Cpu::default().build_module::<f32>(self.policy_arch())
+    }
+
     #[must_use]
     pub fn critic_arch(self) -> CriticArchitecture {
         (
@@ -89,6 +95,183 @@ where
     }
 }
 
+/// A tensor of parameters, viewed as a flat slice of values.
+pub trait FlatTensor<D: Device<f32>> {
+    fn values(&self) -> Vec<f32>;
+
+    /// Copies the first elements of `values` into the tensor, and advances `values` past them.
+    fn take_values(&mut self, values: &mut &[f32]);
+
+    /// Returns the values that `gradients` holds for the tensor.
+    fn gradient(&self, gradients: &Gradients<f32, D>) -> Vec<f32>;
+
+    /// Returns a loss whose gradient with respect to the tensor is the first elements of
+    /// `values`, and advances `values` past them.
+    fn loss_with_gradient(&self, values: &mut &[f32]) -> Tensor<Rank0, f32, D, OwnedTape<f32, D>>;
+}
+
+impl<S: Shape, D: Device<f32>> FlatTensor<D> for Tensor<S, f32, D> {
+    fn values(&self) -> Vec<f32> {
+        self.as_vec()
+    }
+
+    fn take_values(&mut self, values: &mut &[f32]) {
+        let (head, tail) = values.split_at(self.shape().num_elements());
+        self.copy_from(head);
+        *values = tail;
+    }
+
+    fn gradient(&self, gradients: &Gradients<f32, D>) -> Vec<f32> {
+        gradients.get(self).as_vec()
+    }
+
+    fn loss_with_gradient(&self, values: &mut &[f32]) -> Tensor<Rank0, f32, D, OwnedTape<f32, D>> {
+        let (head, tail) = values.split_at(self.shape().num_elements());
+        *values = tail;
+        let gradient = self.dev().tensor_from_vec(head.to_vec(), *self.shape());
+        (self.clone().leaky_trace() * gradient).sum()
+    }
+}
+
+/// The weights and biases of a network as a single vector, for training methods that treat the
+/// network as a black box, and for saving the state of an optimizer.
+pub trait FlatParameters<D: Device<f32> = Cpu> {
+    /// Every tensor of parameters, in the order they are flattened.
+    fn tensors(&self) -> Vec<&dyn FlatTensor<D>>;
+
+    fn tensors_mut(&mut self) -> Vec<&mut dyn FlatTensor<D>>;
+
+    fn parameters(&self) -> Vec<f32> {
+        self.tensors()
+            .into_iter()
+            .flat_map(FlatTensor::values)
+            .collect()
+    }
+
+    /// Panics if `parameters` is not the same length as the vector returned by `parameters`.
+    fn set_parameters(&mut self, parameters: &[f32]) {
+        let mut rest = parameters;
+        for tensor in self.tensors_mut() {
+            tensor.take_values(&mut rest);
+        }
+        assert!(rest.is_empty(), "Too many parameters for the network");
+    }
+
+    /// Returns the values that `gradients` holds for the parameters, such as the moments of an
+    /// optimizer, in the same order as `parameters`.
+    fn flat_gradients(&self, gradients: &Gradients<f32, D>) -> Vec<f32> {
+        self.tensors()
+            .into_iter()
+            .flat_map(|tensor| tensor.gradient(gradients))
+            .collect()
+    }
+
+    /// The inverse of `flat_gradients`. dfdx has no way to fill in gradients directly, so they
+    /// are found by back-propagating through the sum of each parameter times its value.
+    fn gradients_from_flat(&self, values: &[f32]) -> Gradients<f32, D> {
+        let mut rest = values;
+        let loss = self
+            .tensors()
+            .into_iter()
+            .map(|tensor| tensor.loss_with_gradient(&mut rest))
+            .reduce(|a, b| a + b)
+            .expect("Network to have parameters");
+        assert!(rest.is_empty(), "Too many values for the network");
+        loss.backward()
+    }
+}
+
+impl<I: Dim, O: Dim, D: Device<f32>> FlatParameters<D> for Linear<I, O, f32, D> {
+    fn tensors(&self) -> Vec<&dyn FlatTensor<D>> {
+        vec![&self.weight, &self.bias]
+    }
+
+    fn tensors_mut(&mut self) -> Vec<&mut dyn FlatTensor<D>> {
+        vec![&mut self.weight, &mut self.bias]
+    }
+}
+
+impl<I: Dim, D: Device<f32>> FlatParameters<D> for Bias1D<I, f32, D> {
+    fn tensors(&self) -> Vec<&dyn FlatTensor<D>> {
+        vec![&self.bias]
+    }
+
+    fn tensors_mut(&mut self) -> Vec<&mut dyn FlatTensor<D>> {
+        vec![&mut self.bias]
+    }
+}
+
+impl<D: Device<f32>> FlatParameters<D> for Tanh {
+    fn tensors(&self) -> Vec<&dyn FlatTensor<D>> {
+        Vec::new()
+    }
+
+    fn tensors_mut(&mut self) -> Vec<&mut dyn FlatTensor<D>> {
+        Vec::new()
+    }
+}
+
+impl<D: Device<f32>> FlatParameters<D> for FastGeLU {
+    fn tensors(&self) -> Vec<&dyn FlatTensor<D>> {
+        Vec::new()
+    }
+
+    fn tensors_mut(&mut self) -> Vec<&mut dyn FlatTensor<D>> {
+        Vec::new()
+    }
+}
+
+impl<D, A> FlatParameters<D> for (A,)
+where
+    D: Device<f32>,
+    A: FlatParameters<D>,
+{
+    fn tensors(&self) -> Vec<&dyn FlatTensor<D>> {
+        self.0.tensors()
+    }
+
+    fn tensors_mut(&mut self) -> Vec<&mut dyn FlatTensor<D>> {
+        self.0.tensors_mut()
+    }
+}
+
+impl<D, A, B> FlatParameters<D> for (A, B)
+where
+    D: Device<f32>,
+    A: FlatParameters<D>,
+    B: FlatParameters<D>,
+{
+    fn tensors(&self) -> Vec<&dyn FlatTensor<D>> {
+        [self.0.tensors(), self.1.tensors()].concat()
+    }
+
+    fn tensors_mut(&mut self) -> Vec<&mut dyn FlatTensor<D>> {
+        let (a, b) = self;
+        a.tensors_mut().into_iter().chain(b.tensors_mut()).collect()
+    }
+}
+
+impl<D, A, B, C> FlatParameters<D> for (A, B, C)
+where
+    D: Device<f32>,
+    A: FlatParameters<D>,
+    B: FlatParameters<D>,
+    C: FlatParameters<D>,
+{
+    fn tensors(&self) -> Vec<&dyn FlatTensor<D>> {
+        [self.0.tensors(), self.1.tensors(), self.2.tensors()].concat()
+    }
+
+    fn tensors_mut(&mut self) -> Vec<&mut dyn FlatTensor<D>> {
+        let (a, b, c) = self;
+        a.tensors_mut()
+            .into_iter()
+            .chain(b.tensors_mut())
+            .chain(c.tensors_mut())
+            .collect()
+    }
+}
+
 pub trait PolicyNet<D> {
     fn device(&self) -> &D;
 
@@ -114,7 +297,7 @@ mod tests {
 
     use crate::ccas::remyr::dna::SerializeTensors;
 
-    use super::HiddenLayers;
+    use super::{FlatParameters, HiddenLayers, ACTION, OBSERVATION};
 
     #[test]
     fn determinism() {
@@ -125,4 +308,21 @@ mod tests {
         assert_eq!(n1.serialize(), n2.serialize());
         insta::assert_yaml_snapshot!(n1.serialize());
     }
+
+    #[test]
+    fn flat_parameters() {
+        let hidden_layers = HiddenLayers(32, 16);
+        let original = hidden_layers.build_policy();
+        let parameters = original.parameters();
+        assert_eq!(
+            parameters.len(),
+            (OBSERVATION * 32 + 32) + (32 * 16 + 16) + (16 * ACTION + ACTION)
+        );
+
+        let mut other = Cpu::seed_from_u64(1).build_module::<f32>(hidden_layers.policy_arch());
+        assert_ne!(other.serialize(), original.serialize());
+        other.set_parameters(&parameters);
+        assert_eq!(other.serialize(), original.serialize());
+        assert_eq!(other.parameters(), parameters);
+    }
 }
@@ -0,0 +1,153 @@
+use crate::{
+    quantities::{Float, Time, TimeSpan},
+    util::{logging::Logger, rand::Rng},
+    AckReceived, Cca, CcaTemplate, LossCause, LossDetected, PacketSent,
+};
+
+use super::window::LossWindow;
+
+/// TCP Vegas, which once per round trip compares the expected and actual sending rates to
+/// estimate how many packets it has queued at the bottleneck. Losses are handled as in Reno.
+#[derive(Debug)]
+pub struct VegasCca {
+    alpha: Float,
+    beta: Float,
+    gamma: Float,
+    window: LossWindow,
+    base_rtt: Option<TimeSpan>,
+    round_min_rtt: Option<TimeSpan>,
+    /// The round ends once a packet sent after this time is acknowledged.
+    round_start: Time,
+}
+
+impl VegasCca {
+    #[must_use]
+    pub fn new(initial_cwnd: u32, alpha: Float, beta: Float, gamma: Float) -> VegasCca {
+        VegasCca {
+            alpha,
+            beta,
+            gamma,
+            window: LossWindow::new(Float::from(initial_cwnd)),
+            base_rtt: None,
+            round_min_rtt: None,
+            round_start: Time::MIN,
+        }
+    }
+
+    fn end_round(&mut self, base_rtt: TimeSpan, rtt: TimeSpan, logger: &mut impl Logger) {
+        let cwnd = self.window.cwnd;
+        let queued = cwnd * (1. - base_rtt / rtt);
+        log!(logger, "Estimated {} packets queued", queued);
+        if self.window.in_slow_start() {
+            if queued > self.gamma {
+                self.window.cwnd = cwnd.min(cwnd * base_rtt / rtt + 1.);
+                self.window.ssthresh = self.window.cwnd;
+            } else {
+                self.window.cwnd += 1.;
+            }
+        } else if queued < self.alpha {
+            self.window.cwnd += 1.;
+        } else if queued > self.beta {
+            self.window.cwnd = (cwnd - 1.).max(2.);
+        }
+    }
+}
+
+impl Cca for VegasCca {
+    fn initial_cwnd(&self, _time: Time) -> u32 {
+        self.window.packets()
+    }
+
+    fn next_tick(&self, _time: Time) -> Option<Time> {
+        None
+    }
+
+    fn tick(&mut self, _rng: &mut Rng, _logger: &mut impl Logger) -> u32 {
+        panic!("Vegas never ticks!")
+    }
+
+    fn packet_sent(
+        &mut self,
+        _packet: PacketSent,
+        _rng: &mut Rng,
+        _logger: &mut impl Logger,
+    ) -> u32 {
+        self.window.packets()
+    }
+
+    fn ack_received(&mut self, ack: AckReceived, rng: &mut Rng, logger: &mut impl Logger) -> u32 {
+        let rtt = ack.received_time - ack.sent_time;
+        let base_rtt = *self
+            .base_rtt
+            .insert(self.base_rtt.map_or(rtt, |x| x.min(rtt)));
+        let round_min_rtt = *self
+            .round_min_rtt
+            .insert(self.round_min_rtt.map_or(rtt, |x| x.min(rtt)));
+        if !self.window.ack_received(&ack) {
+            return self.window.packets();
+        }
+        if let Some(cwnd) =
+            LossWindow::marked_loss(&ack).and_then(|loss| self.loss_detected(loss, rng, logger))
+        {
+            return cwnd;
+        }
+        if ack.sent_time >= self.round_start {
+            self.end_round(base_rtt, round_min_rtt, logger);
+            self.round_start = ack.received_time;
+            self.round_min_rtt = None;
+        } else if self.window.in_slow_start() {
+            self.window.cwnd += 1.;
+        }
+        self.window.packets()
+    }
+
+    fn loss_detected(
+        &mut self,
+        loss: LossDetected,
+        _rng: &mut Rng,
+        logger: &mut impl Logger,
+    ) -> Option<u32> {
+        if self.window.loss_detected(&loss) {
+            self.window.ssthresh = (self.window.cwnd / 2.).max(2.);
+            self.window.cwnd = match loss.cause {
+                LossCause::DuplicateAcks => self.window.ssthresh,
+                LossCause::Timeout => 1.,
+            };
+            log!(logger, "Reduced cwnd to {}", self.window.cwnd);
+        }
+        Some(self.window.packets())
+    }
+}
+
+#[derive(Debug)]
+pub struct VegasCcaTemplate {
+    pub initial_cwnd: u32,
+    /// Grow the window if fewer than this many packets are queued.
+    pub alpha: Float,
+    /// Shrink the window if more than this many packets are queued.
+    pub beta: Float,
+    /// Leave slow start if more than this many packets are queued.
+    pub gamma: Float,
+}
+
+impl Default for VegasCcaTemplate {
+    fn default() -> Self {
+        VegasCcaTemplate {
+            initial_cwnd: 10,
+            alpha: 2.,
+            beta: 4.,
+            gamma: 1.,
+        }
+    }
+}
+
+impl CcaTemplate<'_> for VegasCcaTemplate {
+    type Policy = ();
+    type Cca = VegasCca;
+
+    fn with(&self, (): ()) -> impl Fn() -> VegasCca + Sync {
+        let (initial_cwnd, alpha, beta, gamma) =
+            (self.initial_cwnd, self.alpha, self.beta, self.gamma);
+        move || VegasCca::new(initial_cwnd, alpha, beta, gamma)
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{quantities::Float, quantities::Time, AckReceived, LossCause, LossDetected};
+
+/// Congestion window with slow start and loss recovery, shared by the loss-based CCAs.
+///
+/// The window is only reduced once per round trip: after a loss, further losses are ignored until
+/// a packet sent after the loss was detected has been acknowledged.
+#[derive(Debug, Clone)]
+pub struct LossWindow {
+    pub cwnd: Float,
+    pub ssthresh: Float,
+    recovery_start: Option<Time>,
+}
+
+impl LossWindow {
+    #[must_use]
+    pub const fn new(initial_cwnd: Float) -> LossWindow {
+        LossWindow {
+            cwnd: initial_cwnd,
+            ssthresh: Float::INFINITY,
+            recovery_start: None,
+        }
+    }
+
+    #[must_use]
+    pub fn in_slow_start(&self) -> bool {
+        self.cwnd < self.ssthresh
+    }
+
+    /// Returns whether the window may grow in response to this ACK, which isn't the case while
+    /// recovering from a loss.
+    pub fn ack_received(&mut self, ack: &AckReceived) -> bool {
+        if self
+            .recovery_start
+            .is_some_and(|start| ack.sent_time >= start)
+        {
+            self.recovery_start = None;
+        }
+        self.recovery_start.is_none()
+    }
+
+    /// Returns whether the window should be reduced in response to this loss. Timeouts always
+    /// reduce the window.
+    pub fn loss_detected(&mut self, loss: &LossDetected) -> bool {
+        if self.recovery_start.is_some() && loss.cause == LossCause::DuplicateAcks {
+            return false;
+        }
+        self.recovery_start = Some(loss.detected_time);
+        true
+    }
+
+    /// ECN-capable senders respond to an ACK echoing congestion marks as they would to a single
+    /// lost packet (RFC 3168).
+    #[must_use]
+    pub const fn marked_loss(ack: &AckReceived) -> Option<LossDetected> {
+        if ack.marked == 0 {
+            return None;
+        }
+        Some(LossDetected {
+            seq: ack.seq,
+            sent_time: ack.sent_time,
+            detected_time: ack.received_time,
+            cause: LossCause::DuplicateAcks,
+        })
+    }
+
+    /// Grows the window by one packet per ACK in slow start, or by roughly one packet per round
+    /// trip otherwise.
+    pub fn reno_increase(&mut self) {
+        if self.in_slow_start() {
+            self.cwnd += 1.;
+        } else {
+            self.cwnd += 1. / self.cwnd;
+        }
+    }
+
+    #[must_use]
+    #[allow(clippy::cast_sign_loss)]
+    pub const fn packets(&self) -> u32 {
+        self.cwnd.max(1.) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        quantities::{milliseconds, packets, Time},
+        AckReceived, LossCause, LossDetected,
+    };
+
+    use super::LossWindow;
+
+    fn at(ms: f64) -> Time {
+        Time::SIM_START + milliseconds(ms)
+    }
+
+    fn loss(sent: f64, detected: f64, cause: LossCause) -> LossDetected {
+        LossDetected {
+            seq: 0,
+            sent_time: at(sent),
+            detected_time: at(detected),
+            cause,
+        }
+    }
+
+    fn ack(sent: f64, received: f64) -> AckReceived {
+        AckReceived {
+            seq: 0,
+            sent_time: at(sent),
+            received_time: at(received),
+            delivered: 1,
+            delivered_bytes: packets(1),
+            marked: 0,
+        }
+    }
+
+    #[test]
+    fn reduces_once_per_round_trip() {
+        let mut window = LossWindow::new(10.);
+        assert!(window.loss_detected(&loss(0., 100., LossCause::DuplicateAcks)));
+        assert!(!window.loss_detected(&loss(1., 101., LossCause::DuplicateAcks)));
+        // Sent before the loss was detected
+        assert!(!window.ack_received(&ack(50., 150.)));
+        assert!(window.ack_received(&ack(100., 200.)));
+        assert!(window.loss_detected(&loss(150., 250., LossCause::DuplicateAcks)));
+        assert!(window.loss_detected(&loss(150., 450., LossCause::Timeout)));
+    }
+}
@@ -5,21 +5,51 @@ use derive_where::derive_where;
 use crate::{
     quantities::{earliest_opt, latest, Information, InformationRate, Time, TimeSpan},
     simulation::{Component, Message},
-    util::logging::Logger,
+    util::logging::{DropReason, Event, Logger},
     util::rand::{ContinuousDistribution, Rng},
 };
 
-use super::packet::Packet;
+use super::{
+    mahimahi::{MahimahiTrace, OPPORTUNITY_SIZE},
+    packet::Packet,
+    queue::Queue,
+};
+
+/// How quickly a link can transmit packets.
+#[derive(Debug, Clone)]
+pub enum Capacity {
+    Rate(InformationRate),
+    /// Packets are only transmitted at the delivery opportunities in the trace.
+    Trace(MahimahiTrace),
+}
+
+impl Capacity {
+    #[must_use]
+    pub fn average_rate(&self) -> InformationRate {
+        match self {
+            Capacity::Rate(rate) => *rate,
+            Capacity::Trace(trace) => trace.average_rate(),
+        }
+    }
+}
+
+impl From<InformationRate> for Capacity {
+    fn from(rate: InformationRate) -> Self {
+        Capacity::Rate(rate)
+    }
+}
 
 #[derive_where(Debug; L)]
 pub struct Link<'sim, E, L> {
     delay: TimeSpan,
-    packet_rate: InformationRate,
+    capacity: Capacity,
     loss: f64,
-    buffer_size: Option<Information>,
     earliest_transmit: Time,
-    buffer_contains: Information,
-    buffer: VecDeque<Packet<'sim, E>>,
+    /// Index of the next delivery opportunity, if the capacity is given by a trace.
+    next_opportunity: u64,
+    /// Packet taken from the queue that didn't fit in the last delivery opportunity.
+    held: Option<Packet<'sim, E>>,
+    queue: Queue<'sim, E>,
     transmitting: VecDeque<(Packet<'sim, E>, Time)>,
     rng: Rng,
     logger: L,
@@ -38,14 +68,33 @@ where
         rng: Rng,
         logger: L,
     ) -> Self {
-        Link {
+        Self::with_queue(
             delay,
             packet_rate,
             loss,
-            buffer_size,
+            Queue::drop_tail(buffer_size),
+            rng,
+            logger,
+        )
+    }
+
+    #[must_use]
+    pub fn with_queue(
+        delay: TimeSpan,
+        capacity: impl Into<Capacity>,
+        loss: f64,
+        queue: Queue<'sim, E>,
+        rng: Rng,
+        logger: L,
+    ) -> Self {
+        Link {
+            delay,
+            capacity: capacity.into(),
+            loss,
             earliest_transmit: Time::MIN,
-            buffer_contains: Information::ZERO,
-            buffer: VecDeque::new(),
+            next_opportunity: 0,
+            held: None,
+            queue,
             transmitting: VecDeque::new(),
             rng,
             logger,
@@ -58,33 +107,75 @@ where
     L: Logger,
 {
     fn try_transmit(&mut self, time: Time) {
+        let packet_rate = match &self.capacity {
+            Capacity::Rate(packet_rate) => *packet_rate,
+            Capacity::Trace(trace) => {
+                if time < trace.opportunity(self.next_opportunity) {
+                    return;
+                }
+                self.next_opportunity += 1;
+                self.use_opportunity(time);
+                return;
+            }
+        };
         // If there is a planned buffer release then wait for it
         if time < self.earliest_transmit {
             return;
         }
 
-        if let Some(p) = self.buffer.pop_front() {
+        if let Some(p) = self.queue.dequeue(time, &mut self.logger) {
             // Don't transmit another packet until this time
-            self.earliest_transmit = time + p.size() / self.packet_rate;
-            self.buffer_contains = self.buffer_contains - p.size();
+            self.earliest_transmit = time + p.size() / packet_rate;
             self.transmitting.push_back((p, time + self.delay));
         }
     }
 
+    /// Transmits as many packets as fit in a delivery opportunity of a trace. Bytes left unused
+    /// are wasted, as in Mahimahi.
+    fn use_opportunity(&mut self, time: Time) {
+        let mut remaining = OPPORTUNITY_SIZE;
+        while let Some(p) = self
+            .held
+            .take()
+            .or_else(|| self.queue.dequeue(time, &mut self.logger))
+        {
+            // Packets larger than an opportunity are sent anyway, rather than blocking the link
+            if p.size() > remaining && remaining < OPPORTUNITY_SIZE {
+                self.held = Some(p);
+                return;
+            }
+            remaining = if p.size() > remaining {
+                Information::ZERO
+            } else {
+                remaining - p.size()
+            };
+            self.transmitting.push_back((p, time + self.delay));
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.held.is_none() && self.queue.is_empty()
+    }
+
     #[must_use]
     fn try_deliver(&mut self, time: Time) -> Option<Message<'sim, E>> {
         match self.transmitting.front() {
             Some((_, t)) if t == &time => {
                 let (mut packet, _) = self.transmitting.pop_front().unwrap();
+                let seq = packet.seq;
                 // Randomly drop packets to simulate loss
                 if self
                     .rng
                     .sample(&ContinuousDistribution::Uniform { min: 0., max: 1. })
                     < self.loss
                 {
-                    log!(self.logger, "Dropped packet (loss)");
+                    self.logger.event(time, || Event::PacketDropped {
+                        seq,
+                        reason: DropReason::Loss,
+                    });
                     None
                 } else {
+                    self.logger.event(time, || Event::PacketDelivered { seq });
                     let next_hop = packet.pop_next_hop();
                     Some(next_hop.create_message(packet))
                 }
@@ -110,23 +201,42 @@ where
         effects
     }
 
-    fn receive(&mut self, packet: Self::Receive, _time: Time) -> Vec<Message<'sim, E>> {
-        if self
-            .buffer_size
-            .is_some_and(|limit| self.buffer_contains + packet.size() > limit)
-        {
-            log!(self.logger, "Dropped packet (buffer full)");
-        } else {
-            log!(self.logger, "Buffered packet");
-            self.buffer_contains = self.buffer_contains + packet.size();
-            self.buffer.push_back(packet);
+    fn receive(&mut self, packet: Self::Receive, time: Time) -> Vec<Message<'sim, E>> {
+        if let Capacity::Trace(trace) = &self.capacity {
+            // Opportunities are wasted while there's nothing to send
+            if self.is_idle() {
+                self.next_opportunity = self
+                    .next_opportunity
+                    .max(trace.first_opportunity_from(time));
+            }
+        }
+        let seq = packet.seq;
+        match self.queue.enqueue(packet, time, &mut self.rng) {
+            Ok(dropped) => {
+                // FQ-CoDel may drop a packet from another flow to make room
+                if let Some(dropped) = dropped {
+                    self.logger.event(time, || Event::PacketDropped {
+                        seq: dropped.seq,
+                        reason: DropReason::Enqueue,
+                    });
+                }
+                self.logger.event(time, || Event::PacketEnqueued { seq });
+            }
+            Err(_) => {
+                self.logger.event(time, || Event::PacketDropped {
+                    seq,
+                    reason: DropReason::Enqueue,
+                });
+            }
         }
         vec![]
     }
 
     fn next_tick(&self, time: Time) -> Option<Time> {
-        let next_try_transmit = if self.buffer.is_empty() {
+        let next_try_transmit = if self.is_idle() {
             None
+        } else if let Capacity::Trace(trace) = &self.capacity {
+            Some(latest(&[time, trace.opportunity(self.next_opportunity)]))
         } else {
             Some(latest(&[time, self.earliest_transmit]))
         };
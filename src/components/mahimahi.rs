@@ -0,0 +1,162 @@
+use std::{
+    fmt::Debug,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::quantities::{bytes, milliseconds, Float, Information, InformationRate, Time, TimeSpan};
+
+/// Bytes that can be delivered at each opportunity in a Mahimahi trace.
+pub const OPPORTUNITY_SIZE: Information = bytes(1500);
+
+/// Packet delivery opportunities read from a Mahimahi trace file.
+///
+/// The file has one millisecond timestamp per line. Each opportunity can deliver up to
+/// `OPPORTUNITY_SIZE`, and the trace loops once its last timestamp has passed. Serialized as the
+/// path to the trace file, which is read when deserializing.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "PathBuf", into = "PathBuf")]
+pub struct MahimahiTrace {
+    path: PathBuf,
+    /// Offset of each opportunity from the start of the loop in milliseconds, in increasing order.
+    opportunities: Arc<[u64]>,
+    /// Length of each loop in milliseconds.
+    period: u64,
+}
+
+impl Debug for MahimahiTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MahimahiTrace")
+            .field("path", &self.path)
+            .field("opportunities", &self.opportunities.len())
+            .field("period", &self.period)
+            .finish()
+    }
+}
+
+impl MahimahiTrace {
+    pub fn load(path: impl AsRef<Path>) -> Result<MahimahiTrace> {
+        let path = path.as_ref();
+        let contents = read_to_string(path)
+            .with_context(|| format!("Failed to read trace file {}", path.display()))?;
+        let mut opportunities = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let timestamp: u64 = line.parse().with_context(|| {
+                format!("Invalid timestamp on line {} of {}", i + 1, path.display())
+            })?;
+            if opportunities.last().is_some_and(|&last| timestamp < last) {
+                return Err(anyhow!(
+                    "Timestamps in {} should be non-decreasing",
+                    path.display()
+                ));
+            }
+            opportunities.push(timestamp);
+        }
+        let period = match opportunities.last() {
+            Some(&last) if last > 0 => last,
+            _ => return Err(anyhow!("Trace {} has no duration", path.display())),
+        };
+        Ok(MahimahiTrace {
+            path: path.to_path_buf(),
+            opportunities: opportunities.into(),
+            period,
+        })
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[must_use]
+    pub fn average_rate(&self) -> InformationRate {
+        #[allow(clippy::cast_precision_loss)]
+        let opportunities = self.opportunities.len() as Float;
+        opportunities * (OPPORTUNITY_SIZE / to_time_span(self.period))
+    }
+
+    /// Time of the opportunity with the given index, counting from the start of the simulation
+    /// across loops of the trace.
+    #[must_use]
+    pub fn opportunity(&self, index: u64) -> Time {
+        let len = self.opportunities.len() as u64;
+        #[allow(clippy::cast_possible_truncation)]
+        let offset = self.opportunities[(index % len) as usize];
+        Time::SIM_START + to_time_span(index / len * self.period + offset)
+    }
+
+    /// Index of the first opportunity at or after `time`.
+    #[must_use]
+    pub fn first_opportunity_from(&self, time: Time) -> u64 {
+        let len = self.opportunities.len() as u64;
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let loops = ((time - Time::SIM_START) / to_time_span(self.period))
+            .floor()
+            .max(0.) as u64;
+        // The last opportunities of the previous loop may coincide with the start of this one
+        let (mut low, mut high) = (loops.saturating_sub(1) * len, (loops + 1) * len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.opportunity(mid) < time {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn to_time_span(ms: u64) -> TimeSpan {
+    milliseconds(ms as Float)
+}
+
+impl TryFrom<PathBuf> for MahimahiTrace {
+    type Error = anyhow::Error;
+
+    fn try_from(path: PathBuf) -> Result<Self> {
+        MahimahiTrace::load(path)
+    }
+}
+
+impl From<MahimahiTrace> for PathBuf {
+    fn from(trace: MahimahiTrace) -> Self {
+        trace.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tempfile::NamedTempFile;
+
+    use crate::quantities::{milliseconds, Time};
+
+    use super::MahimahiTrace;
+
+    #[test]
+    fn loops_opportunities() -> anyhow::Result<()> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "3\n3\n10")?;
+        let trace = MahimahiTrace::load(file.path())?;
+        let at = |ms| Time::SIM_START + milliseconds(ms);
+        let times = (0..7).map(|i| trace.opportunity(i)).collect::<Vec<_>>();
+        assert_eq!(times, [3., 3., 10., 13., 13., 20., 23.].map(at).to_vec());
+        assert_eq!(trace.first_opportunity_from(at(0.)), 0);
+        assert_eq!(trace.first_opportunity_from(at(4.)), 2);
+        assert_eq!(trace.first_opportunity_from(at(10.)), 2);
+        assert_eq!(trace.first_opportunity_from(at(10.5)), 3);
+        assert_eq!(trace.first_opportunity_from(at(21.)), 6);
+        Ok(())
+    }
+}
@@ -1,6 +1,11 @@
 pub mod bouncer;
 pub mod link;
+pub mod router;
 pub mod senders;
 pub mod ticker;
 pub mod toggler;
 pub mod packet;
+pub mod receiver;
+pub mod queue;
+pub mod mahimahi;
+pub mod workload;
@@ -1,28 +1,89 @@
+use std::rc::Rc;
+
 use derive_where::derive_where;
 
 use crate::{
-    quantities::{packets, Information, Time},
+    quantities::{bytes, Information, Time},
     simulation::Address,
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketKind {
+    Data {
+        /// The receiver should not wait for any packets before `first_seq`, as the sender will
+        /// never send them.
+        first_seq: u64,
+        size: Information,
+    },
+    /// Acknowledges the data packet with the same `seq` and `sent_time`.
+    Ack {
+        /// Every data packet up to and including `cumulative` has been received.
+        cumulative: u64,
+        /// Number of data packets received since the previous ACK.
+        packets: u64,
+        /// Total size of those data packets.
+        bytes: Information,
+        /// Number of those data packets that arrived marked with congestion experienced.
+        marked: u64,
+    },
+}
+
+/// ECN codepoint, as in RFC 3168.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Ecn {
+    /// The sender doesn't understand ECN, so congestion can only be signalled by dropping.
+    #[default]
+    NotEct,
+    /// ECN-capable transport.
+    Ect,
+    /// Congestion experienced.
+    Ce,
+}
+
 #[derive_where(Debug)]
 pub struct Packet<'sim, E> {
     pub(super) seq: u64,
     pub(super) source: Address<'sim, Packet<'sim, E>, E>,
     pub(super) destination: Address<'sim, Packet<'sim, E>, E>,
+    /// Hops to visit before being delivered to `destination`.
+    pub(super) hops: Rc<[PacketAddress<'sim, E>]>,
+    pub(super) next_hop: usize,
     pub(super) sent_time: Time,
+    pub(super) kind: PacketKind,
+    pub(super) ecn: Ecn,
 }
 
 impl<'sim, E> Packet<'sim, E> {
     pub fn pop_next_hop(&mut self) -> Address<'sim, Packet<'sim, E>, E> {
-        self.destination.clone()
+        if let Some(hop) = self.hops.get(self.next_hop) {
+            self.next_hop += 1;
+            hop.clone()
+        } else {
+            self.destination.clone()
+        }
+    }
+
+    /// Marks the packet as having experienced congestion, returning false if the packet isn't
+    /// ECN-capable and should be dropped instead.
+    pub fn mark_congestion(&mut self) -> bool {
+        if self.ecn == Ecn::NotEct {
+            false
+        } else {
+            self.ecn = Ecn::Ce;
+            true
+        }
     }
 
-    #[allow(clippy::unused_self)]
     #[must_use]
     pub const fn size(&self) -> Information {
-        packets(1)
+        match self.kind {
+            PacketKind::Data { size, .. } => size,
+            PacketKind::Ack { .. } => ACK_SIZE,
+        }
     }
 }
 
+/// Size of a bare TCP/IP header.
+pub const ACK_SIZE: Information = bytes(40);
+
 pub type PacketAddress<'sim, E> = Address<'sim, Packet<'sim, E>, E>;
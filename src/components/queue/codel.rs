@@ -0,0 +1,258 @@
+use derive_where::derive_where;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::packet::Packet,
+    quantities::{milliseconds, packets, Float, Information, Time, TimeSpan},
+    util::logging::{DropReason, Event, Logger},
+};
+
+use super::Fifo;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CoDelConfig {
+    /// Acceptable standing queue delay.
+    pub target: TimeSpan,
+    /// Queue delay has to stay above `target` for this long before packets are dropped.
+    pub interval: TimeSpan,
+    /// Mark ECN-capable packets instead of dropping them.
+    pub ecn: bool,
+}
+
+impl Default for CoDelConfig {
+    fn default() -> Self {
+        CoDelConfig {
+            target: milliseconds(5.),
+            interval: milliseconds(100.),
+            ecn: false,
+        }
+    }
+}
+
+/// Dropping state of a `CoDel` queue, following `codel_dequeue` in Linux.
+#[derive(Debug, Default)]
+pub(super) struct CoDelState {
+    first_above_time: Option<Time>,
+    drop_next: Option<Time>,
+    count: u32,
+    last_count: u32,
+    dropping: bool,
+}
+
+impl CoDelState {
+    fn should_drop(
+        &mut self,
+        config: &CoDelConfig,
+        enqueued: Time,
+        backlog: Information,
+        time: Time,
+    ) -> bool {
+        if time - enqueued < config.target || backlog <= packets(1) {
+            self.first_above_time = None;
+            return false;
+        }
+        if let Some(first_above_time) = self.first_above_time {
+            time >= first_above_time
+        } else {
+            self.first_above_time = Some(time + config.interval);
+            false
+        }
+    }
+
+    fn control_law(&self, config: &CoDelConfig, time: Time) -> Time {
+        time + config.interval / Float::from(self.count).sqrt()
+    }
+
+    /// Pops the next packet to transmit from `fifo`, dropping packets ahead of it that have
+    /// spent too long in the queue.
+    pub(super) fn dequeue<'sim, E>(
+        &mut self,
+        config: &CoDelConfig,
+        fifo: &mut Fifo<'sim, E>,
+        time: Time,
+        logger: &mut impl Logger,
+    ) -> Option<Packet<'sim, E>> {
+        let Some((mut packet, enqueued)) = fifo.pop() else {
+            self.first_above_time = None;
+            self.dropping = false;
+            return None;
+        };
+        let drop = self.should_drop(config, enqueued, fifo.bytes, time);
+        if self.dropping {
+            if !drop {
+                self.dropping = false;
+                return Some(packet);
+            }
+            while self.dropping && self.drop_next.is_some_and(|next| time >= next) {
+                self.count += 1;
+                let drop_next = self.control_law(config, self.drop_next.unwrap());
+                if config.ecn && packet.mark_congestion() {
+                    self.drop_next = Some(drop_next);
+                    return Some(packet);
+                }
+                let seq = packet.seq;
+                logger.event(time, || Event::PacketDropped {
+                    seq,
+                    reason: DropReason::Dequeue,
+                });
+                let Some((next, enqueued)) = fifo.pop() else {
+                    self.first_above_time = None;
+                    self.dropping = false;
+                    return None;
+                };
+                packet = next;
+                if self.should_drop(config, enqueued, fifo.bytes, time) {
+                    self.drop_next = Some(drop_next);
+                } else {
+                    self.dropping = false;
+                }
+            }
+        } else if drop {
+            if !(config.ecn && packet.mark_congestion()) {
+                let seq = packet.seq;
+                logger.event(time, || Event::PacketDropped {
+                    seq,
+                    reason: DropReason::Dequeue,
+                });
+                let (next, enqueued) = fifo.pop()?;
+                packet = next;
+                self.should_drop(config, enqueued, fifo.bytes, time);
+            }
+            self.dropping = true;
+            // Resume at a higher drop rate if we were dropping recently
+            let delta = self.count.saturating_sub(self.last_count);
+            self.count = if delta > 1
+                && self
+                    .drop_next
+                    .is_some_and(|next| time - next < 16. * config.interval)
+            {
+                delta
+            } else {
+                1
+            };
+            self.last_count = self.count;
+            self.drop_next = Some(self.control_law(config, time));
+        }
+        Some(packet)
+    }
+}
+
+/// Controlled Delay (RFC 8289), which drops packets at the head of the queue while the minimum
+/// queue delay stays above a target.
+#[derive_where(Debug)]
+pub struct CoDel<'sim, E> {
+    config: CoDelConfig,
+    fifo: Fifo<'sim, E>,
+    limit: Option<Information>,
+    state: CoDelState,
+}
+
+impl<'sim, E> CoDel<'sim, E> {
+    #[must_use]
+    pub fn new(config: &CoDelConfig, limit: Option<Information>) -> CoDel<'sim, E> {
+        CoDel {
+            config: config.clone(),
+            fifo: Fifo::default(),
+            limit,
+            state: CoDelState::default(),
+        }
+    }
+
+    pub fn enqueue(&mut self, packet: Packet<'sim, E>, time: Time) -> Result<(), Packet<'sim, E>> {
+        if self.fifo.would_overflow(&packet, self.limit) {
+            return Err(packet);
+        }
+        self.fifo.push(packet, time);
+        Ok(())
+    }
+
+    pub fn dequeue(&mut self, time: Time, logger: &mut impl Logger) -> Option<Packet<'sim, E>> {
+        self.state
+            .dequeue(&self.config, &mut self.fifo, time, logger)
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.fifo.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use generativity::make_guard;
+
+    use crate::{
+        components::{
+            link::Link,
+            packet::{Ecn, Packet, PacketKind},
+        },
+        quantities::{milliseconds, packets, packets_per_second, Time},
+        simulation::SimulatorBuilder,
+        trainers::DefaultEffect,
+        util::{logging::NothingLogger, rand::Rng},
+    };
+
+    use super::{CoDel, CoDelConfig};
+
+    #[test]
+    fn signals_once_queue_delay_stays_above_target() {
+        for ecn in [false, true] {
+            make_guard!(guard);
+            let builder = SimulatorBuilder::<DefaultEffect>::new(guard);
+            let address = builder.insert(Link::create(
+                milliseconds(1.),
+                packets_per_second(1.),
+                0.,
+                None,
+                Rng::from_seed(0),
+                NothingLogger,
+            ));
+            let mut codel = CoDel::new(
+                &CoDelConfig {
+                    ecn,
+                    ..CoDelConfig::default()
+                },
+                None,
+            );
+            for seq in 0..100 {
+                let packet = Packet {
+                    seq,
+                    source: address.clone(),
+                    destination: address.clone(),
+                    hops: Rc::new([]),
+                    next_hop: 0,
+                    sent_time: Time::SIM_START,
+                    kind: PacketKind::Data {
+                        first_seq: 0,
+                        size: packets(1),
+                    },
+                    ecn: Ecn::Ect,
+                };
+                codel.enqueue(packet, Time::SIM_START).unwrap();
+            }
+
+            // Transmit a packet every 10ms, so the standing queue never drains
+            let mut expected_seq = 0;
+            let mut signalled_at = None;
+            for i in 1..50 {
+                let time = Time::SIM_START + milliseconds(f64::from(i) * 10.);
+                let packet = codel.dequeue(time, &mut NothingLogger).unwrap();
+                let signalled = packet.seq != expected_seq || packet.ecn == Ecn::Ce;
+                if signalled {
+                    signalled_at.get_or_insert(time);
+                }
+                if ecn {
+                    assert_eq!(packet.seq, expected_seq);
+                }
+                expected_seq = packet.seq + 1;
+            }
+            assert_eq!(
+                signalled_at,
+                Some(Time::SIM_START + milliseconds(110.)),
+                "ECN: {ecn}"
+            );
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::VecDeque;
+
+use derive_where::derive_where;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::packet::Packet,
+    quantities::{packets, Information, Time},
+    simulation::Address,
+    util::logging::Logger,
+};
+
+use super::{
+    codel::{CoDelConfig, CoDelState},
+    Fifo,
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FqCoDelConfig {
+    /// Number of queues that flows are hashed into.
+    pub flows: usize,
+    /// Bytes each queue may send per round of deficit round robin.
+    pub quantum: Information,
+    /// Parameters of the `CoDel` instance managing each queue.
+    pub codel: CoDelConfig,
+}
+
+impl Default for FqCoDelConfig {
+    fn default() -> Self {
+        FqCoDelConfig {
+            flows: 1024,
+            quantum: packets(1),
+            codel: CoDelConfig::default(),
+        }
+    }
+}
+
+#[derive_where(Debug)]
+struct Flow<'sim, E> {
+    fifo: Fifo<'sim, E>,
+    deficit: i64,
+    codel: CoDelState,
+    /// Whether the flow is in either the new or old list.
+    active: bool,
+}
+
+/// Flow queueing with `CoDel` (RFC 8290). Packets are classified by their source, and each queue
+/// is served by deficit round robin, with newly active queues served first.
+#[derive_where(Debug)]
+pub struct FqCoDel<'sim, E> {
+    config: FqCoDelConfig,
+    limit: Option<Information>,
+    backlog: Information,
+    /// Sources seen so far, whose indices are hashed into `flows`.
+    sources: Vec<Address<'sim, Packet<'sim, E>, E>>,
+    flows: Vec<Flow<'sim, E>>,
+    new_flows: VecDeque<usize>,
+    old_flows: VecDeque<usize>,
+}
+
+#[allow(clippy::cast_possible_wrap)]
+const fn to_signed(x: Information) -> i64 {
+    x.bytes() as i64
+}
+
+impl<'sim, E> FqCoDel<'sim, E> {
+    #[must_use]
+    pub fn new(config: &FqCoDelConfig, limit: Option<Information>) -> FqCoDel<'sim, E> {
+        assert!(config.flows > 0, "FQ-CoDel needs at least one queue");
+        FqCoDel {
+            config: config.clone(),
+            limit,
+            backlog: Information::ZERO,
+            sources: Vec::new(),
+            flows: Vec::new(),
+            new_flows: VecDeque::new(),
+            old_flows: VecDeque::new(),
+        }
+    }
+
+    fn classify(&mut self, packet: &Packet<'sim, E>) -> usize {
+        let index = self
+            .sources
+            .iter()
+            .position(|source| source.same_as(&packet.source))
+            .unwrap_or_else(|| {
+                self.sources.push(packet.source.clone());
+                self.sources.len() - 1
+            });
+        let bucket = index % self.config.flows;
+        if bucket == self.flows.len() {
+            self.flows.push(Flow {
+                fifo: Fifo::default(),
+                deficit: 0,
+                codel: CoDelState::default(),
+                active: false,
+            });
+        }
+        bucket
+    }
+
+    /// Adds a packet to its flow's queue. If the buffer is full, the packet at the head of the
+    /// longest queue is dropped and returned instead, unless that queue is the packet's own, in
+    /// which case the packet is returned as an error.
+    pub fn enqueue(
+        &mut self,
+        packet: Packet<'sim, E>,
+        time: Time,
+    ) -> Result<Option<Packet<'sim, E>>, Packet<'sim, E>> {
+        let bucket = self.classify(&packet);
+        let dropped = if self
+            .limit
+            .is_some_and(|limit| self.backlog + packet.size() > limit)
+        {
+            let backlog = |i: usize, flow: &Flow<'sim, E>| {
+                let bytes = if i == bucket {
+                    flow.fifo.bytes + packet.size()
+                } else {
+                    flow.fifo.bytes
+                };
+                bytes.bytes()
+            };
+            let (fattest, _) = self
+                .flows
+                .iter()
+                .enumerate()
+                .max_by_key(|&(i, flow)| backlog(i, flow))
+                .expect("a flow to have been classified");
+            let freed = self.flows[fattest]
+                .fifo
+                .packets
+                .front()
+                .map_or(Information::ZERO, |(head, _)| head.size());
+            if fattest == bucket || freed < packet.size() {
+                return Err(packet);
+            }
+            let (head, _) = self.flows[fattest].fifo.pop().unwrap();
+            self.backlog = self.backlog - head.size();
+            Some(head)
+        } else {
+            None
+        };
+
+        self.backlog = self.backlog + packet.size();
+        let flow = &mut self.flows[bucket];
+        flow.fifo.push(packet, time);
+        if !flow.active {
+            flow.active = true;
+            flow.deficit = to_signed(self.config.quantum);
+            self.new_flows.push_back(bucket);
+        }
+        Ok(dropped)
+    }
+
+    pub fn dequeue(&mut self, time: Time, logger: &mut impl Logger) -> Option<Packet<'sim, E>> {
+        loop {
+            let from_new = !self.new_flows.is_empty();
+            let bucket = if from_new {
+                self.new_flows.pop_front().unwrap()
+            } else {
+                self.old_flows.pop_front()?
+            };
+            let flow = &mut self.flows[bucket];
+            if flow.deficit <= 0 {
+                flow.deficit += to_signed(self.config.quantum);
+                self.old_flows.push_back(bucket);
+                continue;
+            }
+
+            let before = flow.fifo.bytes;
+            let packet = flow
+                .codel
+                .dequeue(&self.config.codel, &mut flow.fifo, time, logger);
+            self.backlog = self.backlog - (before - flow.fifo.bytes);
+            if let Some(packet) = packet {
+                flow.deficit -= to_signed(packet.size());
+                if from_new {
+                    self.new_flows.push_front(bucket);
+                } else {
+                    self.old_flows.push_front(bucket);
+                }
+                return Some(packet);
+            }
+
+            if from_new && !self.old_flows.is_empty() {
+                // Move the empty flow behind the old flows, so that it can't starve them by
+                // repeatedly becoming new
+                self.old_flows.push_back(bucket);
+            } else {
+                flow.active = false;
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.backlog == Information::ZERO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use generativity::make_guard;
+
+    use crate::{
+        components::{
+            link::Link,
+            packet::{Ecn, Packet, PacketKind},
+            queue::Queue,
+        },
+        quantities::{milliseconds, packets, packets_per_second, Time},
+        simulation::{Component, SimulatorBuilder},
+        trainers::DefaultEffect,
+        util::{
+            logging::{DropReason, Event, EventBuffer, NothingLogger},
+            rand::Rng,
+        },
+    };
+
+    use super::{FqCoDel, FqCoDelConfig};
+
+    #[test]
+    fn link_logs_the_packet_dropped_to_make_room() {
+        make_guard!(guard);
+        let builder = SimulatorBuilder::<DefaultEffect>::new(guard);
+        let [first, second] = [0, 1].map(|_| {
+            builder.insert(Link::create(
+                milliseconds(1.),
+                packets_per_second(1.),
+                0.,
+                None,
+                Rng::from_seed(0),
+                NothingLogger,
+            ))
+        });
+        let events = EventBuffer::new();
+        let mut link = Link::with_queue(
+            milliseconds(1.),
+            packets_per_second(1.),
+            0.,
+            Queue::FqCoDel(FqCoDel::new(&FqCoDelConfig::default(), Some(packets(3)))),
+            Rng::from_seed(0),
+            events.logger(0),
+        );
+        let packet = |seq, source| Packet {
+            seq,
+            source,
+            destination: first.clone(),
+            hops: Rc::new([]),
+            next_hop: 0,
+            sent_time: Time::SIM_START,
+            kind: PacketKind::Data {
+                first_seq: 0,
+                size: packets(1),
+            },
+            ecn: Ecn::NotEct,
+        };
+        for seq in 0..3 {
+            link.receive(packet(seq, first.clone()), Time::SIM_START);
+        }
+        // Makes room by dropping the head of the first flow, which is the longest
+        link.receive(packet(7, second.clone()), Time::SIM_START);
+        // The first flow is still the longest, so its own packet is dropped
+        link.receive(packet(3, first.clone()), Time::SIM_START);
+        drop(link);
+
+        let dropped = |seq| Event::PacketDropped {
+            seq,
+            reason: DropReason::Enqueue,
+        };
+        let enqueued = |seq| Event::PacketEnqueued { seq };
+        assert_eq!(
+            events
+                .into_events()
+                .into_iter()
+                .map(|logged| logged.event)
+                .collect::<Vec<_>>(),
+            [
+                enqueued(0),
+                enqueued(1),
+                enqueued(2),
+                dropped(0),
+                enqueued(7),
+                dropped(3),
+            ]
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::VecDeque;
+
+use derive_where::derive_where;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    quantities::{Information, InformationRate, Time},
+    util::{logging::Logger, rand::Rng},
+};
+
+use self::{
+    codel::{CoDel, CoDelConfig},
+    fq_codel::{FqCoDel, FqCoDelConfig},
+    red::{Red, RedConfig},
+};
+
+use super::packet::Packet;
+
+pub mod codel;
+pub mod fq_codel;
+pub mod red;
+
+/// Queue discipline used by a link to decide which packets to drop or mark. Every discipline also
+/// drops packets that would overflow the link's buffer.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QueueConfig {
+    #[default]
+    DropTail,
+    /// Drop-tail queue which signals congestion to packets arriving while at least `threshold`
+    /// is queued, as used by DCTCP. ECN-capable packets are marked, and other packets dropped.
+    Threshold {
+        threshold: Information,
+    },
+    Red(RedConfig),
+    #[serde(rename = "codel")]
+    CoDel(CoDelConfig),
+    #[serde(rename = "fq_codel")]
+    FqCoDel(FqCoDelConfig),
+}
+
+impl QueueConfig {
+    #[must_use]
+    pub fn build<'sim, E>(
+        &self,
+        buffer_size: Option<Information>,
+        packet_rate: InformationRate,
+    ) -> Queue<'sim, E> {
+        match self {
+            QueueConfig::DropTail => Queue::drop_tail(buffer_size),
+            QueueConfig::Threshold { threshold } => Queue::DropTail(DropTail {
+                fifo: Fifo::default(),
+                limit: buffer_size,
+                threshold: Some(*threshold),
+            }),
+            QueueConfig::Red(config) => Queue::Red(Red::new(config, buffer_size, packet_rate)),
+            QueueConfig::CoDel(config) => Queue::CoDel(CoDel::new(config, buffer_size)),
+            QueueConfig::FqCoDel(config) => Queue::FqCoDel(FqCoDel::new(config, buffer_size)),
+        }
+    }
+}
+
+/// Packets waiting to be transmitted by a link, along with the time they were enqueued.
+#[derive_where(Debug)]
+struct Fifo<'sim, E> {
+    packets: VecDeque<(Packet<'sim, E>, Time)>,
+    bytes: Information,
+}
+
+impl<E> Default for Fifo<'_, E> {
+    fn default() -> Self {
+        Fifo {
+            packets: VecDeque::new(),
+            bytes: Information::ZERO,
+        }
+    }
+}
+
+impl<'sim, E> Fifo<'sim, E> {
+    fn push(&mut self, packet: Packet<'sim, E>, time: Time) {
+        self.bytes = self.bytes + packet.size();
+        self.packets.push_back((packet, time));
+    }
+
+    fn pop(&mut self) -> Option<(Packet<'sim, E>, Time)> {
+        let (packet, time) = self.packets.pop_front()?;
+        self.bytes = self.bytes - packet.size();
+        Some((packet, time))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    fn would_overflow(&self, packet: &Packet<'sim, E>, limit: Option<Information>) -> bool {
+        limit.is_some_and(|limit| self.bytes + packet.size() > limit)
+    }
+}
+
+#[derive_where(Debug)]
+pub struct DropTail<'sim, E> {
+    fifo: Fifo<'sim, E>,
+    limit: Option<Information>,
+    threshold: Option<Information>,
+}
+
+#[derive_where(Debug)]
+pub enum Queue<'sim, E> {
+    DropTail(DropTail<'sim, E>),
+    Red(Red<'sim, E>),
+    CoDel(CoDel<'sim, E>),
+    FqCoDel(FqCoDel<'sim, E>),
+}
+
+impl<'sim, E> Queue<'sim, E> {
+    #[must_use]
+    pub fn drop_tail(buffer_size: Option<Information>) -> Queue<'sim, E> {
+        Queue::DropTail(DropTail {
+            fifo: Fifo::default(),
+            limit: buffer_size,
+            threshold: None,
+        })
+    }
+
+    /// Adds a packet to the queue, or returns it as an error if it was dropped. If another packet
+    /// was dropped to make room for it instead, returns that packet.
+    pub fn enqueue(
+        &mut self,
+        mut packet: Packet<'sim, E>,
+        time: Time,
+        rng: &mut Rng,
+    ) -> Result<Option<Packet<'sim, E>>, Packet<'sim, E>> {
+        match self {
+            Queue::DropTail(DropTail {
+                fifo,
+                limit,
+                threshold,
+            }) => {
+                let congested = threshold.is_some_and(|threshold| fifo.bytes >= threshold);
+                if fifo.would_overflow(&packet, *limit) || (congested && !packet.mark_congestion())
+                {
+                    return Err(packet);
+                }
+                fifo.push(packet, time);
+                Ok(None)
+            }
+            Queue::Red(queue) => queue.enqueue(packet, time, rng).map(|()| None),
+            Queue::CoDel(queue) => queue.enqueue(packet, time).map(|()| None),
+            Queue::FqCoDel(queue) => queue.enqueue(packet, time),
+        }
+    }
+
+    /// Removes the next packet to transmit, dropping any packets that the discipline decides not
+    /// to transmit on the way.
+    pub fn dequeue(&mut self, time: Time, logger: &mut impl Logger) -> Option<Packet<'sim, E>> {
+        match self {
+            Queue::DropTail(DropTail { fifo, .. }) => fifo.pop().map(|(packet, _)| packet),
+            Queue::Red(queue) => queue.dequeue(time),
+            Queue::CoDel(queue) => queue.dequeue(time, logger),
+            Queue::FqCoDel(queue) => queue.dequeue(time, logger),
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Queue::DropTail(DropTail { fifo, .. }) => fifo.is_empty(),
+            Queue::Red(queue) => queue.is_empty(),
+            Queue::CoDel(queue) => queue.is_empty(),
+            Queue::FqCoDel(queue) => queue.is_empty(),
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use derive_where::derive_where;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::packet::Packet,
+    quantities::{packets, Float, Information, InformationRate, Time, TimeSpan},
+    util::rand::{ContinuousDistribution, Rng},
+};
+
+use super::Fifo;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RedConfig {
+    /// Packets may be dropped once the average queue length reaches this.
+    pub min_threshold: Information,
+    /// Every packet is dropped once the average queue length reaches this.
+    pub max_threshold: Information,
+    /// Drop probability when the average queue length is just below `max_threshold`.
+    pub max_probability: Float,
+    /// Weight given to the current queue length when updating the average.
+    pub weight: Float,
+    /// Mark ECN-capable packets instead of dropping them, below `max_threshold`.
+    pub ecn: bool,
+}
+
+impl Default for RedConfig {
+    fn default() -> Self {
+        RedConfig {
+            min_threshold: packets(5),
+            max_threshold: packets(15),
+            max_probability: 0.1,
+            weight: 0.002,
+            ecn: false,
+        }
+    }
+}
+
+/// Random Early Detection, as described by Floyd and Jacobson (1993).
+#[derive_where(Debug)]
+pub struct Red<'sim, E> {
+    config: RedConfig,
+    fifo: Fifo<'sim, E>,
+    limit: Option<Information>,
+    /// Time taken to transmit a typical packet, used to decay the average while idle.
+    transmission_time: TimeSpan,
+    /// Average queue length, in bytes.
+    average: Float,
+    /// Packets enqueued since the last drop, or `None` if the average is below `min_threshold`.
+    count: Option<u32>,
+    idle_since: Option<Time>,
+}
+
+#[allow(clippy::cast_precision_loss)]
+const fn to_float(x: Information) -> Float {
+    x.bytes() as Float
+}
+
+impl<'sim, E> Red<'sim, E> {
+    #[must_use]
+    pub fn new(
+        config: &RedConfig,
+        limit: Option<Information>,
+        packet_rate: InformationRate,
+    ) -> Red<'sim, E> {
+        Red {
+            config: config.clone(),
+            fifo: Fifo::default(),
+            limit,
+            transmission_time: packets(1) / packet_rate,
+            average: 0.,
+            count: None,
+            idle_since: Some(Time::SIM_START),
+        }
+    }
+
+    fn update_average(&mut self, time: Time) {
+        let weight = self.config.weight;
+        self.average = if let Some(idle_since) = self.idle_since.take() {
+            // Decay as if small packets had been transmitted while the queue was idle
+            let m = (time - idle_since) / self.transmission_time;
+            self.average * (1. - weight).powf(m)
+        } else {
+            (1. - weight).mul_add(self.average, weight * to_float(self.fifo.bytes))
+        };
+    }
+
+    /// Returns whether to signal congestion for an arriving packet, and whether the signal has
+    /// to be a drop.
+    fn congestion(&mut self, rng: &mut Rng) -> Option<bool> {
+        let (min, max) = (
+            to_float(self.config.min_threshold),
+            to_float(self.config.max_threshold),
+        );
+        if self.average < min {
+            self.count = None;
+            return None;
+        }
+        if self.average >= max {
+            self.count = Some(0);
+            return Some(true);
+        }
+        let count = self.count.map_or(0, |count| count + 1);
+        let pb = self.config.max_probability * (self.average - min) / (max - min);
+        // Signal for certain once `count * pb` reaches 1, where the formula for `pa` breaks down
+        let remaining = Float::from(count).mul_add(-pb, 1.);
+        let pa = if remaining > 0. { pb / remaining } else { 1. };
+        if rng.sample(&ContinuousDistribution::Uniform { min: 0., max: 1. }) >= pa {
+            self.count = Some(count);
+            return None;
+        }
+        self.count = Some(0);
+        Some(!self.config.ecn)
+    }
+
+    pub fn enqueue(
+        &mut self,
+        mut packet: Packet<'sim, E>,
+        time: Time,
+        rng: &mut Rng,
+    ) -> Result<(), Packet<'sim, E>> {
+        self.update_average(time);
+        let drop = self
+            .congestion(rng)
+            .is_some_and(|must_drop| must_drop || !packet.mark_congestion());
+        if drop || self.fifo.would_overflow(&packet, self.limit) {
+            if self.fifo.is_empty() {
+                self.idle_since = Some(time);
+            }
+            return Err(packet);
+        }
+        self.fifo.push(packet, time);
+        Ok(())
+    }
+
+    pub fn dequeue(&mut self, time: Time) -> Option<Packet<'sim, E>> {
+        let (packet, _) = self.fifo.pop()?;
+        if self.fifo.is_empty() {
+            self.idle_since = Some(time);
+        }
+        Some(packet)
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.fifo.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        quantities::{packets, packets_per_second},
+        trainers::DefaultEffect,
+        util::rand::Rng,
+    };
+
+    use super::{to_float, Red, RedConfig};
+
+    #[test]
+    fn signals_once_count_times_pb_reaches_one() {
+        let config = RedConfig::default();
+        let mut red = Red::<DefaultEffect>::new(&config, None, packets_per_second(100.));
+        let mut rng = Rng::from_seed(0);
+        // Just above the minimum threshold, pb is small and the count grows large
+        red.average = to_float(config.min_threshold) + 10.;
+        for _ in 0..200 {
+            red.congestion(&mut rng);
+        }
+        // Halfway between the thresholds, pb is 0.05, so the count times pb is already above 1
+        red.average = to_float(packets(10));
+        let mut since_signal = 0;
+        for _ in 0..1000 {
+            if red.congestion(&mut rng).is_some() {
+                since_signal = 0;
+            } else {
+                since_signal += 1;
+                assert!(since_signal < 20, "RED stopped signalling congestion");
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use std::{collections::BTreeSet, rc::Rc};
+
+use derive_where::derive_where;
+
+use crate::{
+    quantities::{Information, Time, TimeSpan},
+    simulation::{Component, Message},
+    util::logging::Logger,
+};
+
+use super::packet::{Ecn, Packet, PacketAddress, PacketKind};
+
+#[derive(Debug, Clone, Copy)]
+struct PendingAck {
+    seq: u64,
+    sent_time: Time,
+    packets: u64,
+    bytes: Information,
+    marked: u64,
+    deadline: Time,
+}
+
+/// Acknowledges data packets with cumulative ACKs, which are sent back to the data packet's
+/// source along `route`.
+///
+/// If `ack_every` is greater than one, ACKs are delayed until that many data packets have arrived
+/// or `ack_timeout` has passed since the first unacknowledged one. Out-of-order packets are
+/// always acknowledged immediately.
+#[derive_where(Debug; L)]
+pub struct Receiver<'sim, E, L> {
+    id: PacketAddress<'sim, E>,
+    link: PacketAddress<'sim, E>,
+    hops: Rc<[PacketAddress<'sim, E>]>,
+    ack_every: u32,
+    ack_timeout: TimeSpan,
+    cumulative: u64,
+    out_of_order: BTreeSet<u64>,
+    source: Option<PacketAddress<'sim, E>>,
+    pending: Option<PendingAck>,
+    logger: L,
+}
+
+impl<'sim, E, L> Receiver<'sim, E, L>
+where
+    L: Logger,
+{
+    #[must_use]
+    pub fn new(
+        id: PacketAddress<'sim, E>,
+        route: Vec<PacketAddress<'sim, E>>,
+        ack_every: u32,
+        ack_timeout: TimeSpan,
+        logger: L,
+    ) -> Receiver<'sim, E, L> {
+        assert!(ack_every > 0);
+        let mut route = route.into_iter();
+        Receiver {
+            id,
+            link: route.next().expect("Route to be non-empty"),
+            hops: route.collect(),
+            ack_every,
+            ack_timeout,
+            cumulative: 0,
+            out_of_order: BTreeSet::new(),
+            source: None,
+            pending: None,
+            logger,
+        }
+    }
+
+    /// Returns whether the packet arrived out of order, in which case it should be
+    /// acknowledged immediately.
+    fn record(&mut self, seq: u64, first_seq: u64) -> bool {
+        if first_seq > self.cumulative + 1 {
+            self.cumulative = first_seq - 1;
+            self.out_of_order = self.out_of_order.split_off(&first_seq);
+        }
+        let in_order = seq == self.cumulative + 1 && self.out_of_order.is_empty();
+        if seq == self.cumulative + 1 {
+            self.cumulative = seq;
+            while self.out_of_order.remove(&(self.cumulative + 1)) {
+                self.cumulative += 1;
+            }
+        } else if seq > self.cumulative {
+            self.out_of_order.insert(seq);
+        }
+        !in_order
+    }
+
+    fn send_ack(&mut self) -> Message<'sim, E> {
+        let PendingAck {
+            seq,
+            sent_time,
+            packets,
+            bytes,
+            marked,
+            ..
+        } = self.pending.take().unwrap();
+        log!(
+            self.logger,
+            "Acknowledging up to {} (triggered by packet {})",
+            self.cumulative,
+            seq
+        );
+        self.link.create_message(Packet {
+            seq,
+            source: self.id.clone(),
+            destination: self.source.clone().unwrap(),
+            hops: self.hops.clone(),
+            next_hop: 0,
+            sent_time,
+            kind: PacketKind::Ack {
+                cumulative: self.cumulative,
+                packets,
+                bytes,
+                marked,
+            },
+            ecn: Ecn::NotEct,
+        })
+    }
+}
+
+impl<'sim, E, L> Component<'sim, E> for Receiver<'sim, E, L>
+where
+    L: Logger,
+{
+    type Receive = Packet<'sim, E>;
+
+    fn next_tick(&self, _time: Time) -> Option<Time> {
+        self.pending.map(|p| p.deadline)
+    }
+
+    fn tick(&mut self, time: Time) -> Vec<Message<'sim, E>> {
+        assert_eq!(self.next_tick(time), Some(time));
+        vec![self.send_ack()]
+    }
+
+    fn receive(&mut self, packet: Self::Receive, time: Time) -> Vec<Message<'sim, E>> {
+        let PacketKind::Data { first_seq, size } = packet.kind else {
+            panic!("Receiver should only receive data packets!")
+        };
+        log!(self.logger, "Received packet {}", packet.seq);
+        let out_of_order = self.record(packet.seq, first_seq);
+        self.source = Some(packet.source);
+        let pending = self.pending.get_or_insert(PendingAck {
+            seq: packet.seq,
+            sent_time: packet.sent_time,
+            packets: 0,
+            bytes: Information::ZERO,
+            marked: 0,
+            deadline: time + self.ack_timeout,
+        });
+        pending.seq = packet.seq;
+        pending.sent_time = packet.sent_time;
+        pending.packets += 1;
+        pending.bytes = pending.bytes + size;
+        pending.marked += u64::from(packet.ecn == Ecn::Ce);
+        if out_of_order || pending.packets >= u64::from(self.ack_every) {
+            vec![self.send_ack()]
+        } else {
+            vec![]
+        }
+    }
+}
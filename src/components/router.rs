@@ -0,0 +1,47 @@
+use std::marker::PhantomData;
+
+use derive_where::derive_where;
+
+use crate::{
+    quantities::Time,
+    simulation::{Component, Message},
+    util::logging::Logger,
+};
+
+use super::packet::Packet;
+
+/// Forwards each packet to the next hop on its route, joining together the links of a
+/// multi-hop topology.
+#[derive_where(Debug; L)]
+pub struct Router<'sim, E, L> {
+    logger: L,
+    phantom: PhantomData<Packet<'sim, E>>,
+}
+
+impl<'sim, E, L> Router<'sim, E, L> {
+    pub const fn new(logger: L) -> Router<'sim, E, L> {
+        Router {
+            logger,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'sim, E, L> Component<'sim, E> for Router<'sim, E, L>
+where
+    L: Logger,
+{
+    type Receive = Packet<'sim, E>;
+
+    fn receive(&mut self, mut packet: Self::Receive, _: Time) -> Vec<Message<'sim, E>> {
+        let seq = packet.seq;
+        let message = packet.pop_next_hop().create_message(packet);
+        log!(
+            self.logger,
+            "Forwarding packet {} via {:?}",
+            seq,
+            message.destination()
+        );
+        vec![message]
+    }
+}
@@ -1,18 +1,25 @@
-use std::{fmt::Debug, marker::PhantomData};
+use std::{collections::VecDeque, fmt::Debug, marker::PhantomData, rc::Rc};
 
 use derive_more::{From, TryInto};
 
 use crate::{
     components::{
-        packet::{Packet, PacketAddress},
+        packet::{Ecn, Packet, PacketAddress, PacketKind},
         toggler::Toggle,
+        workload::{FlowCompleted, Transfer},
     },
-    quantities::{earliest_opt, Time},
+    quantities::{earliest_opt, Information, Time},
     simulation::{Component, Message},
-    util::{logging::Logger, meters::FlowMeter, rand::Rng},
+    util::{
+        logging::{Event, Logger},
+        meters::FlowMeter,
+        rand::Rng,
+    },
     AckReceived, Cca, PacketSent,
 };
 
+use super::recovery::LossRecovery;
+
 #[derive(Debug)]
 struct Disabled {
     packets_sent: u64,
@@ -22,31 +29,72 @@ struct Disabled {
 struct Enabled<C> {
     started: Time,
     last_send: Time,
+    first_seq: u64,
     greatest_ack: u64,
     cwnd: u32,
     packets_sent: u64,
+    recovery: Option<LossRecovery>,
+    /// Sequence number of the last packet of a finite transfer.
+    last_seq: Option<u64>,
     cca: C,
 }
 
 impl<C: Cca> Enabled<C> {
-    fn new(cca: C, packets_sent: u64, time: Time) -> Self {
+    fn new(
+        cca: C,
+        packets_sent: u64,
+        time: Time,
+        loss_recovery: bool,
+        last_seq: Option<u64>,
+    ) -> Self {
         Self {
             started: time,
             last_send: Time::MIN,
+            first_seq: packets_sent + 1,
             greatest_ack: packets_sent,
             cwnd: cca.initial_cwnd(time),
             cca,
             packets_sent,
+            recovery: loss_recovery.then(LossRecovery::new),
+            last_seq,
         }
     }
 
     fn next_send(&self, time: Time) -> Option<Time> {
-        if self.packets_sent < self.greatest_ack + u64::from(self.cwnd) {
+        let sent_everything = self.last_seq.is_some_and(|last| self.packets_sent >= last)
+            && !self
+                .recovery
+                .as_ref()
+                .is_some_and(LossRecovery::waiting_to_retransmit);
+        if sent_everything {
+            return None;
+        }
+        // Without loss recovery, lost packets are never acknowledged, and so they don't count as
+        // being in flight once a later packet has been acknowledged.
+        let in_flight = self
+            .recovery
+            .as_ref()
+            .map_or(self.packets_sent - self.greatest_ack, |recovery| {
+                recovery.in_flight() as u64
+            });
+        if in_flight < u64::from(self.cwnd) {
             Some(time)
         } else {
             None
         }
     }
+
+    /// Whether every packet of a finite transfer has been acknowledged. Without loss recovery,
+    /// lost packets are never retransmitted, so only the last packet has to be acknowledged.
+    fn transfer_complete(&self) -> bool {
+        self.last_seq.is_some_and(|last| {
+            self.packets_sent >= last
+                && self
+                    .recovery
+                    .as_ref()
+                    .map_or(self.greatest_ack >= last, LossRecovery::all_acknowledged)
+        })
+    }
 }
 
 #[derive(Debug, From)]
@@ -59,14 +107,23 @@ enum State<C> {
 pub enum LossySenderEffect<'sim, E> {
     Packet(Packet<'sim, E>),
     Toggle(Toggle),
+    Transfer(Transfer<'sim, E>),
 }
 
 pub struct LossySender<'sim, 'a, C, F, G, E, L> {
     id: PacketAddress<'sim, E>,
     link: PacketAddress<'sim, E>,
+    hops: Rc<[PacketAddress<'sim, E>]>,
     destination: PacketAddress<'sim, E>,
     cca_generator: G,
+    loss_recovery: bool,
+    ecn: bool,
+    segment_size: Information,
     state: State<C>,
+    /// Transfer being sent, and when it arrived.
+    transfer: Option<(Transfer<'sim, E>, Time)>,
+    /// Transfers waiting for the current one to complete.
+    queued_transfers: VecDeque<(Transfer<'sim, E>, Time)>,
     flow_meter: F,
     rng: Rng,
     logger: L,
@@ -78,8 +135,14 @@ impl<'sim, 'a, C: Debug, F: Debug, G, E, L: Debug> Debug for LossySender<'sim, '
         f.debug_struct("LossySender")
             .field("id", &self.id)
             .field("link", &self.link)
+            .field("hops", &self.hops)
             .field("destination", &self.destination)
+            .field("loss_recovery", &self.loss_recovery)
+            .field("ecn", &self.ecn)
+            .field("segment_size", &self.segment_size)
             .field("state", &self.state)
+            .field("transfer", &self.transfer)
+            .field("queued_transfers", &self.queued_transfers)
             .field("flow_meter", &self.flow_meter)
             .field("rng", &self.rng)
             .field("logger", &self.logger)
@@ -95,29 +158,49 @@ where
     F: FlowMeter,
     L: Logger,
 {
+    /// Packets are transmitted to the first address in `route`, and then visit the
+    /// remaining addresses in order before being delivered to `destination`.
+    ///
+    /// If `loss_recovery` is enabled, lost packets are detected and retransmitted, and the CCA is
+    /// notified of them. If `ecn` is enabled, data packets are sent as ECN-capable, so that
+    /// links may mark them instead of dropping them. Every data packet is `segment_size` long, and
+    /// the congestion window counts segments of that size.
+    ///
+    /// While enabled, the sender sends as much as its congestion window allows. Alternatively, it
+    /// can be sent `Transfer`s, which enable it until the requested data has been acknowledged.
     pub fn new(
         id: PacketAddress<'sim, E>,
-        link: PacketAddress<'sim, E>,
+        route: Vec<PacketAddress<'sim, E>>,
         destination: PacketAddress<'sim, E>,
         mut flow_meter: F,
         cca_generator: G,
         wait_for_enable: bool,
+        loss_recovery: bool,
+        ecn: bool,
+        segment_size: Information,
         rng: Rng,
         logger: L,
     ) -> Self {
         if !wait_for_enable {
             flow_meter.set_enabled(Time::SIM_START);
         }
+        let mut route = route.into_iter();
         LossySender {
             id,
-            link,
+            link: route.next().expect("Route to be non-empty"),
+            hops: route.collect(),
             destination,
             state: if wait_for_enable {
                 Disabled { packets_sent: 0 }.into()
             } else {
-                Enabled::new(cca_generator(), 0, Time::SIM_START).into()
+                Enabled::new(cca_generator(), 0, Time::SIM_START, loss_recovery, None).into()
             },
+            transfer: None,
+            queued_transfers: VecDeque::new(),
             cca_generator,
+            loss_recovery,
+            ecn,
+            segment_size,
             rng,
             flow_meter,
             logger,
@@ -128,12 +211,20 @@ where
     fn receive_toggle(&mut self, toggle: Toggle, time: Time) {
         match (&mut self.state, toggle) {
             (State::WaitingForEnable(Disabled { packets_sent, .. }), Toggle::Enable) => {
-                log!(self.logger, "Enabled");
+                self.logger.event(time, || Event::Toggled { enabled: true });
                 self.flow_meter.set_enabled(time);
-                self.state = Enabled::new((self.cca_generator)(), *packets_sent, time).into();
+                self.state = Enabled::new(
+                    (self.cca_generator)(),
+                    *packets_sent,
+                    time,
+                    self.loss_recovery,
+                    None,
+                )
+                .into();
             }
             (State::Enabled(Enabled { packets_sent, .. }), Toggle::Disable) => {
-                log!(self.logger, "Disabled");
+                self.logger
+                    .event(time, || Event::Toggled { enabled: false });
                 self.flow_meter.set_disabled(time);
                 self.state = Disabled {
                     packets_sent: *packets_sent,
@@ -144,7 +235,62 @@ where
         }
     }
 
-    fn receive_packet(&mut self, packet: &Packet<'sim, E>, time: Time) {
+    fn receive_transfer(&mut self, transfer: Transfer<'sim, E>, time: Time) {
+        log!(self.logger, "Received transfer of {}", transfer.size);
+        self.queued_transfers.push_back((transfer, time));
+        if matches!(self.state, State::WaitingForEnable(_)) {
+            self.start_transfer(time);
+        }
+    }
+
+    fn start_transfer(&mut self, time: Time) {
+        let State::WaitingForEnable(Disabled { packets_sent }) = self.state else {
+            panic!("Tried to start a transfer while enabled!")
+        };
+        let Some((transfer, arrived)) = self.queued_transfers.pop_front() else {
+            return;
+        };
+        let segments = transfer
+            .size
+            .bytes()
+            .div_ceil(self.segment_size.bytes())
+            .max(1);
+        log!(self.logger, "Started transfer of {}", transfer.size);
+        self.flow_meter.set_enabled(time);
+        self.state = Enabled::new(
+            (self.cca_generator)(),
+            packets_sent,
+            time,
+            self.loss_recovery,
+            Some(packets_sent + segments),
+        )
+        .into();
+        self.transfer = Some((transfer, arrived));
+    }
+
+    fn complete_transfer(&mut self, time: Time) -> Vec<Message<'sim, E>> {
+        let State::Enabled(Enabled { packets_sent, .. }) = self.state else {
+            panic!("Tried to complete a transfer while disabled!")
+        };
+        let (transfer, arrived) = self.transfer.take().unwrap();
+        log!(self.logger, "Completed transfer of {}", transfer.size);
+        self.flow_meter.transfer_completed(
+            transfer.size,
+            time - arrived,
+            transfer.ideal_duration,
+            time,
+        );
+        self.flow_meter.set_disabled(time);
+        self.state = Disabled { packets_sent }.into();
+        self.start_transfer(time);
+        transfer
+            .notify
+            .map(|notify| notify.create_message(FlowCompleted))
+            .into_iter()
+            .collect()
+    }
+
+    fn receive_packet(&mut self, packet: &Packet<'sim, E>, time: Time) -> Vec<Message<'sim, E>> {
         match &mut self.state {
             State::WaitingForEnable(_) => {
                 log!(
@@ -152,35 +298,87 @@ where
                     "Received packet {}, ignoring as disabled",
                     packet.seq
                 );
+                vec![]
             }
             State::Enabled(Enabled {
                 started,
                 greatest_ack,
                 cwnd,
+                recovery,
                 cca,
                 ..
             }) => {
                 if &packet.sent_time < started {
                     log!(self.logger, "Received old packet {}", packet.seq);
-                    return;
+                    return vec![];
                 }
+                // Packets reflected straight back to the sender act as their own ACK
+                let (acked_seq, cumulative, acked_packets, acked_bytes, marked) = match packet.kind
+                {
+                    PacketKind::Data { .. } => (
+                        packet.seq,
+                        None,
+                        1,
+                        packet.size(),
+                        u64::from(packet.ecn == Ecn::Ce),
+                    ),
+                    PacketKind::Ack {
+                        cumulative,
+                        packets,
+                        bytes,
+                        marked,
+                    } => (cumulative, Some(cumulative), packets, bytes, marked),
+                };
+                let rtt = time - packet.sent_time;
+                let (new_packets, loss) = recovery.as_mut().map_or((acked_packets, None), |r| {
+                    r.ack_received(packet.seq, cumulative, packet.sent_time, time)
+                });
+                // Every data packet from this sender is a full segment, so newly delivered data can
+                // be counted in segments even when only some of the acknowledged packets are new
+                let delivered_bytes = new_packets * self.segment_size;
                 self.flow_meter
-                    .packet_received(packet.size(), time - packet.sent_time, time);
+                    .packet_received(acked_bytes, delivered_bytes, rtt, time);
                 log!(self.logger, "Received packet {}", packet.seq);
-                *cwnd = cca.ack_received(
+                let new_cwnd = cca.ack_received(
                     AckReceived {
+                        seq: acked_seq,
                         sent_time: packet.sent_time,
                         received_time: time,
+                        delivered: new_packets,
+                        delivered_bytes,
+                        marked,
                     },
                     &mut self.rng,
                     &mut self.logger,
                 );
+                set_cwnd(cwnd, new_cwnd, time, &mut self.logger);
                 *greatest_ack = (*greatest_ack).max(packet.seq);
+                if let Some(loss) = loss {
+                    log!(self.logger, "Detected loss of packet {}", loss.seq);
+                    if let Some(new_cwnd) = cca.loss_detected(loss, &mut self.rng, &mut self.logger)
+                    {
+                        set_cwnd(cwnd, new_cwnd, time, &mut self.logger);
+                    }
+                }
+                if self.transfer.is_some()
+                    && matches!(&self.state, State::Enabled(enabled) if enabled.transfer_complete())
+                {
+                    return self.complete_transfer(time);
+                }
+                vec![]
             }
         }
     }
 }
 
+/// Sets the congestion window, emitting an event if it changed.
+fn set_cwnd(cwnd: &mut u32, new_cwnd: u32, time: Time, logger: &mut impl Logger) {
+    if *cwnd != new_cwnd {
+        *cwnd = new_cwnd;
+        logger.event(time, || Event::CwndChanged { cwnd: new_cwnd });
+    }
+}
+
 impl<'sim, 'a, C, F, G, E, L> Component<'sim, E> for LossySender<'sim, 'a, C, F, G, E, L>
 where
     C: Cca,
@@ -193,9 +391,14 @@ where
     fn next_tick(&self, time: Time) -> Option<Time> {
         match &self.state {
             State::WaitingForEnable(_) => None,
-            State::Enabled(enabled) => {
-                earliest_opt(&[enabled.next_send(time), enabled.cca.next_tick(time)])
-            }
+            State::Enabled(enabled) => earliest_opt(&[
+                enabled.next_send(time),
+                enabled.cca.next_tick(time),
+                enabled
+                    .recovery
+                    .as_ref()
+                    .and_then(LossRecovery::next_timeout),
+            ]),
         }
     }
 
@@ -203,29 +406,64 @@ where
         match &mut self.state {
             State::Enabled(s) => {
                 if s.cca.next_tick(time) == Some(time) {
-                    s.cwnd = s.cca.tick(&mut self.rng, &mut self.logger);
+                    let cwnd = s.cca.tick(&mut self.rng, &mut self.logger);
+                    set_cwnd(&mut s.cwnd, cwnd, time, &mut self.logger);
+                    vec![]
+                } else if let Some(recovery) = s
+                    .recovery
+                    .as_mut()
+                    .filter(|r| r.next_timeout() == Some(time))
+                {
+                    let loss = recovery.timeout(time);
+                    log!(self.logger, "Retransmission timeout");
+                    if let Some(cwnd) = s.cca.loss_detected(loss, &mut self.rng, &mut self.logger) {
+                        set_cwnd(&mut s.cwnd, cwnd, time, &mut self.logger);
+                    }
                     vec![]
                 } else if s.next_send(time) == Some(time) {
                     let Enabled {
                         last_send,
+                        first_seq,
                         cwnd,
                         packets_sent,
+                        recovery,
                         cca,
                         ..
                     } = s;
-                    *packets_sent += 1;
+                    let retransmission = recovery
+                        .as_mut()
+                        .and_then(LossRecovery::next_retransmission);
+                    let seq = if let Some(seq) = retransmission {
+                        log!(self.logger, "Retransmitting packet {}", seq);
+                        seq
+                    } else {
+                        *packets_sent += 1;
+                        *packets_sent
+                    };
+                    if let Some(recovery) = recovery {
+                        recovery.packet_sent(seq, time, retransmission.is_some());
+                    }
                     *last_send = time;
                     let packet = Packet {
-                        seq: *packets_sent,
+                        seq,
                         source: self.id.clone(),
                         destination: self.destination.clone(),
+                        hops: self.hops.clone(),
+                        next_hop: 0,
                         sent_time: time,
+                        kind: PacketKind::Data {
+                            first_seq: *first_seq,
+                            size: self.segment_size,
+                        },
+                        ecn: if self.ecn { Ecn::Ect } else { Ecn::NotEct },
                     };
-                    *cwnd = cca.packet_sent(
+                    let new_cwnd = cca.packet_sent(
                         PacketSent { sent_time: time },
                         &mut self.rng,
                         &mut self.logger,
                     );
+                    set_cwnd(cwnd, new_cwnd, time, &mut self.logger);
+                    self.flow_meter.packet_sent(self.segment_size, time);
                     vec![self.link.create_message(packet)]
                 } else {
                     panic!()
@@ -237,9 +475,311 @@ where
 
     fn receive(&mut self, e: Self::Receive, time: Time) -> Vec<Message<'sim, E>> {
         match e {
-            LossySenderEffect::Packet(packet) => self.receive_packet(&packet, time),
+            LossySenderEffect::Packet(packet) => return self.receive_packet(&packet, time),
             LossySenderEffect::Toggle(toggle) => self.receive_toggle(toggle, time),
+            LossySenderEffect::Transfer(transfer) => self.receive_transfer(transfer, time),
         }
         vec![]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use generativity::make_guard;
+
+    use crate::{
+        components::{
+            link::Link,
+            workload::{FlowGenerator, IdealPath},
+        },
+        quantities::{bits_per_second, bytes, milliseconds, seconds, Information, Time, TimeSpan},
+        simulation::SimulatorBuilder,
+        trainers::DefaultEffect,
+        util::{
+            logging::{DropReason, Event, EventBuffer, Logger, NothingLogger},
+            meters::{AverageFlowMeter, FlowMeter, NoFlowMeter},
+            rand::{
+                ContinuousDistribution, DiscreteDistribution, PositiveContinuousDistribution, Rng,
+            },
+        },
+        AckReceived, Cca, PacketSent,
+    };
+
+    use super::LossySender;
+
+    #[derive(Debug)]
+    struct FixedWindow;
+
+    impl Cca for FixedWindow {
+        fn initial_cwnd(&self, _time: Time) -> u32 {
+            20
+        }
+
+        fn next_tick(&self, _time: Time) -> Option<Time> {
+            None
+        }
+
+        fn tick(&mut self, _rng: &mut Rng, _logger: &mut impl Logger) -> u32 {
+            unreachable!()
+        }
+
+        fn packet_sent(
+            &mut self,
+            _packet: PacketSent,
+            _rng: &mut Rng,
+            _logger: &mut impl Logger,
+        ) -> u32 {
+            20
+        }
+
+        fn ack_received(
+            &mut self,
+            _ack: AckReceived,
+            _rng: &mut Rng,
+            _logger: &mut impl Logger,
+        ) -> u32 {
+            20
+        }
+    }
+
+    #[test]
+    fn throughput_counts_bytes_of_each_segment() {
+        let flow_meter = RefCell::new(AverageFlowMeter::new_disabled());
+        make_guard!(guard);
+        let builder = SimulatorBuilder::<DefaultEffect>::new(guard);
+        let sender_slot = builder.reserve_slot();
+        let link = builder.insert(Link::create(
+            milliseconds(10.),
+            bits_per_second(1_000_000.),
+            0.,
+            None,
+            Rng::from_seed(0),
+            NothingLogger,
+        ));
+        let sender_address = sender_slot.address().cast();
+        sender_slot.fill(LossySender::new(
+            sender_address.clone(),
+            vec![link],
+            sender_address,
+            &flow_meter,
+            || FixedWindow,
+            false,
+            false,
+            false,
+            bytes(500),
+            Rng::from_seed(1),
+            NothingLogger,
+        ));
+        let mut sim = builder.build(NothingLogger).unwrap();
+        let end = Time::from_sim_start(seconds(10.));
+        while sim.time() < end && sim.tick() {}
+        drop(sim);
+
+        let throughput = flow_meter
+            .borrow()
+            .average_properties(end)
+            .unwrap()
+            .throughput;
+        let ratio = throughput.bits_per_second() / 1_000_000.;
+        assert!((0.99..=1.).contains(&ratio), "ratio was {ratio}");
+    }
+
+    #[test]
+    fn loss_rate_counts_unacknowledged_data() {
+        let flow_meter = RefCell::new(AverageFlowMeter::new_disabled());
+        make_guard!(guard);
+        let builder = SimulatorBuilder::<DefaultEffect>::new(guard);
+        let sender_slot = builder.reserve_slot();
+        let link = builder.insert(Link::create(
+            milliseconds(10.),
+            bits_per_second(1_000_000.),
+            0.1,
+            None,
+            Rng::from_seed(0),
+            NothingLogger,
+        ));
+        let sender_address = sender_slot.address().cast();
+        sender_slot.fill(LossySender::new(
+            sender_address.clone(),
+            vec![link],
+            sender_address,
+            &flow_meter,
+            || FixedWindow,
+            false,
+            false,
+            false,
+            bytes(500),
+            Rng::from_seed(1),
+            NothingLogger,
+        ));
+        let mut sim = builder.build(NothingLogger).unwrap();
+        let end = Time::from_sim_start(seconds(10.));
+        while sim.time() < end && sim.tick() {}
+        drop(sim);
+
+        let loss_rate = flow_meter
+            .borrow()
+            .average_properties(end)
+            .unwrap()
+            .loss_rate
+            .unwrap();
+        // Packets still in flight at the end count as lost too
+        assert!(
+            (0.08..=0.12).contains(&loss_rate),
+            "loss rate was {loss_rate}"
+        );
+    }
+
+    #[test]
+    fn link_emits_an_event_for_every_packet() {
+        let events = EventBuffer::new();
+        make_guard!(guard);
+        let builder = SimulatorBuilder::<DefaultEffect>::new(guard);
+        let sender_slot = builder.reserve_slot();
+        let link = builder.insert(Link::create(
+            milliseconds(10.),
+            bits_per_second(1_000_000.),
+            0.,
+            Some(bytes(2000)),
+            Rng::from_seed(0),
+            events.logger(0),
+        ));
+        let sender_address = sender_slot.address().cast();
+        sender_slot.fill(LossySender::new(
+            sender_address.clone(),
+            vec![link],
+            sender_address,
+            NoFlowMeter,
+            || FixedWindow,
+            false,
+            false,
+            false,
+            bytes(500),
+            Rng::from_seed(1),
+            events.logger(1),
+        ));
+        let mut sim = builder.build(NothingLogger).unwrap();
+        let end = Time::from_sim_start(seconds(1.));
+        while sim.time() < end && sim.tick() {}
+        drop(sim);
+
+        let events = events.into_events();
+        assert!(events.iter().all(|e| e.source == 0));
+        let mut buffered = Vec::new();
+        let mut dropped = 0;
+        for logged in events {
+            match logged.event {
+                Event::PacketEnqueued { seq } => buffered.push(seq),
+                Event::PacketDropped {
+                    reason: DropReason::Enqueue,
+                    ..
+                } => dropped += 1,
+                Event::PacketDelivered { seq } => {
+                    assert_eq!(buffered.first(), Some(&seq));
+                    buffered.remove(0);
+                }
+                event => panic!("Unexpected event {event}"),
+            }
+        }
+        assert!(dropped > 0);
+        // Only the 4 packets that fit in the buffer and the 3 that fit on the link can be left over
+        assert!(buffered.len() <= 7);
+    }
+
+    #[derive(Debug, Default)]
+    struct CompletionMeter {
+        completed: Vec<(Information, TimeSpan, TimeSpan)>,
+    }
+
+    impl FlowMeter for CompletionMeter {
+        fn set_enabled(&mut self, _time: Time) {}
+
+        fn set_disabled(&mut self, _time: Time) {}
+
+        fn packet_sent(&mut self, _data: Information, _time: Time) {}
+
+        fn packet_received(
+            &mut self,
+            _data: Information,
+            _new_data: Information,
+            _rtt: TimeSpan,
+            _time: Time,
+        ) {
+        }
+
+        fn transfer_completed(
+            &mut self,
+            size: Information,
+            duration: TimeSpan,
+            ideal_duration: TimeSpan,
+            _time: Time,
+        ) {
+            self.completed.push((size, duration, ideal_duration));
+        }
+    }
+
+    #[test]
+    fn finite_transfers_complete_once_acknowledged() {
+        let flow_meter = RefCell::new(CompletionMeter::default());
+        make_guard!(guard);
+        let builder = SimulatorBuilder::<DefaultEffect>::new(guard);
+        let sender_slot = builder.reserve_slot();
+        let generator_slot = builder.reserve_slot();
+        let link = builder.insert(Link::create(
+            milliseconds(10.),
+            bits_per_second(1_000_000.),
+            0.,
+            None,
+            Rng::from_seed(0),
+            NothingLogger,
+        ));
+        let address = sender_slot.address();
+        let sender_address = address.clone().cast();
+        sender_slot.fill(LossySender::new(
+            sender_address.clone(),
+            vec![link],
+            sender_address,
+            &flow_meter,
+            || FixedWindow,
+            true,
+            true,
+            false,
+            bytes(500),
+            Rng::from_seed(1),
+            NothingLogger,
+        ));
+        let generator_address = generator_slot.address();
+        generator_slot.fill(FlowGenerator::closed_loop(
+            address.cast(),
+            generator_address,
+            DiscreteDistribution::Always {
+                value: bytes(10_000),
+            },
+            PositiveContinuousDistribution(ContinuousDistribution::Always { value: seconds(1.) }),
+            IdealPath {
+                rtt: milliseconds(10.),
+                rate: bits_per_second(1_000_000.),
+            },
+            bytes(500),
+            Rng::from_seed(2),
+        ));
+        let mut sim = builder.build(NothingLogger).unwrap();
+        let end = Time::from_sim_start(seconds(5.));
+        while sim.time() < end && sim.tick() {}
+        drop(sim);
+
+        // The whole window of 20 segments is sent at once, each taking 4ms to transmit, so the
+        // last one leaves the link after 76ms and arrives 10ms later, as fast as is possible. The
+        // next flow arrives a second after that.
+        let completed = flow_meter.into_inner().completed;
+        assert_eq!(completed.len(), 4);
+        for (size, duration, ideal_duration) in completed {
+            assert_eq!(size, bytes(10_000));
+            assert!((ideal_duration - milliseconds(86.)).abs() < milliseconds(0.001));
+            assert!((duration - milliseconds(86.)).abs() < milliseconds(0.001));
+            assert!(duration / ideal_duration >= 1. - 1e-9);
+        }
+    }
+}
@@ -1 +1,2 @@
 pub mod lossy;
+mod recovery;
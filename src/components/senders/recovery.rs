@@ -0,0 +1,276 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    quantities::{seconds, Time, TimeSpan},
+    LossCause, LossDetected,
+};
+
+const INITIAL_RTO: TimeSpan = seconds(1.);
+const MIN_RTO: TimeSpan = seconds(0.2);
+const MAX_RTO: TimeSpan = seconds(60.);
+const DUPLICATE_ACK_THRESHOLD: u32 = 3;
+
+/// Detects lost packets from duplicate ACKs and retransmission timeouts, and keeps track of
+/// which packets need to be retransmitted.
+///
+/// An ACK counts as a duplicate if it doesn't acknowledge the lowest outstanding packet, but does
+/// acknowledge a packet that was sent after it (so ACKs for packets sent before a retransmission
+/// don't count against the retransmitted packet). The retransmission timeout is calculated as in
+/// RFC 6298, but with a minimum of 200ms. ACKs for retransmitted packets aren't used as RTT samples
+/// (Karn's algorithm), and after a timeout the backed-off RTO is kept until a packet sent after the
+/// timeout is acknowledged.
+#[derive(Debug)]
+pub struct LossRecovery {
+    /// Packets that have been sent, but not yet acknowledged or declared lost.
+    outstanding: BTreeMap<u64, Time>,
+    /// Packets that have been declared lost, but not yet retransmitted.
+    lost: BTreeSet<u64>,
+    /// Packets that have been retransmitted and not yet acknowledged.
+    retransmitted: BTreeSet<u64>,
+    duplicate_acks: u32,
+    srtt: Option<TimeSpan>,
+    rttvar: TimeSpan,
+    rto: TimeSpan,
+    rto_deadline: Option<Time>,
+    /// When the last timeout happened, if no packet sent since has been acknowledged.
+    backed_off_at: Option<Time>,
+}
+
+impl LossRecovery {
+    pub const fn new() -> LossRecovery {
+        LossRecovery {
+            outstanding: BTreeMap::new(),
+            lost: BTreeSet::new(),
+            retransmitted: BTreeSet::new(),
+            duplicate_acks: 0,
+            srtt: None,
+            rttvar: TimeSpan::ZERO,
+            rto: INITIAL_RTO,
+            rto_deadline: None,
+            backed_off_at: None,
+        }
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.outstanding.len()
+    }
+
+    pub fn waiting_to_retransmit(&self) -> bool {
+        !self.lost.is_empty()
+    }
+
+    /// Whether every packet sent so far has been acknowledged.
+    pub fn all_acknowledged(&self) -> bool {
+        self.outstanding.is_empty() && self.lost.is_empty()
+    }
+
+    /// Returns the next packet that is waiting to be retransmitted, if any.
+    pub fn next_retransmission(&mut self) -> Option<u64> {
+        self.lost.pop_first()
+    }
+
+    /// Starts the retransmission timer if it isn't already running, or restarts it if the packet
+    /// is a retransmission.
+    pub fn packet_sent(&mut self, seq: u64, time: Time, retransmission: bool) {
+        self.outstanding.insert(seq, time);
+        if retransmission {
+            self.retransmitted.insert(seq);
+        }
+        if retransmission || self.rto_deadline.is_none() {
+            self.rto_deadline = Some(time + self.rto);
+        }
+    }
+
+    pub const fn next_timeout(&self) -> Option<Time> {
+        self.rto_deadline
+    }
+
+    /// Returns the number of packets that were acknowledged for the first time, and any loss
+    /// that was detected as a result of this ACK.
+    pub fn ack_received(
+        &mut self,
+        seq: u64,
+        cumulative: Option<u64>,
+        sent_time: Time,
+        time: Time,
+    ) -> (u64, Option<LossDetected>) {
+        let retransmitted = self.retransmitted.remove(&seq);
+        let sent_before_timeout = self
+            .backed_off_at
+            .is_some_and(|timeout| sent_time < timeout);
+        if !retransmitted && !sent_before_timeout {
+            self.backed_off_at = None;
+            self.update_rto(time - sent_time);
+        }
+        let lowest = self.outstanding.first_key_value().map(|(&seq, _)| seq);
+        let mut newly_acked = 0;
+        if let Some(cumulative) = cumulative {
+            let remaining = self.outstanding.split_off(&(cumulative + 1));
+            newly_acked += std::mem::replace(&mut self.outstanding, remaining).len() as u64;
+            let remaining = self.lost.split_off(&(cumulative + 1));
+            newly_acked += std::mem::replace(&mut self.lost, remaining).len() as u64;
+            self.retransmitted = self.retransmitted.split_off(&(cumulative + 1));
+        }
+        newly_acked += u64::from(self.outstanding.remove(&seq).is_some());
+        newly_acked += u64::from(self.lost.remove(&seq));
+
+        if self.outstanding.first_key_value().map(|(&seq, _)| seq) != lowest {
+            self.duplicate_acks = 0;
+            self.rto_deadline = if self.outstanding.is_empty() {
+                None
+            } else {
+                Some(time + self.rto)
+            };
+            return (newly_acked, None);
+        }
+        let Some((&lowest, &lowest_sent_time)) = self.outstanding.first_key_value() else {
+            return (newly_acked, None);
+        };
+        if sent_time <= lowest_sent_time {
+            return (newly_acked, None);
+        }
+        self.duplicate_acks += 1;
+        if self.duplicate_acks < DUPLICATE_ACK_THRESHOLD {
+            return (newly_acked, None);
+        }
+        self.duplicate_acks = 0;
+        self.outstanding.remove(&lowest);
+        self.lost.insert(lowest);
+        if self.outstanding.is_empty() {
+            // Restarted when the packet is retransmitted
+            self.rto_deadline = None;
+        }
+        (
+            newly_acked,
+            Some(LossDetected {
+                seq: lowest,
+                sent_time: lowest_sent_time,
+                detected_time: time,
+                cause: LossCause::DuplicateAcks,
+            }),
+        )
+    }
+
+    /// Declares every outstanding packet lost, and backs off the retransmission timeout.
+    pub fn timeout(&mut self, time: Time) -> LossDetected {
+        assert_eq!(self.rto_deadline, Some(time));
+        let (&seq, &sent_time) = self
+            .outstanding
+            .first_key_value()
+            .expect("Timer to only run while packets are outstanding");
+        self.lost
+            .extend(std::mem::take(&mut self.outstanding).into_keys());
+        self.duplicate_acks = 0;
+        self.rto = (2. * self.rto).min(MAX_RTO);
+        self.rto_deadline = None;
+        self.backed_off_at = Some(time);
+        LossDetected {
+            seq,
+            sent_time,
+            detected_time: time,
+            cause: LossCause::Timeout,
+        }
+    }
+
+    fn update_rto(&mut self, rtt: TimeSpan) {
+        let srtt = if let Some(srtt) = self.srtt {
+            self.rttvar = 0.75 * self.rttvar + 0.25 * (srtt - rtt).abs();
+            0.875 * srtt + 0.125 * rtt
+        } else {
+            self.rttvar = rtt / 2.;
+            rtt
+        };
+        self.srtt = Some(srtt);
+        self.rto = (srtt + 4. * self.rttvar).clamp(MIN_RTO, MAX_RTO);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        quantities::{milliseconds, seconds, Time},
+        LossCause,
+    };
+
+    use super::LossRecovery;
+
+    fn at(ms: f64) -> Time {
+        Time::SIM_START + milliseconds(ms)
+    }
+
+    #[test]
+    fn fast_retransmit() {
+        let mut recovery = LossRecovery::new();
+        for seq in 1..=5_u32 {
+            recovery.packet_sent(u64::from(seq), at(f64::from(seq)), false);
+        }
+        assert_eq!(recovery.ack_received(1, None, at(1.), at(101.)).0, 1);
+        // Packet 2 is lost
+        assert!(recovery.ack_received(3, None, at(3.), at(103.)).1.is_none());
+        assert!(recovery.ack_received(4, None, at(4.), at(104.)).1.is_none());
+        let (newly_acked, loss) = recovery.ack_received(5, None, at(5.), at(105.));
+        assert_eq!(newly_acked, 1);
+        let loss = loss.unwrap();
+        assert_eq!((loss.seq, loss.cause), (2, LossCause::DuplicateAcks));
+        assert_eq!(recovery.in_flight(), 0);
+        assert_eq!(recovery.next_timeout(), None);
+        assert_eq!(recovery.next_retransmission(), Some(2));
+        assert_eq!(recovery.next_retransmission(), None);
+    }
+
+    #[test]
+    fn retransmission_not_blamed_for_earlier_packets() {
+        let mut recovery = LossRecovery::new();
+        for seq in 1..=6_u32 {
+            recovery.packet_sent(u64::from(seq), at(f64::from(seq)), false);
+        }
+        recovery.ack_received(2, Some(0), at(2.), at(102.));
+        recovery.ack_received(3, Some(0), at(3.), at(103.));
+        assert!(recovery
+            .ack_received(4, Some(0), at(4.), at(104.))
+            .1
+            .is_some());
+        assert_eq!(recovery.next_retransmission(), Some(1));
+        recovery.packet_sent(1, at(104.), true);
+        // These were sent before the retransmission, so aren't evidence that it was lost
+        assert!(recovery
+            .ack_received(5, Some(0), at(5.), at(105.))
+            .1
+            .is_none());
+        assert!(recovery
+            .ack_received(6, Some(0), at(6.), at(106.))
+            .1
+            .is_none());
+        assert_eq!(recovery.ack_received(1, Some(6), at(104.), at(204.)).0, 1);
+        assert_eq!(recovery.in_flight(), 0);
+        assert_eq!(recovery.next_timeout(), None);
+    }
+
+    #[test]
+    fn timeout_backs_off() {
+        let mut recovery = LossRecovery::new();
+        for seq in 1..=3_u32 {
+            recovery.packet_sent(u64::from(seq), at(f64::from(seq - 1)), false);
+        }
+        assert_eq!(recovery.next_timeout(), Some(at(0.) + seconds(1.)));
+        let loss = recovery.timeout(at(1000.));
+        assert_eq!((loss.seq, loss.cause), (1, LossCause::Timeout));
+        assert_eq!(recovery.next_timeout(), None);
+        assert_eq!(recovery.next_retransmission(), Some(1));
+        recovery.packet_sent(1, at(1000.), true);
+        assert_eq!(recovery.next_timeout(), Some(at(1000.) + seconds(2.)));
+        // A late ACK for a packet sent before the timeout doesn't undo the back-off
+        assert_eq!(recovery.ack_received(2, None, at(1.), at(1050.)).0, 1);
+        assert_eq!(recovery.next_retransmission(), Some(3));
+        recovery.packet_sent(3, at(1060.), true);
+        assert_eq!(recovery.next_timeout(), Some(at(1060.) + seconds(2.)));
+        // Nor does the ACK for a retransmission, which is ambiguous
+        assert_eq!(recovery.ack_received(1, Some(2), at(1000.), at(1100.)).0, 1);
+        assert_eq!(recovery.next_timeout(), Some(at(1100.) + seconds(2.)));
+        // New data being acknowledged brings the RTO back down
+        recovery.packet_sent(4, at(1110.), false);
+        recovery.packet_sent(5, at(1120.), false);
+        assert_eq!(recovery.ack_received(4, Some(4), at(1110.), at(1160.)).0, 2);
+        assert_eq!(recovery.next_timeout(), Some(at(1160.) + seconds(0.2)));
+    }
+}
@@ -0,0 +1,185 @@
+use derive_where::derive_where;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    quantities::{Information, InformationRate, Time, TimeSpan},
+    simulation::{Address, Component, Message},
+    util::rand::{
+        ContinuousDistribution, DiscreteDistribution, PositiveContinuousDistribution, Rng,
+    },
+};
+
+/// How senders decide when to send, and how much.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Workload {
+    /// Senders are switched on and off by a `Toggler`, and send as much as they can while on.
+    #[default]
+    OnOff,
+    /// Each sender transfers one flow at a time, and stays idle for the off time after each flow
+    /// completes.
+    FiniteFlows {
+        flow_size: DiscreteDistribution<Information>,
+    },
+    /// Flows arrive at each sender as a Poisson process, whether or not earlier flows have
+    /// completed, and are transferred in order of arrival.
+    PoissonArrivals {
+        flow_size: DiscreteDistribution<Information>,
+        mean_interarrival: TimeSpan,
+    },
+}
+
+/// Base RTT and bottleneck rate of a sender's path, which determine how long a transfer would
+/// take if the sender had the network to itself.
+#[derive(Debug, Clone, Copy)]
+pub struct IdealPath {
+    pub rtt: TimeSpan,
+    pub rate: InformationRate,
+}
+
+impl IdealPath {
+    /// Links forward each packet as soon as the one before it has been serialized, so only the
+    /// packets after the first are delayed by the rate. Every packet is `segment_size` long.
+    #[must_use]
+    pub fn transfer_time(&self, size: Information, segment_size: Information) -> TimeSpan {
+        let segments = size.bytes().div_ceil(segment_size.bytes());
+        self.rtt + segments.saturating_sub(1) * segment_size / self.rate
+    }
+}
+
+/// Asks a sender to transfer `size` bytes, after any transfers it was already asked for.
+#[derive_where(Debug)]
+pub struct Transfer<'sim, E> {
+    pub size: Information,
+    /// Time the transfer would take on an idle network, used to calculate its slowdown.
+    pub ideal_duration: TimeSpan,
+    /// Told when the transfer completes.
+    pub notify: Option<Address<'sim, FlowCompleted, E>>,
+}
+
+#[derive(Debug)]
+pub struct FlowCompleted;
+
+#[derive(Debug)]
+enum Arrivals {
+    /// Wait for each flow to complete, then for a sample of this, before the next flow arrives.
+    ClosedLoop(PositiveContinuousDistribution<TimeSpan>),
+    /// Time between arrivals, regardless of completions.
+    OpenLoop(PositiveContinuousDistribution<TimeSpan>),
+}
+
+/// Generates the flows transferred by a sender with a finite-flow `Workload`.
+#[derive_where(Debug)]
+pub struct FlowGenerator<'sim, E> {
+    target: Address<'sim, Transfer<'sim, E>, E>,
+    /// Address of this generator, if it waits for flows to complete.
+    own_address: Option<Address<'sim, FlowCompleted, E>>,
+    arrivals: Arrivals,
+    flow_size: DiscreteDistribution<Information>,
+    path: IdealPath,
+    /// Size of the packets the target sends, used to calculate the ideal duration of each flow.
+    segment_size: Information,
+    next_arrival: Option<Time>,
+    rng: Rng,
+}
+
+impl<'sim, E> FlowGenerator<'sim, E> {
+    /// Flows wait for the previous flow to complete, then for a sample of `off_time`, before
+    /// arriving. `own_address` should be the address this generator is inserted at.
+    #[must_use]
+    pub fn closed_loop(
+        target: Address<'sim, Transfer<'sim, E>, E>,
+        own_address: Address<'sim, FlowCompleted, E>,
+        flow_size: DiscreteDistribution<Information>,
+        off_time: PositiveContinuousDistribution<TimeSpan>,
+        path: IdealPath,
+        segment_size: Information,
+        rng: Rng,
+    ) -> FlowGenerator<'sim, E> {
+        FlowGenerator::new(
+            target,
+            Some(own_address),
+            Arrivals::ClosedLoop(off_time),
+            flow_size,
+            path,
+            segment_size,
+            rng,
+        )
+    }
+
+    /// Flows arrive as a Poisson process with the given mean time between arrivals.
+    #[must_use]
+    pub fn poisson(
+        target: Address<'sim, Transfer<'sim, E>, E>,
+        flow_size: DiscreteDistribution<Information>,
+        mean_interarrival: TimeSpan,
+        path: IdealPath,
+        segment_size: Information,
+        rng: Rng,
+    ) -> FlowGenerator<'sim, E> {
+        FlowGenerator::new(
+            target,
+            None,
+            Arrivals::OpenLoop(PositiveContinuousDistribution(
+                ContinuousDistribution::Exponential {
+                    mean: mean_interarrival,
+                },
+            )),
+            flow_size,
+            path,
+            segment_size,
+            rng,
+        )
+    }
+
+    fn new(
+        target: Address<'sim, Transfer<'sim, E>, E>,
+        own_address: Option<Address<'sim, FlowCompleted, E>>,
+        arrivals: Arrivals,
+        flow_size: DiscreteDistribution<Information>,
+        path: IdealPath,
+        segment_size: Information,
+        mut rng: Rng,
+    ) -> FlowGenerator<'sim, E> {
+        let (Arrivals::ClosedLoop(wait) | Arrivals::OpenLoop(wait)) = &arrivals;
+        FlowGenerator {
+            target,
+            own_address,
+            next_arrival: Some(Time::from_sim_start(rng.sample(wait))),
+            arrivals,
+            flow_size,
+            path,
+            segment_size,
+            rng,
+        }
+    }
+}
+
+impl<'sim, E> Component<'sim, E> for FlowGenerator<'sim, E> {
+    type Receive = FlowCompleted;
+
+    fn tick(&mut self, time: Time) -> Vec<Message<'sim, E>> {
+        assert_eq!(Some(time), self.next_arrival);
+        self.next_arrival = match &self.arrivals {
+            Arrivals::ClosedLoop(_) => None,
+            Arrivals::OpenLoop(interarrival) => Some(time + self.rng.sample(interarrival)),
+        };
+        let size = self.rng.sample(&self.flow_size);
+        vec![self.target.create_message(Transfer {
+            size,
+            ideal_duration: self.path.transfer_time(size, self.segment_size),
+            notify: self.own_address.clone(),
+        })]
+    }
+
+    fn next_tick(&self, _time: Time) -> Option<Time> {
+        self.next_arrival
+    }
+
+    fn receive(&mut self, FlowCompleted: FlowCompleted, time: Time) -> Vec<Message<'sim, E>> {
+        if let Arrivals::ClosedLoop(off_time) = &self.arrivals {
+            self.next_arrival = Some(time + self.rng.sample(off_time));
+        }
+        vec![]
+    }
+}
@@ -1,25 +1,32 @@
-use std::cell::RefCell;
+use std::{cell::RefCell, collections::BTreeMap};
 
 use append_only_vec::AppendOnlyVec;
 use generativity::make_guard;
 use itertools::Itertools;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    flow::{FlowProperties, NoActiveFlows, UtilityFunction},
+    ccas::either::EitherCca,
+    flow::{FairnessStats, FlowProperties, NoActiveFlows, UtilityFunction},
+    networks::remy::{HasRemyNetworkVariants, RemyNetworkDistribution, RemyNetworkSweep},
     quantities::{seconds, Float, Time, TimeSpan},
     simulation::SimulatorBuilder,
     util::{
-        average::{AveragePair, IterAverage, NoItems, SameEmptiness},
+        average::{AverageIfSome, AveragePair, IterAverage, NoItems, SameEmptiness},
         logging::NothingLogger,
-        meters::AverageFlowMeter,
+        meters::{AverageFlowMeter, IntervalThroughputMeter},
         rand::Rng,
+        stats::{paired_permutation_test, Estimate},
         OfLifetime,
     },
     Cca, Network, NetworkDistribution,
 };
 
+/// Length of the intervals over which the throughput of each flow is measured to decide when
+/// flows have converged to their fair shares.
+const FAIRNESS_INTERVAL: TimeSpan = seconds(0.1);
+
 #[allow(clippy::unsafe_derive_deserialize)]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct EvaluationConfig {
@@ -36,6 +43,223 @@ impl Default for EvaluationConfig {
     }
 }
 
+/// How senders are split between the two CCAs in [`EvaluationConfig::evaluate_against`].
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum SenderMix {
+    /// Senders alternate between the two CCAs, starting with the first.
+    Alternate,
+    /// The first `n` senders in each network use the first CCA, and the rest use the second.
+    First(u32),
+}
+
+impl SenderMix {
+    /// Returns 0 if the sender with the given index uses the first CCA, or 1 otherwise.
+    #[must_use]
+    pub const fn group(self, sender: usize) -> usize {
+        match self {
+            SenderMix::Alternate => sender % 2,
+            SenderMix::First(n) => {
+                if sender < n as usize {
+                    0
+                } else {
+                    1
+                }
+            }
+        }
+    }
+}
+
+/// Results of a single sampled network.
+#[derive(Debug)]
+pub struct NetworkEvaluation<N> {
+    /// Index of the network among those sampled.
+    pub sample: usize,
+    pub network: N,
+    pub utility: Float,
+    /// Averaged over the flows in the network.
+    pub flow_properties: FlowProperties,
+    pub fairness: FairnessStats,
+}
+
+/// Networks sampled from a `NetworkDistribution`, each with the random number generator its
+/// simulation uses, so that several CCAs can be evaluated on exactly the same networks.
+#[derive(Debug, Clone)]
+pub struct SampledNetworks<B>(Vec<(B, Rng)>);
+
+impl<B: Clone> SampledNetworks<B> {
+    /// Returns a copy of the networks with `f` applied to each, keeping their random number
+    /// generators.
+    #[must_use]
+    pub fn map(&self, f: impl Fn(&mut B)) -> SampledNetworks<B> {
+        SampledNetworks(
+            self.0
+                .iter()
+                .map(|(network, rng)| {
+                    let mut network = network.clone();
+                    f(&mut network);
+                    (network, rng.clone())
+                })
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct Evaluation<N> {
+    pub utility: Float,
+    /// Averaged over all flows.
+    pub flow_properties: FlowProperties,
+    /// Averaged over all networks.
+    pub fairness: FairnessStats,
+    /// Results of each sampled network, in the order they were sampled, leaving out networks in
+    /// which no flow was ever active.
+    pub networks: Vec<NetworkEvaluation<N>>,
+}
+
+/// Confidence level of the intervals in an `EvaluationReport`.
+pub const REPORT_CONFIDENCE: Float = 0.95;
+
+/// Results of a single network in an `EvaluationReport`.
+#[derive(Serialize, Debug)]
+pub struct NetworkReport<N> {
+    pub network: N,
+    pub utility: Float,
+    /// Mean throughput of the flows, in bits per second.
+    pub throughput: Float,
+    /// Mean RTT of the flows, in seconds, or `None` if no packets were acknowledged.
+    pub rtt: Option<Float>,
+    pub jain_index: Float,
+}
+
+/// Machine-readable results of an evaluation, with bootstrap confidence intervals for the means
+/// over networks.
+#[derive(Serialize, Debug)]
+pub struct EvaluationReport<N> {
+    pub utility: Estimate,
+    pub throughput: Estimate,
+    /// Over the networks in which any packets were acknowledged, or `None` if there were none.
+    pub rtt: Option<Estimate>,
+    pub jain_index: Estimate,
+    pub networks: Vec<NetworkReport<N>>,
+}
+
+impl<N> EvaluationReport<N> {
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn new(evaluation: Evaluation<N>, rng: &mut Rng) -> EvaluationReport<N> {
+        let networks = evaluation
+            .networks
+            .into_iter()
+            .map(|network| NetworkReport {
+                utility: network.utility,
+                throughput: network.flow_properties.throughput.bits_per_second(),
+                rtt: network.flow_properties.rtt.ok().map(TimeSpan::seconds),
+                jain_index: network.fairness.jain_index,
+                network: network.network,
+            })
+            .collect_vec();
+        let mut estimate = |value: fn(&NetworkReport<N>) -> Option<Float>| {
+            Estimate::bootstrap(
+                &networks.iter().filter_map(value).collect_vec(),
+                REPORT_CONFIDENCE,
+                rng,
+            )
+            .ok()
+        };
+        // An evaluation always has at least one network
+        EvaluationReport {
+            utility: estimate(|n| Some(n.utility)).unwrap(),
+            throughput: estimate(|n| Some(n.throughput)).unwrap(),
+            rtt: estimate(|n| n.rtt),
+            jain_index: estimate(|n| Some(n.jain_index)).unwrap(),
+            networks,
+        }
+    }
+}
+
+/// Results of evaluating a CCA at one point of a `RemyNetworkSweep`, with bootstrap confidence
+/// intervals for the means over networks.
+#[derive(Serialize, Debug)]
+pub struct SweepPoint {
+    /// Value of each dimension of the sweep, as given by `RemySweepDimension::value`.
+    pub values: Vec<Float>,
+    pub utility: Estimate,
+    /// In bits per second.
+    pub throughput: Estimate,
+    /// In seconds, or `None` if no packets were acknowledged.
+    pub rtt: Option<Estimate>,
+    pub jain_index: Estimate,
+}
+
+/// Utility of two CCAs in the same network.
+#[derive(Serialize, Debug)]
+pub struct PairedNetwork {
+    /// Index of the network among those sampled.
+    pub sample: usize,
+    pub baseline: Float,
+    pub other: Float,
+    /// `other` minus `baseline`.
+    pub difference: Float,
+}
+
+/// Paired comparison of the utility of a CCA against a baseline, both evaluated on the same
+/// `SampledNetworks`.
+#[derive(Serialize, Debug)]
+pub struct PairedComparison {
+    /// Mean improvement over the baseline, with a bootstrap confidence interval.
+    pub difference: Estimate,
+    /// Two-sided p-value of a paired permutation test of whether the mean difference is zero.
+    pub p_value: Float,
+    /// Only networks in which both CCAs had active flows.
+    pub networks: Vec<PairedNetwork>,
+}
+
+impl PairedComparison {
+    pub fn new<N>(
+        baseline: &Evaluation<N>,
+        other: &Evaluation<N>,
+        rng: &mut Rng,
+    ) -> Result<PairedComparison, NoItems> {
+        let other_utilities: BTreeMap<_, _> = other
+            .networks
+            .iter()
+            .map(|network| (network.sample, network.utility))
+            .collect();
+        let networks = baseline
+            .networks
+            .iter()
+            .filter_map(|network| {
+                let other = *other_utilities.get(&network.sample)?;
+                Some(PairedNetwork {
+                    sample: network.sample,
+                    baseline: network.utility,
+                    other,
+                    difference: other - network.utility,
+                })
+            })
+            .collect_vec();
+        let differences = networks.iter().map(|x| x.difference).collect_vec();
+        Ok(PairedComparison {
+            difference: Estimate::bootstrap(&differences, REPORT_CONFIDENCE, rng)?,
+            p_value: paired_permutation_test(&differences, rng)?,
+            networks,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct MixedEvaluation {
+    pub utility: Float,
+    /// Averaged over all flows.
+    pub flow_properties: FlowProperties,
+    /// Averaged over all networks, including flows using either CCA.
+    pub fairness: FairnessStats,
+    /// Averaged over the flows using the first CCA, or `None` if there never were any.
+    pub first: Option<FlowProperties>,
+    /// Averaged over the flows using the second CCA, or `None` if there never were any.
+    pub second: Option<FlowProperties>,
+}
+
 impl EvaluationConfig {
     pub fn evaluate<C, G, B>(
         &self,
@@ -49,42 +273,275 @@ impl EvaluationConfig {
         C: Cca,
         G: OfLifetime,
     {
-        let score_network = |(n, mut rng): (B, Rng)| {
+        self.evaluate_detailed(new_cca, network_config, utility_function, rng)
+            .map(|evaluation| (evaluation.utility, evaluation.flow_properties))
+    }
+
+    /// Like `evaluate`, but also reports how fairly the flows shared each network, and the
+    /// results of each network.
+    pub fn evaluate_detailed<C, G, B>(
+        &self,
+        new_cca: impl Fn() -> C + Sync,
+        network_config: &impl NetworkDistribution<G, Network = B>,
+        utility_function: &(impl UtilityFunction + ?Sized),
+        rng: &mut Rng,
+    ) -> Result<Evaluation<B>, NoActiveFlows>
+    where
+        B: Network<G>,
+        C: Cca,
+        G: OfLifetime,
+    {
+        self.evaluate_groups::<_, _, _, _, 1>(
+            |_| &new_cca,
+            |_| 0,
+            self.sample_networks(network_config, rng),
+            utility_function,
+        )
+        .map(|(evaluation, _)| evaluation)
+    }
+
+    /// Samples the networks that `evaluate` would, for use with `evaluate_on`.
+    pub fn sample_networks<G, B>(
+        &self,
+        network_config: &impl NetworkDistribution<G, Network = B>,
+        rng: &mut Rng,
+    ) -> SampledNetworks<B>
+    where
+        B: Network<G>,
+        G: OfLifetime,
+    {
+        SampledNetworks(
+            (0..self.network_samples)
+                .map(|_| (rng.sample(network_config), rng.create_child()))
+                .collect_vec(),
+        )
+    }
+
+    /// Like `evaluate_detailed`, but using networks that were already sampled. Evaluating
+    /// different CCAs on the same `networks` gives each of them the same random number streams.
+    pub fn evaluate_on<C, G, B>(
+        &self,
+        new_cca: impl Fn() -> C + Sync,
+        networks: &SampledNetworks<B>,
+        utility_function: &(impl UtilityFunction + ?Sized),
+    ) -> Result<Evaluation<B>, NoActiveFlows>
+    where
+        B: Network<G> + Clone,
+        C: Cca,
+        G: OfLifetime,
+    {
+        self.evaluate_groups::<_, _, _, _, 1>(
+            |_| &new_cca,
+            |_| 0,
+            networks.clone(),
+            utility_function,
+        )
+        .map(|(evaluation, _)| evaluation)
+    }
+
+    /// Evaluates a CCA at each point of `sweep` in parallel. The same networks are sampled from
+    /// `network_config` for every point, so only the swept fields differ between points.
+    pub fn sweep<C, G>(
+        &self,
+        new_cca: impl Fn() -> C + Sync,
+        network_config: &RemyNetworkDistribution,
+        sweep: &RemyNetworkSweep,
+        utility_function: &(impl UtilityFunction + ?Sized),
+        rng: &mut Rng,
+    ) -> Result<Vec<SweepPoint>, NoActiveFlows>
+    where
+        C: Cca,
+        G: OfLifetime,
+        for<'sim> G::Of<'sim>: HasRemyNetworkVariants<'sim, G::Of<'sim>>,
+    {
+        let networks = self.sample_networks::<G, _>(network_config, rng);
+        let report_rng = rng.create_child();
+        sweep
+            .points()
+            .into_par_iter()
+            .map(|point| {
+                let evaluation = self.evaluate_on::<_, G, _>(
+                    &new_cca,
+                    &networks.map(|network| sweep.apply(&point, network)),
+                    utility_function,
+                )?;
+                let report = EvaluationReport::new(evaluation, &mut report_rng.clone());
+                Ok(SweepPoint {
+                    values: sweep.values(&point),
+                    utility: report.utility,
+                    throughput: report.throughput,
+                    rtt: report.rtt,
+                    jain_index: report.jain_index,
+                })
+            })
+            .collect()
+    }
+
+    /// Evaluates two CCAs competing in the same networks, with senders assigned to each
+    /// according to `mix`.
+    pub fn evaluate_against<C, D, G, B>(
+        &self,
+        new_cca: impl Fn() -> C + Sync,
+        new_other_cca: impl Fn() -> D + Sync,
+        mix: SenderMix,
+        network_config: &impl NetworkDistribution<G, Network = B>,
+        utility_function: &(impl UtilityFunction + ?Sized),
+        rng: &mut Rng,
+    ) -> Result<MixedEvaluation, NoActiveFlows>
+    where
+        B: Network<G>,
+        C: Cca,
+        D: Cca,
+        G: OfLifetime,
+    {
+        let (new_cca, new_other_cca) = (&new_cca, &new_other_cca);
+        let (
+            Evaluation {
+                utility,
+                flow_properties,
+                fairness,
+                ..
+            },
+            [first, second],
+        ) = self.evaluate_groups(
+            |group| {
+                move || {
+                    if group == 0 {
+                        EitherCca::Left(new_cca())
+                    } else {
+                        EitherCca::Right(new_other_cca())
+                    }
+                }
+            },
+            |sender| mix.group(sender),
+            self.sample_networks(network_config, rng),
+            utility_function,
+        )?;
+        Ok(MixedEvaluation {
+            utility,
+            flow_properties,
+            fairness,
+            first,
+            second,
+        })
+    }
+
+    /// Evaluates senders split into `GROUPS` groups, where `group` maps the index of each sender
+    /// to its group, and `new_cca` returns the CCA factory for each group.
+    fn evaluate_groups<C, N, G, B, const GROUPS: usize>(
+        &self,
+        new_cca: impl Fn(usize) -> N + Sync,
+        group: impl Fn(usize) -> usize + Sync,
+        SampledNetworks(networks): SampledNetworks<B>,
+        utility_function: &(impl UtilityFunction + ?Sized),
+    ) -> Result<(Evaluation<B>, [Option<FlowProperties>; GROUPS]), NoActiveFlows>
+    where
+        B: Network<G>,
+        C: Cca,
+        N: Fn() -> C,
+        G: OfLifetime,
+    {
+        let score_network = |(sample, (n, mut rng)): (usize, (B, Rng))| {
             let flows = AppendOnlyVec::new();
             let new_flow = || {
-                let index = flows.push(RefCell::new(AverageFlowMeter::new_disabled()));
+                let index = flows.push(RefCell::new((
+                    AverageFlowMeter::new_disabled(),
+                    IntervalThroughputMeter::new(FAIRNESS_INTERVAL),
+                )));
                 &flows[index]
             };
+            let mut senders = 0;
+            let new_sender_cca = || {
+                senders += 1;
+                new_cca(group(senders - 1))
+            };
             make_guard!(guard);
             let builder = SimulatorBuilder::new(guard);
-            n.populate_sim(&builder, &new_cca, &mut rng, new_flow);
+            n.populate_sim(&builder, new_sender_cca, &mut rng, new_flow);
             let clock = builder.clock();
             let mut sim = builder.build(NothingLogger).unwrap();
             let sim_end = Time::from_sim_start(self.run_sim_for);
             while clock.time() < sim_end && sim.tick() {}
             let flow_stats = flows
                 .iter()
-                .filter_map(|x| x.borrow().average_properties(sim_end).ok())
+                .map(|x| x.borrow().0.average_properties(sim_end).ok())
                 .collect_vec();
+            let intervals = flows
+                .iter()
+                .zip(&flow_stats)
+                .filter(|(_, stats)| stats.is_some())
+                .map(|(x, _)| x.borrow().1.throughputs(sim_end))
+                .collect_vec();
+            let group_stats: [_; GROUPS] = std::array::from_fn(|g| {
+                flow_stats
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| group(i) == g)
+                    .filter_map(|(_, x)| x.clone())
+                    .average()
+                    .ok()
+            });
+            let flow_stats = flow_stats.into_iter().flatten().collect_vec();
+
+            let fairness = FairnessStats::new(&flow_stats, &intervals, FAIRNESS_INTERVAL);
 
             (
-                utility_function.utility(&flow_stats).map_err(|_| NoItems),
-                flow_stats.average(),
+                (
+                    utility_function.utility(&flow_stats).map_err(|_| NoItems),
+                    flow_stats.average(),
+                )
+                    .assert_same_emptiness(),
+                fairness,
             )
                 .assert_same_emptiness()
+                .map(|((utility, flow_properties), fairness)| {
+                    (
+                        NetworkEvaluation {
+                            sample,
+                            network: n,
+                            utility,
+                            flow_properties,
+                            fairness,
+                        },
+                        group_stats,
+                    )
+                })
         };
 
-        let networks: Vec<_> = (0..self.network_samples)
-            .map(|_| (rng.sample(network_config), rng.create_child()))
-            .collect_vec();
-        networks
+        let results = networks
             .into_par_iter()
+            .enumerate()
             .map(score_network)
             .filter_map(Result::ok)
-            .map(AveragePair::new)
-            .collect::<Vec<_>>()
-            .average()
-            .assert_same_emptiness()
-            .map_err(|_| NoActiveFlows)
+            .collect::<Vec<_>>();
+        let ((utility, flow_properties), fairness) = results
+            .iter()
+            .map(|(network, _)| {
+                AveragePair(
+                    AveragePair(network.utility, network.flow_properties.clone()),
+                    network.fairness.clone(),
+                )
+            })
+            .average();
+        let ((utility, flow_properties), fairness) =
+            ((utility, flow_properties).assert_same_emptiness(), fairness)
+                .assert_same_emptiness()
+                .map_err(|_| NoActiveFlows)?;
+        let groups = std::array::from_fn(|g| {
+            results
+                .iter()
+                .map(|(_, groups)| AverageIfSome::new(groups[g].clone()))
+                .average()
+                .ok()
+        });
+        Ok((
+            Evaluation {
+                utility,
+                flow_properties,
+                fairness,
+                networks: results.into_iter().map(|(network, _)| network).collect(),
+            },
+            groups,
+        ))
     }
 }
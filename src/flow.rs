@@ -1,66 +1,269 @@
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    ops::{Add, Div},
+};
 
+use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     quantities::{seconds, Float, InformationRate, TimeSpan},
-    util::average::{Average, AverageIfSome, AveragePair, IterAverage, NoItems},
+    util::average::{Average, AverageIfSome, AveragePair, IterAverage, NoItems, SameEmptiness},
 };
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct NoPacketsAcked;
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoTransfersCompleted;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoPacketsSent;
+
+/// Selects one of the values in a `Summary`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Statistic {
+    Mean,
+    P50,
+    P95,
+    P99,
+}
+
+/// Mean and percentiles of a set of samples.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Summary<T> {
+    pub mean: T,
+    pub p50: T,
+    pub p95: T,
+    pub p99: T,
+}
+
+impl<T> Summary<T>
+where
+    T: Average<Output = Result<T, NoItems>> + PartialOrd + Copy,
+{
+    /// Summarises `samples`, using the nearest-rank method for percentiles.
+    pub fn from_samples(samples: &[T]) -> Result<Summary<T>, NoItems> {
+        let mean = samples.iter().copied().average()?;
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_precision_loss,
+            clippy::cast_sign_loss
+        )]
+        let percentile =
+            |p: Float| sorted[((p * sorted.len() as Float).ceil() as usize).max(1) - 1];
+        Ok(Summary {
+            mean,
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+            p99: percentile(0.99),
+        })
+    }
+}
+
+impl<T: Copy> Summary<T> {
+    pub const fn get(&self, statistic: Statistic) -> T {
+        match statistic {
+            Statistic::Mean => self.mean,
+            Statistic::P50 => self.p50,
+            Statistic::P95 => self.p95,
+            Statistic::P99 => self.p99,
+        }
+    }
+}
+
+impl<T: Add<T, Output = T>> Add<Summary<T>> for Summary<T> {
+    type Output = Summary<T>;
+
+    fn add(self, rhs: Summary<T>) -> Self::Output {
+        Summary {
+            mean: self.mean + rhs.mean,
+            p50: self.p50 + rhs.p50,
+            p95: self.p95 + rhs.p95,
+            p99: self.p99 + rhs.p99,
+        }
+    }
+}
+
+impl<T: Div<Float, Output = T>> Div<Float> for Summary<T> {
+    type Output = Summary<T>;
+
+    fn div(self, rhs: Float) -> Self::Output {
+        Summary {
+            mean: self.mean / rhs,
+            p50: self.p50 / rhs,
+            p95: self.p95 / rhs,
+            p99: self.p99 / rhs,
+        }
+    }
+}
+
+/// Distribution of the round-trip times measured by a flow.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DelayStats {
+    pub rtt: Summary<TimeSpan>,
+    /// Amount by which each RTT exceeded the lowest RTT measured before it.
+    pub queueing_delay: Summary<TimeSpan>,
+}
+
+impl Add<DelayStats> for DelayStats {
+    type Output = DelayStats;
+
+    fn add(self, rhs: DelayStats) -> Self::Output {
+        DelayStats {
+            rtt: self.rtt + rhs.rtt,
+            queueing_delay: self.queueing_delay + rhs.queueing_delay,
+        }
+    }
+}
+
+impl Div<Float> for DelayStats {
+    type Output = DelayStats;
+
+    fn div(self, rhs: Float) -> Self::Output {
+        DelayStats {
+            rtt: self.rtt / rhs,
+            queueing_delay: self.queueing_delay / rhs,
+        }
+    }
+}
+
+/// Statistics of the finite transfers completed by a flow.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FctStats {
+    /// Time from each transfer being requested until all of its data was acknowledged.
+    pub completion_time: Summary<TimeSpan>,
+    /// Completion time of each transfer divided by the time it would have taken on an idle
+    /// network.
+    pub slowdown: Summary<Float>,
+}
+
+impl Add<FctStats> for FctStats {
+    type Output = FctStats;
+
+    fn add(self, rhs: FctStats) -> Self::Output {
+        FctStats {
+            completion_time: self.completion_time + rhs.completion_time,
+            slowdown: self.slowdown + rhs.slowdown,
+        }
+    }
+}
+
+impl Div<Float> for FctStats {
+    type Output = FctStats;
+
+    fn div(self, rhs: Float) -> Self::Output {
+        FctStats {
+            completion_time: self.completion_time / rhs,
+            slowdown: self.slowdown / rhs,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct FlowProperties {
     pub throughput: InformationRate,
+    /// Throughput excluding packets which had already been received.
+    pub goodput: InformationRate,
     pub rtt: Result<TimeSpan, NoPacketsAcked>,
+    pub delay: Result<DelayStats, NoPacketsAcked>,
+    pub fct: Result<FctStats, NoTransfersCompleted>,
+    /// Fraction of the data sent, including retransmissions, that wasn't acknowledged.
+    pub loss_rate: Result<Float, NoPacketsSent>,
 }
 
 impl Display for FlowProperties {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "FlowProperties {{ throughput: {}, goodput: {}, ",
+            self.throughput, self.goodput
+        )?;
         match self.rtt {
-            Ok(average_rtt) => write!(
+            Ok(average_rtt) => write!(f, "rtt: {average_rtt}")?,
+            Err(_) => write!(f, "rtt: NoPacketsAcked")?,
+        }
+        if let Ok(loss_rate) = self.loss_rate {
+            write!(f, ", loss rate: {loss_rate:.4}")?;
+        }
+        if let Ok(delay) = &self.delay {
+            write!(
                 f,
-                "FlowProperties {{ throughput: {}, rtt: {} }}",
-                self.throughput, average_rtt
-            ),
-            Err(_) => write!(
+                ", rtt p95: {}, rtt p99: {}, queueing delay p99: {}",
+                delay.rtt.p95, delay.rtt.p99, delay.queueing_delay.p99
+            )?;
+        }
+        if let Ok(fct) = &self.fct {
+            write!(
                 f,
-                "FlowProperties {{ throughput: {}, rtt: NoPacketsAcked }}",
-                self.throughput
-            ),
+                ", fct: {}, slowdown: {:.2}",
+                fct.completion_time.mean, fct.slowdown.mean
+            )?;
         }
+        write!(f, " }}")
     }
 }
 
+type FlowPropertiesAverage = AveragePair<
+    AveragePair<AveragePair<InformationRate, InformationRate>, AverageIfSome<TimeSpan>>,
+    AveragePair<
+        AveragePair<AverageIfSome<DelayStats>, AverageIfSome<FctStats>>,
+        AverageIfSome<Float>,
+    >,
+>;
+
 impl Average for FlowProperties {
-    type Aggregator =
-        <AveragePair<InformationRate, AverageIfSome<TimeSpan>> as Average>::Aggregator;
+    type Aggregator = <FlowPropertiesAverage as Average>::Aggregator;
     type Output = Result<FlowProperties, NoItems>;
 
     fn average(aggregator: Self::Aggregator) -> Self::Output {
-        let (average_throughput, average_rtt) =
-            AveragePair::<InformationRate, AverageIfSome<TimeSpan>>::average(aggregator);
-        match average_throughput {
-            Ok(average_throughput) => Ok(FlowProperties {
+        let (
+            ((average_throughput, average_goodput), average_rtt),
+            ((average_delay, average_fct), average_loss_rate),
+        ) = FlowPropertiesAverage::average(aggregator);
+        match (average_throughput, average_goodput).assert_same_emptiness() {
+            Ok((average_throughput, average_goodput)) => Ok(FlowProperties {
                 throughput: average_throughput,
+                goodput: average_goodput,
                 rtt: average_rtt.map_err(|_| NoPacketsAcked),
+                delay: average_delay.map_err(|_| NoPacketsAcked),
+                fct: average_fct.map_err(|_| NoTransfersCompleted),
+                loss_rate: average_loss_rate.map_err(|_| NoPacketsSent),
             }),
             Err(NoItems) => {
                 assert!(average_rtt.is_err());
+                assert!(average_delay.is_err());
+                assert!(average_fct.is_err());
+                assert!(average_loss_rate.is_err());
                 Err(NoItems)
             }
         }
     }
 
     fn new_aggregator() -> Self::Aggregator {
-        AveragePair::<InformationRate, AverageIfSome<TimeSpan>>::new_aggregator()
+        FlowPropertiesAverage::new_aggregator()
     }
 
     fn aggregate(aggregator: Self::Aggregator, next: Self) -> Self::Aggregator {
-        AveragePair::<InformationRate, AverageIfSome<TimeSpan>>::aggregate(
+        FlowPropertiesAverage::aggregate(
             aggregator,
-            AveragePair(next.throughput, AverageIfSome::new(next.rtt.ok())),
+            AveragePair(
+                AveragePair(
+                    AveragePair(next.throughput, next.goodput),
+                    AverageIfSome::new(next.rtt.ok()),
+                ),
+                AveragePair(
+                    AveragePair(
+                        AverageIfSome::new(next.delay.ok()),
+                        AverageIfSome::new(next.fct.ok()),
+                    ),
+                    AverageIfSome::new(next.loss_rate.ok()),
+                ),
+            ),
         )
     }
 }
@@ -68,6 +271,177 @@ impl Average for FlowProperties {
 #[derive(Debug)]
 pub struct FlowNeverActive;
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NeverConverged;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlowStarved;
+
+/// Jain's index of a set of flows whose throughputs in an interval is at least this are treated
+/// as having converged to their fair shares.
+pub const CONVERGED_JAIN_INDEX: Float = 0.9;
+
+/// Returns Jain's fairness index of `values`, which ranges from `1 / n` when one of `n` values is
+/// non-zero, to 1 when they are all equal. Returns `None` if `values` is empty.
+#[must_use]
+pub fn jain_index(values: &[Float]) -> Option<Float> {
+    if values.is_empty() {
+        return None;
+    }
+    let sum = values.iter().sum::<Float>();
+    let sum_of_squares = values.iter().map(|x| x * x).sum::<Float>();
+    #[allow(clippy::cast_precision_loss)]
+    Some(if sum_of_squares == 0. {
+        1.
+    } else {
+        sum * sum / (values.len() as Float * sum_of_squares)
+    })
+}
+
+/// How evenly throughput was shared between the flows of a network.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FairnessStats {
+    /// Jain's index of the average throughputs of the flows.
+    pub jain_index: Float,
+    /// Ratio of the highest to the lowest average throughput, or `FlowStarved` if any flow had no
+    /// throughput at all.
+    ///
+    /// Networks in which a flow was starved are left out when averaging over networks, since the
+    /// ratio is infinite for them, and are counted in `starved_fraction` instead.
+    pub max_min_ratio: Result<Float, FlowStarved>,
+    /// 1 if any flow had no throughput at all, and 0 otherwise, so that when averaged over
+    /// networks it is the fraction of networks in which a flow was starved.
+    pub starved_fraction: Float,
+    /// Mean time, after each change in the set of active flows, until Jain's index of their
+    /// throughputs reached `CONVERGED_JAIN_INDEX`.
+    ///
+    /// Changes after which there was only one active flow, or after which the flows didn't
+    /// converge before the next change, are ignored.
+    pub convergence_time: Result<TimeSpan, NeverConverged>,
+}
+
+impl FairnessStats {
+    /// `intervals` has the throughput of each flow in consecutive intervals of length `interval`,
+    /// or `None` for intervals in which the flow wasn't active throughout.
+    pub fn new(
+        flows: &[FlowProperties],
+        intervals: &[Vec<Option<InformationRate>>],
+        interval: TimeSpan,
+    ) -> Result<FairnessStats, NoItems> {
+        let throughputs = flows
+            .iter()
+            .map(|flow| flow.throughput.bits_per_second())
+            .collect::<Vec<_>>();
+        let jain_index = jain_index(&throughputs).ok_or(NoItems)?;
+        let max = throughputs.iter().copied().fold(0., Float::max);
+        let min = throughputs
+            .iter()
+            .copied()
+            .fold(Float::INFINITY, Float::min);
+        let max_min_ratio = if min > 0. {
+            Ok(max / min)
+        } else {
+            Err(FlowStarved)
+        };
+        Ok(FairnessStats {
+            jain_index,
+            starved_fraction: if max_min_ratio.is_ok() { 0. } else { 1. },
+            max_min_ratio,
+            convergence_time: convergence_time(intervals, interval),
+        })
+    }
+}
+
+fn convergence_time(
+    intervals: &[Vec<Option<InformationRate>>],
+    interval: TimeSpan,
+) -> Result<TimeSpan, NeverConverged> {
+    let num_intervals = intervals.iter().map(Vec::len).max().unwrap_or(0);
+    let mut times = Vec::new();
+    // Active flows, first interval, and whether they have converged yet
+    let mut epoch: Option<(Vec<usize>, usize, bool)> = None;
+    for i in 0..num_intervals {
+        let (active, rates): (Vec<_>, Vec<_>) = intervals
+            .iter()
+            .enumerate()
+            .filter_map(|(flow, x)| Some((flow, x.get(i).copied().flatten()?.bits_per_second())))
+            .unzip();
+        if epoch.as_ref().is_none_or(|(flows, _, _)| *flows != active) {
+            epoch = Some((active, i, false));
+        }
+        let (flows, start, converged) = epoch.as_mut().unwrap();
+        if !*converged && flows.len() >= 2 && jain_index(&rates) >= Some(CONVERGED_JAIN_INDEX) {
+            *converged = true;
+            #[allow(clippy::cast_precision_loss)]
+            times.push((i + 1 - *start) as Float * interval);
+        }
+    }
+    times.into_iter().average().map_err(|_| NeverConverged)
+}
+
+impl Display for FairnessStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "FairnessStats {{ jain index: {:.3}, max/min throughput: ",
+            self.jain_index
+        )?;
+        match self.max_min_ratio {
+            Ok(ratio) => write!(f, "{ratio:.2}")?,
+            Err(_) => write!(f, "FlowStarved")?,
+        }
+        write!(
+            f,
+            ", starved fraction: {:.2}, convergence time: ",
+            self.starved_fraction
+        )?;
+        match self.convergence_time {
+            Ok(time) => write!(f, "{time} }}"),
+            Err(_) => write!(f, "NeverConverged }}"),
+        }
+    }
+}
+
+type FairnessStatsAverage = AveragePair<
+    AveragePair<Float, Float>,
+    AveragePair<AverageIfSome<Float>, AverageIfSome<TimeSpan>>,
+>;
+
+impl Average for FairnessStats {
+    type Aggregator = <FairnessStatsAverage as Average>::Aggregator;
+    type Output = Result<FairnessStats, NoItems>;
+
+    fn average(aggregator: Self::Aggregator) -> Self::Output {
+        let ((jain_index, starved_fraction), (max_min_ratio, convergence_time)) =
+            FairnessStatsAverage::average(aggregator);
+        let (jain_index, starved_fraction) =
+            (jain_index, starved_fraction).assert_same_emptiness()?;
+        Ok(FairnessStats {
+            jain_index,
+            max_min_ratio: max_min_ratio.map_err(|_| FlowStarved),
+            starved_fraction,
+            convergence_time: convergence_time.map_err(|_| NeverConverged),
+        })
+    }
+
+    fn new_aggregator() -> Self::Aggregator {
+        FairnessStatsAverage::new_aggregator()
+    }
+
+    fn aggregate(aggregator: Self::Aggregator, next: Self) -> Self::Aggregator {
+        FairnessStatsAverage::aggregate(
+            aggregator,
+            AveragePair(
+                AveragePair(next.jain_index, next.starved_fraction),
+                AveragePair(
+                    AverageIfSome::new(next.max_min_ratio.ok()),
+                    AverageIfSome::new(next.convergence_time.ok()),
+                ),
+            ),
+        )
+    }
+}
+
 fn alpha_fairness(x: Float, alpha: Float) -> Float {
     let x = x + 0.000_001;
     if (alpha - 1.).abs() < 0.000_001 {
@@ -87,12 +461,22 @@ pub trait UtilityFunction: Sync {
 #[derive(Serialize, Deserialize)]
 pub enum UtilityConfig {
     AlphaFairness(AlphaFairness),
+    TailLatency(TailLatency),
+    FlowCompletionTime(FlowCompletionTime),
+    Slowdown(Slowdown),
+    FairnessPenalty(FairnessPenalty),
+    MeanOverFlows(MeanOverFlows),
 }
 
 impl UtilityFunction for UtilityConfig {
     fn utility(&self, flows: &[FlowProperties]) -> Result<Float, NoActiveFlows> {
         match self {
             UtilityConfig::AlphaFairness(x) => x.utility(flows),
+            UtilityConfig::TailLatency(x) => x.utility(flows),
+            UtilityConfig::FlowCompletionTime(x) => x.utility(flows),
+            UtilityConfig::Slowdown(x) => x.utility(flows),
+            UtilityConfig::FairnessPenalty(x) => x.utility(flows),
+            UtilityConfig::MeanOverFlows(x) => x.utility(flows),
         }
     }
 }
@@ -125,17 +509,20 @@ impl AlphaFairness {
     };
 }
 
-impl UtilityFunction for AlphaFairness {
-    fn utility(&self, flows: &[FlowProperties]) -> Result<Float, NoActiveFlows> {
+impl AlphaFairness {
+    /// Like `utility`, but penalising the delay given by `rtt` rather than the mean RTT.
+    fn utility_with_rtt(
+        &self,
+        flows: &[FlowProperties],
+        rtt: impl Fn(&FlowProperties) -> Option<TimeSpan>,
+    ) -> Result<Float, NoActiveFlows> {
         assert!(self.delta >= 0.);
         let flow_utility = |properties: &FlowProperties| {
             let throughput_utility = alpha_fairness(properties.throughput.value(), self.alpha);
             let rtt_utility = -self.delta
                 * alpha_fairness(
-                    properties
-                        .rtt
-                        .as_ref()
-                        .unwrap_or(&self.worst_case_rtt)
+                    rtt(properties)
+                        .unwrap_or(self.worst_case_rtt)
                         .seconds()
                         .clamp(0., self.worst_case_rtt.seconds()),
                     self.beta,
@@ -152,15 +539,337 @@ impl UtilityFunction for AlphaFairness {
     }
 }
 
+impl UtilityFunction for AlphaFairness {
+    fn utility(&self, flows: &[FlowProperties]) -> Result<Float, NoActiveFlows> {
+        self.utility_with_rtt(flows, |properties| properties.rtt.clone().ok())
+    }
+}
+
+/// `AlphaFairness`, but penalising a percentile of each flow's RTT instead of its mean, so that
+/// a flow with a low average delay but occasional large spikes scores worse.
+#[derive(Serialize, Deserialize)]
+pub struct TailLatency {
+    #[serde(flatten)]
+    fairness: AlphaFairness,
+    statistic: Statistic,
+}
+
+impl TailLatency {
+    pub const PROPORTIONAL_THROUGHPUT_P99_DELAY_FAIRNESS: TailLatency = TailLatency {
+        fairness: AlphaFairness::PROPORTIONAL_THROUGHPUT_DELAY_FAIRNESS,
+        statistic: Statistic::P99,
+    };
+}
+
+impl UtilityFunction for TailLatency {
+    fn utility(&self, flows: &[FlowProperties]) -> Result<Float, NoActiveFlows> {
+        self.fairness.utility_with_rtt(flows, |properties| {
+            properties
+                .delay
+                .as_ref()
+                .ok()
+                .map(|delay| delay.rtt.get(self.statistic))
+        })
+    }
+}
+
+/// Negative completion time in seconds of each flow's transfers, averaged over flows.
+///
+/// Each flow's completion times are summarised by `statistic`. Flows which never completed a
+/// transfer count as taking `worst_case`, and transfers still in progress when the simulation
+/// ends are ignored.
+#[derive(Serialize, Deserialize)]
+pub struct FlowCompletionTime {
+    statistic: Statistic,
+    worst_case: TimeSpan,
+}
+
+impl FlowCompletionTime {
+    pub const MEAN: FlowCompletionTime = FlowCompletionTime {
+        statistic: Statistic::Mean,
+        worst_case: seconds(60.),
+    };
+}
+
+impl UtilityFunction for FlowCompletionTime {
+    fn utility(&self, flows: &[FlowProperties]) -> Result<Float, NoActiveFlows> {
+        flows
+            .iter()
+            .map(|properties| {
+                -properties
+                    .fct
+                    .as_ref()
+                    .map_or(self.worst_case, |fct| {
+                        fct.completion_time.get(self.statistic)
+                    })
+                    .seconds()
+            })
+            .average()
+            .map_err(|_| NoActiveFlows)
+    }
+}
+
+/// Like `FlowCompletionTime`, but using the slowdown of each transfer, so that short transfers
+/// count as much as long ones.
+#[derive(Serialize, Deserialize)]
+pub struct Slowdown {
+    statistic: Statistic,
+    worst_case: Float,
+}
+
+impl Slowdown {
+    pub const P99: Slowdown = Slowdown {
+        statistic: Statistic::P99,
+        worst_case: 1000.,
+    };
+}
+
+impl UtilityFunction for Slowdown {
+    fn utility(&self, flows: &[FlowProperties]) -> Result<Float, NoActiveFlows> {
+        flows
+            .iter()
+            .map(|properties| {
+                -properties
+                    .fct
+                    .as_ref()
+                    .map_or(self.worst_case, |fct| fct.slowdown.get(self.statistic))
+            })
+            .average()
+            .map_err(|_| NoActiveFlows)
+    }
+}
+
+/// Another utility function, minus `weight` times how far Jain's index of the throughputs of the
+/// flows falls short of 1.
+#[derive(Serialize, Deserialize)]
+pub struct FairnessPenalty {
+    pub utility: Box<UtilityConfig>,
+    pub weight: Float,
+}
+
+impl UtilityFunction for FairnessPenalty {
+    fn utility(&self, flows: &[FlowProperties]) -> Result<Float, NoActiveFlows> {
+        let throughputs = flows
+            .iter()
+            .map(|flow| flow.throughput.bits_per_second())
+            .collect::<Vec<_>>();
+        let jain_index = jain_index(&throughputs).ok_or(NoActiveFlows)?;
+        Ok(self.utility.utility(flows)? - self.weight * (1. - jain_index))
+    }
+}
+
+/// A property of a single flow, in bits per second for rates and seconds for times.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FlowMetric {
+    Throughput,
+    Goodput,
+    /// Mean round-trip time.
+    Rtt,
+    RttPercentile {
+        statistic: Statistic,
+    },
+    QueueingDelay {
+        statistic: Statistic,
+    },
+    CompletionTime {
+        statistic: Statistic,
+    },
+    Slowdown {
+        statistic: Statistic,
+    },
+    /// Fraction of the data sent that wasn't acknowledged.
+    LossRate,
+}
+
+impl FlowMetric {
+    /// Whether every flow has a value of this metric, even if it never received anything.
+    const fn always_measured(self) -> bool {
+        matches!(self, FlowMetric::Throughput | FlowMetric::Goodput)
+    }
+
+    /// Returns `None` if the flow has no measurements of this metric.
+    #[must_use]
+    pub fn of(self, properties: &FlowProperties) -> Option<Float> {
+        match self {
+            FlowMetric::Throughput => Some(properties.throughput.value()),
+            FlowMetric::Goodput => Some(properties.goodput.value()),
+            FlowMetric::Rtt => properties.rtt.as_ref().ok().map(|rtt| rtt.seconds()),
+            FlowMetric::RttPercentile { statistic } => properties
+                .delay
+                .as_ref()
+                .ok()
+                .map(|delay| delay.rtt.get(statistic).seconds()),
+            FlowMetric::QueueingDelay { statistic } => properties
+                .delay
+                .as_ref()
+                .ok()
+                .map(|delay| delay.queueing_delay.get(statistic).seconds()),
+            FlowMetric::CompletionTime { statistic } => properties
+                .fct
+                .as_ref()
+                .ok()
+                .map(|fct| fct.completion_time.get(statistic).seconds()),
+            FlowMetric::Slowdown { statistic } => properties
+                .fct
+                .as_ref()
+                .ok()
+                .map(|fct| fct.slowdown.get(statistic)),
+            FlowMetric::LossRate => properties.loss_rate.clone().ok(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WeightedTerm {
+    pub weight: Float,
+    pub term: FlowExpression,
+}
+
+/// Utility of a single flow, built up from its `FlowMetric`s.
+#[derive(Serialize, Deserialize, Debug)]
+pub enum FlowExpression {
+    Constant(Float),
+    /// Value of the metric, or `if_missing` if the flow has no measurements of it.
+    Metric {
+        metric: FlowMetric,
+        #[serde(default)]
+        if_missing: Option<Float>,
+    },
+    WeightedSum(Vec<WeightedTerm>),
+    Product(Vec<FlowExpression>),
+    Min(Vec<FlowExpression>),
+    Max(Vec<FlowExpression>),
+    Clamp {
+        of: Box<FlowExpression>,
+        min: Float,
+        max: Float,
+    },
+    /// Natural logarithm.
+    Log(Box<FlowExpression>),
+    Power {
+        of: Box<FlowExpression>,
+        exponent: Float,
+    },
+    /// The alpha-fair utility of the value, as used by `AlphaFairness`, which is its logarithm
+    /// when `alpha` is 1.
+    AlphaFairness {
+        of: Box<FlowExpression>,
+        alpha: Float,
+    },
+}
+
+impl FlowExpression {
+    /// Returns an error if the expression uses a metric that a flow might have no measurements
+    /// of, without an `if_missing` value.
+    fn check_if_missing(&self) -> anyhow::Result<()> {
+        let all = |expressions: &[FlowExpression]| {
+            expressions
+                .iter()
+                .try_for_each(FlowExpression::check_if_missing)
+        };
+        match self {
+            FlowExpression::Constant(_) => Ok(()),
+            FlowExpression::Metric { metric, if_missing } => {
+                if if_missing.is_none() && !metric.always_measured() {
+                    return Err(anyhow!(
+                        "Metric {metric:?} needs an if_missing value, as flows may have no \
+                         measurements of it"
+                    ));
+                }
+                Ok(())
+            }
+            FlowExpression::WeightedSum(terms) => terms
+                .iter()
+                .try_for_each(|term| term.term.check_if_missing()),
+            FlowExpression::Product(expressions)
+            | FlowExpression::Min(expressions)
+            | FlowExpression::Max(expressions) => all(expressions),
+            FlowExpression::Clamp { of, .. }
+            | FlowExpression::Log(of)
+            | FlowExpression::Power { of, .. }
+            | FlowExpression::AlphaFairness { of, .. } => of.check_if_missing(),
+        }
+    }
+
+    /// Returns `None` if the expression uses a metric that the flow has no measurements of, and
+    /// that has no `if_missing` value.
+    #[must_use]
+    pub fn evaluate(&self, properties: &FlowProperties) -> Option<Float> {
+        let all = |expressions: &[FlowExpression]| {
+            expressions
+                .iter()
+                .map(|x| x.evaluate(properties))
+                .collect::<Option<Vec<_>>>()
+        };
+        Some(match self {
+            FlowExpression::Constant(value) => *value,
+            FlowExpression::Metric { metric, if_missing } => {
+                metric.of(properties).or(*if_missing)?
+            }
+            FlowExpression::WeightedSum(terms) => terms.iter().try_fold(0., |acc, term| {
+                Some(acc + term.weight * term.term.evaluate(properties)?)
+            })?,
+            FlowExpression::Product(factors) => all(factors)?.into_iter().product(),
+            FlowExpression::Min(options) => {
+                all(options)?.into_iter().fold(Float::INFINITY, Float::min)
+            }
+            FlowExpression::Max(options) => all(options)?
+                .into_iter()
+                .fold(Float::NEG_INFINITY, Float::max),
+            FlowExpression::Clamp { of, min, max } => of.evaluate(properties)?.clamp(*min, *max),
+            FlowExpression::Log(of) => of.evaluate(properties)?.ln(),
+            FlowExpression::Power { of, exponent } => of.evaluate(properties)?.powf(*exponent),
+            FlowExpression::AlphaFairness { of, alpha } => {
+                alpha_fairness(of.evaluate(properties)?, *alpha)
+            }
+        })
+    }
+}
+
+/// Mean of a `FlowExpression` over every flow.
+///
+/// Every metric that a flow might have no measurements of, such as the RTT of a flow that was
+/// starved, needs an `if_missing` value, so that no flow is left out of the mean.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(try_from = "FlowExpression")]
+pub struct MeanOverFlows(FlowExpression);
+
+impl TryFrom<FlowExpression> for MeanOverFlows {
+    type Error = anyhow::Error;
+
+    fn try_from(expression: FlowExpression) -> anyhow::Result<Self> {
+        expression.check_if_missing()?;
+        Ok(MeanOverFlows(expression))
+    }
+}
+
+impl UtilityFunction for MeanOverFlows {
+    fn utility(&self, flows: &[FlowProperties]) -> Result<Float, NoActiveFlows> {
+        flows
+            .iter()
+            .map(|properties| {
+                self.0
+                    .evaluate(properties)
+                    .expect("Every metric that might be missing to have an if_missing value")
+            })
+            .average()
+            .map_err(|_| NoActiveFlows)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
-        flow::FlowProperties,
+        flow::{AlphaFairness, FlowProperties, UtilityConfig, UtilityFunction},
         quantities::{bits_per_second, seconds, Float},
         util::average::{IterAverage, NoItems},
     };
 
-    use super::NoPacketsAcked;
+    use super::{
+        convergence_time, jain_index, FairnessStats, FlowExpression, FlowMetric, FlowStarved,
+        NeverConverged, NoPacketsAcked, NoPacketsSent, NoTransfersCompleted, Summary,
+    };
 
     #[test]
     fn flow_properties_average() {
@@ -169,12 +878,20 @@ mod tests {
                 .into_iter()
                 .map(|(average_throughput, average_rtt)| FlowProperties {
                     throughput: bits_per_second(average_throughput),
+                    goodput: bits_per_second(average_throughput / 2.),
                     rtt: average_rtt.map(seconds).ok_or(NoPacketsAcked),
+                    delay: Err(NoPacketsAcked),
+                    fct: Err(NoTransfersCompleted),
+                    loss_rate: Err(NoPacketsSent),
                 })
                 .average(),
             Ok(FlowProperties {
                 throughput: bits_per_second(0.5),
-                rtt: Ok(seconds(3.))
+                goodput: bits_per_second(0.25),
+                rtt: Ok(seconds(3.)),
+                delay: Err(NoPacketsAcked),
+                fct: Err(NoTransfersCompleted),
+                loss_rate: Err(NoPacketsSent),
             })
         );
         assert_eq!(
@@ -182,12 +899,20 @@ mod tests {
                 .into_iter()
                 .map(|(average_throughput, average_rtt)| FlowProperties {
                     throughput: bits_per_second(average_throughput),
+                    goodput: bits_per_second(average_throughput / 2.),
                     rtt: average_rtt.map(seconds).ok_or(NoPacketsAcked),
+                    delay: Err(NoPacketsAcked),
+                    fct: Err(NoTransfersCompleted),
+                    loss_rate: Err(NoPacketsSent),
                 })
                 .average(),
             Ok(FlowProperties {
                 throughput: bits_per_second(0.5),
-                rtt: Err(NoPacketsAcked)
+                goodput: bits_per_second(0.25),
+                rtt: Err(NoPacketsAcked),
+                delay: Err(NoPacketsAcked),
+                fct: Err(NoTransfersCompleted),
+                loss_rate: Err(NoPacketsSent),
             })
         );
         assert_eq!(
@@ -196,11 +921,311 @@ mod tests {
                 .map(
                     |(average_throughput, average_rtt): (Float, Option<Float>)| FlowProperties {
                         throughput: bits_per_second(average_throughput),
+                        goodput: bits_per_second(average_throughput / 2.),
                         rtt: average_rtt.map(seconds).ok_or(NoPacketsAcked),
+                        delay: Err(NoPacketsAcked),
+                        fct: Err(NoTransfersCompleted),
+                        loss_rate: Err(NoPacketsSent),
                     }
                 )
                 .average(),
             Err(NoItems)
         );
     }
+
+    #[test]
+    fn summary_uses_nearest_rank_percentiles() {
+        let samples: Vec<Float> = (1..=200).map(Float::from).collect();
+        assert_eq!(
+            Summary::from_samples(&samples),
+            Ok(Summary {
+                mean: 100.5,
+                p50: 100.,
+                p95: 190.,
+                p99: 198.,
+            })
+        );
+        assert_eq!(
+            Summary::from_samples(&[3.]),
+            Ok(Summary {
+                mean: 3.,
+                p50: 3.,
+                p95: 3.,
+                p99: 3.,
+            })
+        );
+        assert_eq!(Summary::<Float>::from_samples(&[]), Err(NoItems));
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn jain_index_ranges_from_one_over_n_to_one() {
+        assert_eq!(jain_index(&[]), None);
+        assert_eq!(jain_index(&[2., 2., 2.]), Some(1.));
+        assert_eq!(jain_index(&[0., 0., 6.]), Some(1. / 3.));
+        assert_eq!(jain_index(&[1., 3.]), Some(0.8));
+    }
+
+    #[test]
+    fn starved_networks_left_out_of_max_min_ratio() {
+        let stats = |max_min_ratio: Result<Float, FlowStarved>| FairnessStats {
+            jain_index: 0.5,
+            starved_fraction: if max_min_ratio.is_ok() { 0. } else { 1. },
+            max_min_ratio,
+            convergence_time: Err(NeverConverged),
+        };
+        let average = vec![stats(Ok(2.)), stats(Err(FlowStarved)), stats(Ok(4.))]
+            .into_iter()
+            .average()
+            .unwrap();
+        assert_eq!(average.max_min_ratio, Ok(3.));
+        assert!((average.starved_fraction - 1. / 3.).abs() < 1e-9);
+        assert_eq!(
+            vec![stats(Err(FlowStarved))]
+                .into_iter()
+                .average()
+                .unwrap()
+                .max_min_ratio,
+            Err(FlowStarved)
+        );
+    }
+
+    #[test]
+    fn convergence_time_restarts_when_flows_change() {
+        let rate = |x: Float| Some(bits_per_second(x));
+        let intervals = [
+            // Alone at first, then converges 3 intervals after the second flow joins
+            vec![rate(10.), rate(10.), rate(9.), rate(8.), rate(6.), rate(5.)],
+            vec![None, None, rate(1.), rate(2.), rate(4.), rate(5.)],
+            // Joins in the last interval, and never converges
+            vec![None, None, None, None, None, rate(0.)],
+        ];
+        assert_eq!(
+            convergence_time(&intervals[..2], seconds(0.25)),
+            Ok(seconds(0.75))
+        );
+        assert_eq!(
+            convergence_time(&intervals, seconds(0.25)),
+            Ok(seconds(0.75))
+        );
+        assert_eq!(
+            convergence_time(&intervals[1..], seconds(0.25)),
+            Err(NeverConverged)
+        );
+    }
+
+    const PTDF_EXPRESSION: &str = r#"{
+        "MeanOverFlows": {
+            "WeightedSum": [
+                {
+                    "weight": 1.0,
+                    "term": {
+                        "WeightedSum": [
+                            {
+                                "weight": 1.0,
+                                "term": {
+                                    "AlphaFairness": {
+                                        "of": { "Metric": { "metric": { "type": "throughput" } } },
+                                        "alpha": 1.0
+                                    }
+                                }
+                            },
+                            {
+                                "weight": -1.0,
+                                "term": {
+                                    "AlphaFairness": {
+                                        "of": {
+                                            "Clamp": {
+                                                "of": {
+                                                    "Metric": {
+                                                        "metric": { "type": "rtt" },
+                                                        "if_missing": 10.0
+                                                    }
+                                                },
+                                                "min": 0.0,
+                                                "max": 10.0
+                                            }
+                                        },
+                                        "alpha": 1.0
+                                    }
+                                }
+                            }
+                        ]
+                    }
+                },
+                {
+                    "weight": -1.0,
+                    "term": {
+                        "WeightedSum": [
+                            {
+                                "weight": 1.0,
+                                "term": { "AlphaFairness": { "of": { "Constant": 0.0 }, "alpha": 1.0 } }
+                            },
+                            {
+                                "weight": -1.0,
+                                "term": { "AlphaFairness": { "of": { "Constant": 10.0 }, "alpha": 1.0 } }
+                            }
+                        ]
+                    }
+                }
+            ]
+        }
+    }"#;
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn expression_reproduces_proportional_throughput_delay_fairness() {
+        let expression: UtilityConfig = serde_json::from_str(PTDF_EXPRESSION).unwrap();
+        let flow = |throughput: Float, rtt: Option<Float>| FlowProperties {
+            throughput: bits_per_second(throughput),
+            goodput: bits_per_second(throughput),
+            rtt: rtt.map(seconds).ok_or(NoPacketsAcked),
+            delay: Err(NoPacketsAcked),
+            fct: Err(NoTransfersCompleted),
+            loss_rate: Err(NoPacketsSent),
+        };
+        let networks = [
+            vec![flow(1_000_000., Some(0.05))],
+            vec![flow(12_345.678, Some(0.3)), flow(0., None)],
+            vec![
+                flow(3.3e7, Some(12.)),
+                flow(1e-3, Some(0.)),
+                flow(7., Some(1e-4)),
+            ],
+            vec![],
+        ];
+        for flows in &networks {
+            assert_eq!(
+                expression.utility(flows),
+                AlphaFairness::PROPORTIONAL_THROUGHPUT_DELAY_FAIRNESS.utility(flows)
+            );
+        }
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn expression_penalises_loss_rate() {
+        // log throughput - 0.5 log delay - 10 loss rate
+        let expression: UtilityConfig = serde_json::from_str(
+            r#"{
+                "MeanOverFlows": {
+                    "WeightedSum": [
+                        {
+                            "weight": 1.0,
+                            "term": { "Log": { "Metric": { "metric": { "type": "throughput" } } } }
+                        },
+                        {
+                            "weight": -0.5,
+                            "term": {
+                                "Log": {
+                                    "Metric": { "metric": { "type": "rtt" }, "if_missing": 10.0 }
+                                }
+                            }
+                        },
+                        {
+                            "weight": -10.0,
+                            "term": {
+                                "Metric": { "metric": { "type": "loss_rate" }, "if_missing": 1.0 }
+                            }
+                        }
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        let flow = |loss_rate: Float| FlowProperties {
+            throughput: bits_per_second(1_000_000.),
+            goodput: bits_per_second(1_000_000.),
+            rtt: Ok(seconds(0.1)),
+            delay: Err(NoPacketsAcked),
+            fct: Err(NoTransfersCompleted),
+            loss_rate: Ok(loss_rate),
+        };
+        let utility = |loss_rate| expression.utility(&[flow(loss_rate)]).unwrap();
+        assert_eq!(
+            utility(0.),
+            1_000_000_f64.ln() - 0.5 * 0.1_f64.ln() - 10. * 0.
+        );
+        assert_eq!(utility(0.01), utility(0.) - 10. * 0.01);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn mean_over_flows_includes_starved_flows() {
+        let rtt = |if_missing: &str| {
+            format!(
+                r#"{{
+                    "MeanOverFlows": {{
+                        "Metric": {{ "metric": {{ "type": "rtt" }}{if_missing} }}
+                    }}
+                }}"#
+            )
+        };
+        assert!(serde_json::from_str::<UtilityConfig>(&rtt("")).is_err());
+        let expression: UtilityConfig =
+            serde_json::from_str(&rtt(r#", "if_missing": 10.0"#)).unwrap();
+        let flow = |rtt: Option<Float>| FlowProperties {
+            throughput: bits_per_second(0.),
+            goodput: bits_per_second(0.),
+            rtt: rtt.map(seconds).ok_or(NoPacketsAcked),
+            delay: Err(NoPacketsAcked),
+            fct: Err(NoTransfersCompleted),
+            loss_rate: Err(NoPacketsSent),
+        };
+        assert_eq!(expression.utility(&[flow(Some(2.)), flow(None)]), Ok(6.));
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn expression_operators() {
+        let properties = FlowProperties {
+            throughput: bits_per_second(8.),
+            goodput: bits_per_second(4.),
+            rtt: Err(NoPacketsAcked),
+            delay: Err(NoPacketsAcked),
+            fct: Err(NoTransfersCompleted),
+            loss_rate: Ok(0.25),
+        };
+        let metric = |metric, if_missing| FlowExpression::Metric { metric, if_missing };
+        let throughput = || metric(FlowMetric::Throughput, None);
+        let goodput = || metric(FlowMetric::Goodput, None);
+        let evaluate = |expression: FlowExpression| expression.evaluate(&properties);
+
+        assert_eq!(
+            evaluate(FlowExpression::Product(vec![throughput(), goodput()])),
+            Some(32.)
+        );
+        assert_eq!(
+            evaluate(FlowExpression::Min(vec![throughput(), goodput()])),
+            Some(4.)
+        );
+        assert_eq!(
+            evaluate(FlowExpression::Max(vec![throughput(), goodput()])),
+            Some(8.)
+        );
+        assert_eq!(
+            evaluate(FlowExpression::Clamp {
+                of: Box::new(throughput()),
+                min: 0.,
+                max: 5.
+            }),
+            Some(5.)
+        );
+        assert_eq!(
+            evaluate(FlowExpression::Power {
+                of: Box::new(goodput()),
+                exponent: 0.5
+            }),
+            Some(2.)
+        );
+        assert_eq!(evaluate(metric(FlowMetric::LossRate, None)), Some(0.25));
+        assert_eq!(evaluate(metric(FlowMetric::Rtt, Some(3.))), Some(3.));
+        assert_eq!(
+            evaluate(FlowExpression::Max(vec![
+                throughput(),
+                metric(FlowMetric::Rtt, None)
+            ])),
+            None
+        );
+    }
 }
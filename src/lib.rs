@@ -22,7 +22,7 @@ use rand_distr::Distribution;
 use serde::{de::DeserializeOwned, Serialize};
 
 use flow::UtilityFunction;
-use quantities::{Float, Time};
+use quantities::{Float, Information, Time};
 use simulation::SimulatorBuilder;
 use util::{logging::Logger, meters::FlowMeter, rand::Rng, OfLifetime};
 
@@ -109,11 +109,14 @@ impl<D: Dna> Config<Custom> for D {
     }
 }
 
-pub trait ProgressHandler<P>: Send {
+pub trait ProgressHandler<P, C = ()>: Send {
     fn update_progress(&mut self, frac_complete: Float, current: &P);
+
+    /// Called whenever training reaches a point that it can be resumed from.
+    fn save_checkpoint(&mut self, _checkpoint: &C) {}
 }
 
-impl<P, F: FnMut(Float, &P) + Send> ProgressHandler<P> for F {
+impl<P, C, F: FnMut(Float, &P) + Send> ProgressHandler<P, C> for F {
     fn update_progress(&mut self, frac_complete: Float, current: &P) {
         self(frac_complete, current);
     }
@@ -123,14 +126,18 @@ pub trait Network<G>: Clone + Send
 where
     G: OfLifetime,
 {
-    fn populate_sim<'sim, 'a, C, F>(
+    /// Adds the network's components to the simulation. `new_cca` and `new_flow_meter` are both
+    /// called once per sender, in the same order, so different senders can be given different
+    /// CCAs. Each sender uses the returned factory to create a new CCA whenever it is enabled.
+    fn populate_sim<'sim, 'a, C, N, F>(
         &self,
         builder: &SimulatorBuilder<'sim, 'a, G::Of<'sim>>,
-        new_cca: impl Fn() -> C + Clone + 'a,
+        new_cca: impl FnMut() -> N,
         rng: &'a mut Rng,
         new_flow_meter: impl FnMut() -> F,
     ) where
         C: Cca + 'a,
+        N: Fn() -> C + 'a,
         F: FlowMeter + 'a,
         'sim: 'a;
 }
@@ -142,6 +149,8 @@ where
     type Network: Network<G>;
 }
 
+/// Congestion windows are counted in segments of the sender's segment size, so a window of `n`
+/// allows `n` full-sized data packets to be in flight.
 pub trait Cca: Debug {
     #[must_use]
     fn initial_cwnd(&self, time: Time) -> u32;
@@ -152,6 +161,17 @@ pub trait Cca: Debug {
     fn packet_sent(&mut self, packet: PacketSent, rng: &mut Rng, logger: &mut impl Logger) -> u32;
     #[must_use]
     fn ack_received(&mut self, ack: AckReceived, rng: &mut Rng, logger: &mut impl Logger) -> u32;
+    /// Called when the sender declares packets lost, if it has loss recovery enabled. Returns the
+    /// new congestion window, or `None` to leave it unchanged.
+    #[must_use]
+    fn loss_detected(
+        &mut self,
+        _loss: LossDetected,
+        _rng: &mut Rng,
+        _logger: &mut impl Logger,
+    ) -> Option<u32> {
+        None
+    }
 }
 
 pub trait CcaTemplate<'a>: Default + Debug {
@@ -161,23 +181,55 @@ pub trait CcaTemplate<'a>: Default + Debug {
 }
 
 pub struct AckReceived {
+    /// Every packet up to and including `seq` has been received, or, if packets are reflected
+    /// straight back to the sender, the sequence number of the reflected packet.
+    pub seq: u64,
     pub sent_time: Time,
     pub received_time: Time,
+    /// Number of data packets that this ACK shows to have been delivered for the first time.
+    pub delivered: u64,
+    /// Total size of the packets counted by `delivered`.
+    pub delivered_bytes: Information,
+    /// Number of data packets acknowledged by this ACK that were marked with congestion
+    /// experienced on their way to the receiver.
+    pub marked: u64,
 }
 
 pub struct PacketSent {
     pub sent_time: Time,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LossCause {
+    /// Enough later packets were acknowledged that the packet is assumed lost.
+    DuplicateAcks,
+    /// The retransmission timer expired, and every outstanding packet is assumed lost.
+    Timeout,
+}
+
+pub struct LossDetected {
+    /// Lowest sequence number that was declared lost.
+    pub seq: u64,
+    pub sent_time: Time,
+    pub detected_time: Time,
+    pub cause: LossCause,
+}
+
 pub trait Trainer {
     type Dna: Dna;
+    /// Everything needed to continue training exactly as it would have gone on without stopping,
+    /// including the state of the training RNG.
+    type Checkpoint: Serialize + DeserializeOwned;
     type CcaTemplate<'a>: CcaTemplate<'a, Policy = &'a Self::Dna>;
 
+    /// Trains from scratch, or continues from `checkpoint`, in which case `rng` is replaced by the
+    /// RNG saved in the checkpoint.
     fn train<G>(
         &self,
         network_config: &impl NetworkDistribution<G>,
         utility_function: &impl UtilityFunction,
-        progress_handler: &mut impl ProgressHandler<Self::Dna>,
+        progress_handler: &mut impl ProgressHandler<Self::Dna, Self::Checkpoint>,
+        checkpoint: Option<Self::Checkpoint>,
         rng: &mut Rng,
     ) -> Self::Dna
     where
@@ -0,0 +1,196 @@
+use rand_distr::Distribution;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        link::{Capacity, Link},
+        mahimahi::MahimahiTrace,
+        queue::QueueConfig,
+        router::Router,
+        workload::{IdealPath, Workload},
+    },
+    quantities::{
+        bits_per_second, milliseconds, seconds, Float, Information, InformationRate, TimeSpan,
+    },
+    simulation::SimulatorBuilder,
+    util::{
+        logging::NothingLogger,
+        meters::FlowMeter,
+        rand::{
+            ContinuousDistribution, DiscreteDistribution, PositiveContinuousDistribution,
+            ProbabilityDistribution, Rng,
+        },
+        OfLifetime,
+    },
+    Cca, Network, NetworkDistribution,
+};
+
+use super::{add_sender, default_segment_size, propagation_link, remy::HasRemyNetworkVariants};
+
+/// A single shared bottleneck between two routers, with each sender attached by its own access
+/// link.
+///
+/// Unlike `RemyNetwork`, each sender has its own base RTT, which is split evenly between its
+/// access link and its return path.
+#[derive(Debug, Clone, Serialize)]
+pub struct DumbbellNetwork {
+    pub rtts: Vec<TimeSpan>,
+    pub packet_rate: InformationRate,
+    pub loss_rate: Float,
+    pub buffer_size: Option<Information>,
+    pub off_time: PositiveContinuousDistribution<TimeSpan>,
+    pub on_time: PositiveContinuousDistribution<TimeSpan>,
+    pub loss_recovery: bool,
+    pub ecn: bool,
+    /// Sampled independently for each sender.
+    pub segment_size: DiscreteDistribution<Information>,
+    pub queue: QueueConfig,
+    /// Replaces `packet_rate` for the bottleneck link.
+    pub trace: Option<MahimahiTrace>,
+}
+
+impl<G> Network<G> for DumbbellNetwork
+where
+    G: OfLifetime,
+    for<'sim> G::Of<'sim>: HasRemyNetworkVariants<'sim, G::Of<'sim>>,
+{
+    fn populate_sim<'sim, 'a, C, N, F>(
+        &self,
+        builder: &SimulatorBuilder<'sim, 'a, <G>::Of<'sim>>,
+        mut new_cca: impl FnMut() -> N,
+        rng: &'a mut Rng,
+        mut new_flow_meter: impl FnMut() -> F,
+    ) where
+        C: Cca + 'a,
+        N: Fn() -> C + 'a,
+        F: FlowMeter + 'a,
+        'sim: 'a,
+    {
+        let capacity = self
+            .trace
+            .clone()
+            .map_or(Capacity::Rate(self.packet_rate), Capacity::Trace);
+        let bottleneck = builder.insert(Link::with_queue(
+            TimeSpan::ZERO,
+            capacity.clone(),
+            self.loss_rate,
+            self.queue.build(self.buffer_size, capacity.average_rate()),
+            rng.create_child(),
+            NothingLogger,
+        ));
+        let left_router = builder.insert(Router::new(NothingLogger));
+        let right_router = builder.insert(Router::new(NothingLogger));
+        for &rtt in &self.rtts {
+            let access = builder.insert(propagation_link(rtt / 2., rng.create_child()));
+            let back = builder.insert(propagation_link(rtt / 2., rng.create_child()));
+            add_sender(
+                builder,
+                vec![
+                    access,
+                    left_router.clone(),
+                    bottleneck.clone(),
+                    right_router.clone(),
+                    back,
+                ],
+                None,
+                self.loss_recovery,
+                self.ecn,
+                &self.segment_size,
+                &Workload::OnOff,
+                IdealPath {
+                    rtt,
+                    rate: capacity.average_rate(),
+                },
+                new_cca(),
+                new_flow_meter(),
+                &self.on_time,
+                &self.off_time,
+                rng,
+            );
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DumbbellNetworkDistribution {
+    /// Sampled independently for each sender.
+    pub rtt: PositiveContinuousDistribution<TimeSpan>,
+    pub bandwidth: PositiveContinuousDistribution<InformationRate>,
+    pub loss_rate: ProbabilityDistribution,
+    pub buffer_size: Option<DiscreteDistribution<Information>>,
+    pub num_senders: DiscreteDistribution<u32>,
+    pub off_time: PositiveContinuousDistribution<TimeSpan>,
+    pub on_time: PositiveContinuousDistribution<TimeSpan>,
+    /// Detect and retransmit lost packets.
+    #[serde(default)]
+    pub loss_recovery: bool,
+    /// Send data packets as ECN-capable, so that links may mark them instead of dropping them.
+    #[serde(default)]
+    pub ecn: bool,
+    /// Size of the data packets sent by each sender, sampled independently for each sender.
+    #[serde(default = "default_segment_size")]
+    pub segment_size: DiscreteDistribution<Information>,
+    /// Queue discipline of the bottleneck links.
+    #[serde(default)]
+    pub queue: QueueConfig,
+    /// Mahimahi trace giving the delivery opportunities of the bottleneck link, in which case
+    /// `bandwidth` is ignored.
+    #[serde(default)]
+    pub trace: Option<MahimahiTrace>,
+}
+
+impl Default for DumbbellNetworkDistribution {
+    fn default() -> DumbbellNetworkDistribution {
+        DumbbellNetworkDistribution {
+            rtt: PositiveContinuousDistribution(ContinuousDistribution::Uniform {
+                min: milliseconds(50.),
+                max: milliseconds(300.),
+            }),
+            bandwidth: PositiveContinuousDistribution(ContinuousDistribution::Uniform {
+                min: bits_per_second(10_000_000.),
+                max: bits_per_second(20_000_000.),
+            }),
+            loss_rate: ProbabilityDistribution(ContinuousDistribution::Always { value: 0. }),
+            buffer_size: None,
+            num_senders: DiscreteDistribution::Uniform { min: 2, max: 16 },
+            off_time: PositiveContinuousDistribution(ContinuousDistribution::Exponential {
+                mean: seconds(5.),
+            }),
+            on_time: PositiveContinuousDistribution(ContinuousDistribution::Exponential {
+                mean: seconds(5.),
+            }),
+            loss_recovery: false,
+            ecn: false,
+            segment_size: default_segment_size(),
+            queue: QueueConfig::default(),
+            trace: None,
+        }
+    }
+}
+
+impl Distribution<DumbbellNetwork> for DumbbellNetworkDistribution {
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> DumbbellNetwork {
+        let num_senders = rng.sample(&self.num_senders);
+        DumbbellNetwork {
+            rtts: (0..num_senders).map(|_| rng.sample(&self.rtt)).collect(),
+            packet_rate: rng.sample(&self.bandwidth),
+            loss_rate: rng.sample(&self.loss_rate),
+            buffer_size: self.buffer_size.as_ref().map(|d| rng.sample(d)),
+            off_time: self.off_time.clone(),
+            on_time: self.on_time.clone(),
+            loss_recovery: self.loss_recovery,
+            ecn: self.ecn,
+            segment_size: self.segment_size.clone(),
+            queue: self.queue.clone(),
+            trace: self.trace.clone(),
+        }
+    }
+}
+
+impl<G> NetworkDistribution<G> for DumbbellNetworkDistribution
+where
+    G: OfLifetime,
+    for<'sim> G::Of<'sim>: HasRemyNetworkVariants<'sim, G::Of<'sim>>,
+{
+    type Network = DumbbellNetwork;
+}
@@ -2,22 +2,138 @@ use rand_distr::Distribution;
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    components::{
+        link::Link,
+        packet::PacketAddress,
+        senders::lossy::LossySender,
+        toggler::Toggler,
+        workload::{FlowGenerator, IdealPath, Workload},
+    },
+    quantities::{bits_per_second, packets, Float, Information, TimeSpan},
     simulation::SimulatorBuilder,
-    util::{meters::FlowMeter, rand::Rng, OfLifetime},
+    util::{
+        logging::NothingLogger,
+        meters::FlowMeter,
+        rand::{DiscreteDistribution, PositiveContinuousDistribution, Rng},
+        OfLifetime,
+    },
     Cca, Network, NetworkDistribution,
 };
 
-use self::remy::{HasRemyNetworkVariants, RemyNetwork, RemyNetworkDistribution};
+use self::{
+    dumbbell::{DumbbellNetwork, DumbbellNetworkDistribution},
+    parking_lot::{ParkingLotNetwork, ParkingLotNetworkDistribution},
+    remy::{HasRemyNetworkVariants, RemyNetwork, RemyNetworkDistribution},
+};
 
+pub mod dumbbell;
+pub mod parking_lot;
 pub mod remy;
 
 pub trait HasDefaultNetworkVariants<'sim, E>: HasRemyNetworkVariants<'sim, E> {}
 
 impl<'sim, E, T> HasDefaultNetworkVariants<'sim, E> for T where T: HasRemyNetworkVariants<'sim, E> {}
 
+/// Adds a sender whose packets travel along `route` to `destination`, or back to the sender
+/// itself if there is no separate receiver. The sender is driven by a `Toggler` or a
+/// `FlowGenerator`, depending on the `workload`. `ideal_path` is used to calculate the slowdown
+/// of finite transfers.
+fn add_sender<'sim, 'a, E, C, F>(
+    builder: &SimulatorBuilder<'sim, 'a, E>,
+    route: Vec<PacketAddress<'sim, E>>,
+    destination: Option<PacketAddress<'sim, E>>,
+    loss_recovery: bool,
+    ecn: bool,
+    segment_size: &DiscreteDistribution<Information>,
+    workload: &Workload,
+    ideal_path: IdealPath,
+    new_cca: impl Fn() -> C + 'a,
+    flow_meter: F,
+    on_time: &PositiveContinuousDistribution<TimeSpan>,
+    off_time: &PositiveContinuousDistribution<TimeSpan>,
+    rng: &mut Rng,
+) where
+    E: HasRemyNetworkVariants<'sim, E>,
+    C: Cca + 'a,
+    F: FlowMeter + 'a,
+    'sim: 'a,
+{
+    let slot = builder.reserve_slot();
+    let address = slot.address();
+    let packet_address = address.clone().cast();
+    let segment_size = rng.sample(segment_size);
+    slot.fill(LossySender::new(
+        packet_address.clone(),
+        route,
+        destination.unwrap_or(packet_address),
+        flow_meter,
+        new_cca,
+        true,
+        loss_recovery,
+        ecn,
+        segment_size,
+        rng.create_child(),
+        NothingLogger,
+    ));
+    match workload {
+        Workload::OnOff => {
+            builder.insert(Toggler::new(
+                address.cast(),
+                on_time.clone(),
+                off_time.clone(),
+                rng.create_child(),
+            ));
+        }
+        Workload::FiniteFlows { flow_size } => {
+            let slot = builder.reserve_slot();
+            let generator_address = slot.address();
+            slot.fill(FlowGenerator::closed_loop(
+                address.cast(),
+                generator_address,
+                flow_size.clone(),
+                off_time.clone(),
+                ideal_path,
+                segment_size,
+                rng.create_child(),
+            ));
+        }
+        Workload::PoissonArrivals {
+            flow_size,
+            mean_interarrival,
+        } => {
+            builder.insert(FlowGenerator::poisson(
+                address.cast(),
+                flow_size.clone(),
+                *mean_interarrival,
+                ideal_path,
+                segment_size,
+                rng.create_child(),
+            ));
+        }
+    }
+}
+
+const fn default_segment_size() -> DiscreteDistribution<Information> {
+    DiscreteDistribution::Always { value: packets(1) }
+}
+
+/// A link that only adds propagation delay, and never queues or drops packets.
+fn propagation_link<'sim, E>(delay: TimeSpan, rng: Rng) -> Link<'sim, E, NothingLogger> {
+    Link::create(
+        delay,
+        bits_per_second(Float::INFINITY),
+        0.,
+        None,
+        rng,
+        NothingLogger,
+    )
+}
+
 #[derive(Serialize, Deserialize)]
 pub enum DefaultNetworkConfig {
     Remy(RemyNetworkDistribution),
+    Dumbbell(DumbbellNetworkDistribution),
+    ParkingLot(ParkingLotNetworkDistribution),
 }
 
 impl Default for DefaultNetworkConfig {
@@ -29,12 +145,18 @@ impl Default for DefaultNetworkConfig {
 #[derive(Clone, Serialize)]
 pub enum DefaultNetworkBuilder {
     Remy(RemyNetwork),
+    Dumbbell(DumbbellNetwork),
+    ParkingLot(ParkingLotNetwork),
 }
 
 impl Distribution<DefaultNetworkBuilder> for DefaultNetworkConfig {
     fn sample<R: rand::prelude::Rng + ?Sized>(&self, rng: &mut R) -> DefaultNetworkBuilder {
         match self {
             DefaultNetworkConfig::Remy(cfg) => DefaultNetworkBuilder::Remy(rng.sample(cfg)),
+            DefaultNetworkConfig::Dumbbell(cfg) => DefaultNetworkBuilder::Dumbbell(rng.sample(cfg)),
+            DefaultNetworkConfig::ParkingLot(cfg) => {
+                DefaultNetworkBuilder::ParkingLot(rng.sample(cfg))
+            }
         }
     }
 }
@@ -52,15 +174,15 @@ where
     G: OfLifetime,
     for<'sim> G::Of<'sim>: HasDefaultNetworkVariants<'sim, G::Of<'sim>>,
 {
-    fn populate_sim<'sim, 'a, C, F>(
+    fn populate_sim<'sim, 'a, C, N, F>(
         &self,
         builder: &SimulatorBuilder<'sim, 'a, <G as OfLifetime>::Of<'sim>>,
-        new_cca: impl Fn() -> C + Clone + 'a,
+        new_cca: impl FnMut() -> N,
         rng: &'a mut Rng,
         new_flow_meter: impl FnMut() -> F,
     ) where
         C: Cca + 'a,
-
+        N: Fn() -> C + 'a,
         F: FlowMeter + 'a,
         'sim: 'a,
     {
@@ -68,6 +190,24 @@ where
             DefaultNetworkBuilder::Remy(n) => {
                 <RemyNetwork as Network<G>>::populate_sim(n, builder, new_cca, rng, new_flow_meter);
             }
+            DefaultNetworkBuilder::Dumbbell(n) => {
+                <DumbbellNetwork as Network<G>>::populate_sim(
+                    n,
+                    builder,
+                    new_cca,
+                    rng,
+                    new_flow_meter,
+                );
+            }
+            DefaultNetworkBuilder::ParkingLot(n) => {
+                <ParkingLotNetwork as Network<G>>::populate_sim(
+                    n,
+                    builder,
+                    new_cca,
+                    rng,
+                    new_flow_meter,
+                );
+            }
         }
     }
 }
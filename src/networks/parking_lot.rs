@@ -0,0 +1,248 @@
+use rand_distr::Distribution;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        link::Link,
+        packet::PacketAddress,
+        queue::QueueConfig,
+        router::Router,
+        workload::{IdealPath, Workload},
+    },
+    quantities::{
+        bits_per_second, milliseconds, seconds, Float, Information, InformationRate, TimeSpan,
+    },
+    simulation::SimulatorBuilder,
+    util::{
+        logging::NothingLogger,
+        meters::FlowMeter,
+        rand::{
+            ContinuousDistribution, DiscreteDistribution, PositiveContinuousDistribution,
+            ProbabilityDistribution, Rng,
+        },
+        OfLifetime,
+    },
+    Cca, Network, NetworkDistribution,
+};
+
+use super::{add_sender, default_segment_size, propagation_link, remy::HasRemyNetworkVariants};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ParkingLotHop {
+    pub delay: TimeSpan,
+    pub packet_rate: InformationRate,
+}
+
+/// A chain of bottleneck links joined by routers.
+///
+/// Long flows traverse every hop, while each hop also carries its own cross traffic which
+/// only traverses that hop. Every path returns to its sender with the same propagation delay as
+/// the forward direction.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParkingLotNetwork {
+    pub hops: Vec<ParkingLotHop>,
+    pub loss_rate: Float,
+    pub buffer_size: Option<Information>,
+    pub num_long_senders: u32,
+    pub num_cross_senders: u32,
+    pub off_time: PositiveContinuousDistribution<TimeSpan>,
+    pub on_time: PositiveContinuousDistribution<TimeSpan>,
+    pub loss_recovery: bool,
+    pub ecn: bool,
+    /// Sampled independently for each sender.
+    pub segment_size: DiscreteDistribution<Information>,
+    pub queue: QueueConfig,
+}
+
+impl<G> Network<G> for ParkingLotNetwork
+where
+    G: OfLifetime,
+    for<'sim> G::Of<'sim>: HasRemyNetworkVariants<'sim, G::Of<'sim>>,
+{
+    fn populate_sim<'sim, 'a, C, N, F>(
+        &self,
+        builder: &SimulatorBuilder<'sim, 'a, <G>::Of<'sim>>,
+        mut new_cca: impl FnMut() -> N,
+        rng: &'a mut Rng,
+        mut new_flow_meter: impl FnMut() -> F,
+    ) where
+        C: Cca + 'a,
+        N: Fn() -> C + 'a,
+        F: FlowMeter + 'a,
+        'sim: 'a,
+    {
+        // Each hop is entered through a router, and delivers to the router of the next hop.
+        let hops: Vec<(PacketAddress<'sim, _>, PacketAddress<'sim, _>)> = self
+            .hops
+            .iter()
+            .map(|hop| {
+                let router = builder.insert(Router::new(NothingLogger));
+                let link = builder.insert(Link::with_queue(
+                    hop.delay,
+                    hop.packet_rate,
+                    self.loss_rate,
+                    self.queue.build(self.buffer_size, hop.packet_rate),
+                    rng.create_child(),
+                    NothingLogger,
+                ));
+                (router, link)
+            })
+            .collect();
+        let exit_router = builder.insert(Router::new(NothingLogger));
+        let router_after = |i: usize| hops.get(i + 1).map_or(&exit_router, |(r, _)| r).clone();
+
+        let total_delay = self
+            .hops
+            .iter()
+            .fold(TimeSpan::ZERO, |acc, hop| acc + hop.delay);
+        let min_rate = self.hops.iter().map(|hop| hop.packet_rate).fold(
+            bits_per_second(Float::INFINITY),
+            |acc, rate| {
+                if rate < acc {
+                    rate
+                } else {
+                    acc
+                }
+            },
+        );
+        for _ in 0..self.num_long_senders {
+            let mut route: Vec<_> = hops
+                .iter()
+                .flat_map(|(router, link)| [router.clone(), link.clone()])
+                .collect();
+            route.push(exit_router.clone());
+            route.push(builder.insert(propagation_link(total_delay, rng.create_child())));
+            add_sender(
+                builder,
+                route,
+                None,
+                self.loss_recovery,
+                self.ecn,
+                &self.segment_size,
+                &Workload::OnOff,
+                IdealPath {
+                    rtt: 2. * total_delay,
+                    rate: min_rate,
+                },
+                new_cca(),
+                new_flow_meter(),
+                &self.on_time,
+                &self.off_time,
+                rng,
+            );
+        }
+        for (i, ((router, link), hop)) in hops.iter().zip(&self.hops).enumerate() {
+            for _ in 0..self.num_cross_senders {
+                let back = builder.insert(propagation_link(hop.delay, rng.create_child()));
+                add_sender(
+                    builder,
+                    vec![router.clone(), link.clone(), router_after(i), back],
+                    None,
+                    self.loss_recovery,
+                    self.ecn,
+                    &self.segment_size,
+                    &Workload::OnOff,
+                    IdealPath {
+                        rtt: 2. * hop.delay,
+                        rate: hop.packet_rate,
+                    },
+                    new_cca(),
+                    new_flow_meter(),
+                    &self.on_time,
+                    &self.off_time,
+                    rng,
+                );
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ParkingLotNetworkDistribution {
+    pub num_hops: DiscreteDistribution<u32>,
+    /// Sampled independently for each hop.
+    pub hop_delay: PositiveContinuousDistribution<TimeSpan>,
+    /// Sampled independently for each hop.
+    pub bandwidth: PositiveContinuousDistribution<InformationRate>,
+    pub loss_rate: ProbabilityDistribution,
+    pub buffer_size: Option<DiscreteDistribution<Information>>,
+    pub num_long_senders: DiscreteDistribution<u32>,
+    /// Number of senders whose traffic only crosses a single hop, for each hop.
+    pub num_cross_senders: DiscreteDistribution<u32>,
+    pub off_time: PositiveContinuousDistribution<TimeSpan>,
+    pub on_time: PositiveContinuousDistribution<TimeSpan>,
+    /// Detect and retransmit lost packets.
+    #[serde(default)]
+    pub loss_recovery: bool,
+    /// Send data packets as ECN-capable, so that links may mark them instead of dropping them.
+    #[serde(default)]
+    pub ecn: bool,
+    /// Size of the data packets sent by each sender, sampled independently for each sender.
+    #[serde(default = "default_segment_size")]
+    pub segment_size: DiscreteDistribution<Information>,
+    /// Queue discipline of the bottleneck links.
+    #[serde(default)]
+    pub queue: QueueConfig,
+}
+
+impl Default for ParkingLotNetworkDistribution {
+    fn default() -> ParkingLotNetworkDistribution {
+        ParkingLotNetworkDistribution {
+            num_hops: DiscreteDistribution::Always { value: 3 },
+            hop_delay: PositiveContinuousDistribution(ContinuousDistribution::Uniform {
+                min: milliseconds(10.),
+                max: milliseconds(30.),
+            }),
+            bandwidth: PositiveContinuousDistribution(ContinuousDistribution::Uniform {
+                min: bits_per_second(10_000_000.),
+                max: bits_per_second(20_000_000.),
+            }),
+            loss_rate: ProbabilityDistribution(ContinuousDistribution::Always { value: 0. }),
+            buffer_size: None,
+            num_long_senders: DiscreteDistribution::Uniform { min: 1, max: 4 },
+            num_cross_senders: DiscreteDistribution::Uniform { min: 1, max: 4 },
+            off_time: PositiveContinuousDistribution(ContinuousDistribution::Exponential {
+                mean: seconds(5.),
+            }),
+            on_time: PositiveContinuousDistribution(ContinuousDistribution::Exponential {
+                mean: seconds(5.),
+            }),
+            loss_recovery: false,
+            ecn: false,
+            segment_size: default_segment_size(),
+            queue: QueueConfig::default(),
+        }
+    }
+}
+
+impl Distribution<ParkingLotNetwork> for ParkingLotNetworkDistribution {
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> ParkingLotNetwork {
+        let num_hops = rng.sample(&self.num_hops);
+        ParkingLotNetwork {
+            hops: (0..num_hops)
+                .map(|_| ParkingLotHop {
+                    delay: rng.sample(&self.hop_delay),
+                    packet_rate: rng.sample(&self.bandwidth),
+                })
+                .collect(),
+            loss_rate: rng.sample(&self.loss_rate),
+            buffer_size: self.buffer_size.as_ref().map(|d| rng.sample(d)),
+            num_long_senders: rng.sample(&self.num_long_senders),
+            num_cross_senders: rng.sample(&self.num_cross_senders),
+            off_time: self.off_time.clone(),
+            on_time: self.on_time.clone(),
+            loss_recovery: self.loss_recovery,
+            ecn: self.ecn,
+            segment_size: self.segment_size.clone(),
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl<G> NetworkDistribution<G> for ParkingLotNetworkDistribution
+where
+    G: OfLifetime,
+    for<'sim> G::Of<'sim>: HasRemyNetworkVariants<'sim, G::Of<'sim>>,
+{
+    type Network = ParkingLotNetwork;
+}
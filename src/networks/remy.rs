@@ -1,12 +1,16 @@
+use itertools::Itertools;
 use rand_distr::Distribution;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     components::{
-        link::Link,
+        link::{Capacity, Link},
+        mahimahi::MahimahiTrace,
         packet::Packet,
-        senders::lossy::{LossySender, LossySenderEffect},
-        toggler::Toggler,
+        queue::QueueConfig,
+        receiver::Receiver,
+        senders::lossy::LossySenderEffect,
+        workload::{FlowCompleted, IdealPath, Workload},
     },
     quantities::{
         bits_per_second, milliseconds, seconds, Float, Information, InformationRate, TimeSpan,
@@ -25,6 +29,8 @@ use crate::{
     Cca, Network, NetworkDistribution,
 };
 
+use super::{add_sender, default_segment_size};
+
 #[derive(Debug, Clone, Serialize)]
 pub struct RemyNetwork {
     pub rtt: TimeSpan,
@@ -34,16 +40,41 @@ pub struct RemyNetwork {
     pub num_senders: u32,
     pub off_time: PositiveContinuousDistribution<TimeSpan>,
     pub on_time: PositiveContinuousDistribution<TimeSpan>,
+    pub loss_recovery: bool,
+    pub ecn: bool,
+    /// Sampled independently for each sender.
+    pub segment_size: DiscreteDistribution<Information>,
+    pub receiver: Option<RemyReceiver>,
+    pub workload: Workload,
+    pub queue: QueueConfig,
+    /// Replaces `packet_rate` for the bottleneck link.
+    pub trace: Option<MahimahiTrace>,
+}
+
+/// Gives each sender its own receiver, which acknowledges data over a shared reverse link. The
+/// RTT is split evenly between the forward and reverse links.
+#[derive(Debug, Clone, Serialize)]
+pub struct RemyReceiver {
+    pub packet_rate: InformationRate,
+    pub loss_rate: Float,
+    pub buffer_size: Option<Information>,
+    pub ack_every: u32,
+    pub ack_timeout: TimeSpan,
 }
 
 pub trait HasRemyNetworkVariants<'sim, E>:
-    HasVariant<LossySenderEffect<'sim, E>> + HasVariant<Packet<'sim, E>> + HasVariant<Never> + 'sim
+    HasVariant<LossySenderEffect<'sim, E>>
+    + HasVariant<Packet<'sim, E>>
+    + HasVariant<FlowCompleted>
+    + HasVariant<Never>
+    + 'sim
 {
 }
 
 impl<'sim, E, T> HasRemyNetworkVariants<'sim, E> for T where
     T: HasVariant<LossySenderEffect<'sim, E>>
         + HasVariant<Packet<'sim, E>>
+        + HasVariant<FlowCompleted>
         + HasVariant<Never>
         + 'sim
 {
@@ -54,45 +85,77 @@ where
     G: OfLifetime,
     for<'sim> G::Of<'sim>: HasRemyNetworkVariants<'sim, G::Of<'sim>>,
 {
-    fn populate_sim<'sim, 'a, C, F>(
+    fn populate_sim<'sim, 'a, C, N, F>(
         &self,
         builder: &SimulatorBuilder<'sim, 'a, <G>::Of<'sim>>,
-        new_cca: impl Fn() -> C + Clone + 'a,
+        mut new_cca: impl FnMut() -> N,
         rng: &'a mut Rng,
         mut new_flow_meter: impl FnMut() -> F,
     ) where
         C: Cca + 'a,
+        N: Fn() -> C + 'a,
         F: FlowMeter + 'a,
         'sim: 'a,
     {
-        let sender_link_id = builder.insert(Link::create(
-            self.rtt,
-            self.packet_rate,
+        let capacity = self
+            .trace
+            .clone()
+            .map_or(Capacity::Rate(self.packet_rate), Capacity::Trace);
+        let sender_link_id = builder.insert(Link::with_queue(
+            if self.receiver.is_some() {
+                self.rtt / 2.
+            } else {
+                self.rtt
+            },
+            capacity.clone(),
             self.loss_rate,
-            self.buffer_size,
+            self.queue.build(self.buffer_size, capacity.average_rate()),
             rng.create_child(),
             NothingLogger,
         ));
-        for _ in 0..self.num_senders {
-            let slot = builder.reserve_slot();
-            let address = slot.address();
-            let packet_address = address.clone().cast();
-            slot.fill(LossySender::new(
-                packet_address.clone(),
-                sender_link_id.clone(),
-                packet_address,
-                new_flow_meter(),
-                new_cca.clone(),
-                true,
+        let reverse_link_id = self.receiver.as_ref().map(|receiver| {
+            builder.insert(Link::create(
+                self.rtt / 2.,
+                receiver.packet_rate,
+                receiver.loss_rate,
+                receiver.buffer_size,
                 rng.create_child(),
                 NothingLogger,
-            ));
-            builder.insert(Toggler::new(
-                address.cast(),
-                self.on_time.clone(),
-                self.off_time.clone(),
-                rng.create_child(),
-            ));
+            ))
+        });
+        for _ in 0..self.num_senders {
+            let destination = self.receiver.as_ref().zip(reverse_link_id.as_ref()).map(
+                |(receiver, reverse_link_id)| {
+                    let slot = builder.reserve_slot();
+                    let address = slot.address();
+                    slot.fill(Receiver::new(
+                        address.clone(),
+                        vec![reverse_link_id.clone()],
+                        receiver.ack_every,
+                        receiver.ack_timeout,
+                        NothingLogger,
+                    ));
+                    address
+                },
+            );
+            add_sender(
+                builder,
+                vec![sender_link_id.clone()],
+                destination,
+                self.loss_recovery,
+                self.ecn,
+                &self.segment_size,
+                &self.workload,
+                IdealPath {
+                    rtt: self.rtt,
+                    rate: capacity.average_rate(),
+                },
+                new_cca(),
+                new_flow_meter(),
+                &self.on_time,
+                &self.off_time,
+                rng,
+            );
         }
     }
 }
@@ -106,6 +169,65 @@ pub struct RemyNetworkDistribution {
     pub num_senders: DiscreteDistribution<u32>,
     pub off_time: PositiveContinuousDistribution<TimeSpan>,
     pub on_time: PositiveContinuousDistribution<TimeSpan>,
+    /// Detect and retransmit lost packets.
+    #[serde(default)]
+    pub loss_recovery: bool,
+    /// Send data packets as ECN-capable, so that links may mark them instead of dropping them.
+    #[serde(default)]
+    pub ecn: bool,
+    /// Size of the data packets sent by each sender, sampled independently for each sender.
+    #[serde(default = "default_segment_size")]
+    pub segment_size: DiscreteDistribution<Information>,
+    /// Queue discipline of the bottleneck links.
+    #[serde(default)]
+    pub queue: QueueConfig,
+    /// Mahimahi trace giving the delivery opportunities of the bottleneck link, in which case
+    /// `bandwidth` is ignored.
+    #[serde(default)]
+    pub trace: Option<MahimahiTrace>,
+    #[serde(default)]
+    pub receiver: Option<RemyReceiverDistribution>,
+    /// Whether senders send continuously while switched on by `on_time` and `off_time`, or
+    /// transfer finite flows.
+    #[serde(default)]
+    pub workload: Workload,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RemyReceiverDistribution {
+    pub bandwidth: PositiveContinuousDistribution<InformationRate>,
+    pub loss_rate: ProbabilityDistribution,
+    pub buffer_size: Option<DiscreteDistribution<Information>>,
+    /// Send an ACK for every `ack_every` data packets, or after `ack_timeout` if fewer arrive.
+    pub ack_every: DiscreteDistribution<u32>,
+    pub ack_timeout: TimeSpan,
+}
+
+impl Default for RemyReceiverDistribution {
+    fn default() -> RemyReceiverDistribution {
+        RemyReceiverDistribution {
+            bandwidth: PositiveContinuousDistribution(ContinuousDistribution::Uniform {
+                min: bits_per_second(10_000_000.),
+                max: bits_per_second(20_000_000.),
+            }),
+            loss_rate: ProbabilityDistribution(ContinuousDistribution::Always { value: 0. }),
+            buffer_size: None,
+            ack_every: DiscreteDistribution::Always { value: 2 },
+            ack_timeout: milliseconds(40.),
+        }
+    }
+}
+
+impl Distribution<RemyReceiver> for RemyReceiverDistribution {
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> RemyReceiver {
+        RemyReceiver {
+            packet_rate: rng.sample(&self.bandwidth),
+            loss_rate: rng.sample(&self.loss_rate),
+            buffer_size: self.buffer_size.as_ref().map(|d| rng.sample(d)),
+            ack_every: rng.sample(&self.ack_every),
+            ack_timeout: self.ack_timeout,
+        }
+    }
 }
 
 impl Default for RemyNetworkDistribution {
@@ -128,6 +250,13 @@ impl Default for RemyNetworkDistribution {
             on_time: PositiveContinuousDistribution(ContinuousDistribution::Exponential {
                 mean: seconds(5.),
             }),
+            loss_recovery: false,
+            ecn: false,
+            segment_size: default_segment_size(),
+            queue: QueueConfig::default(),
+            trace: None,
+            receiver: None,
+            workload: Workload::default(),
         }
     }
 }
@@ -142,6 +271,13 @@ impl Distribution<RemyNetwork> for RemyNetworkDistribution {
             num_senders: rng.sample(&self.num_senders),
             off_time: self.off_time.clone(),
             on_time: self.on_time.clone(),
+            loss_recovery: self.loss_recovery,
+            ecn: self.ecn,
+            segment_size: self.segment_size.clone(),
+            queue: self.queue.clone(),
+            trace: self.trace.clone(),
+            receiver: self.receiver.as_ref().map(|d| rng.sample(d)),
+            workload: self.workload.clone(),
         }
     }
 }
@@ -153,3 +289,102 @@ where
 {
     type Network = RemyNetwork;
 }
+
+/// A field of `RemyNetwork` that a sweep holds at each of a list of values in turn.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "parameter", rename_all = "snake_case")]
+pub enum RemySweepDimension {
+    Rtt { values: Vec<TimeSpan> },
+    PacketRate { values: Vec<InformationRate> },
+    LossRate { values: Vec<Float> },
+    BufferSize { values: Vec<Information> },
+    NumSenders { values: Vec<u32> },
+}
+
+impl RemySweepDimension {
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            RemySweepDimension::Rtt { .. } => "rtt",
+            RemySweepDimension::PacketRate { .. } => "packet_rate",
+            RemySweepDimension::LossRate { .. } => "loss_rate",
+            RemySweepDimension::BufferSize { .. } => "buffer_size",
+            RemySweepDimension::NumSenders { .. } => "num_senders",
+        }
+    }
+
+    #[must_use]
+    pub const fn num_values(&self) -> usize {
+        match self {
+            RemySweepDimension::Rtt { values } => values.len(),
+            RemySweepDimension::PacketRate { values } => values.len(),
+            RemySweepDimension::LossRate { values } => values.len(),
+            RemySweepDimension::BufferSize { values } => values.len(),
+            RemySweepDimension::NumSenders { values } => values.len(),
+        }
+    }
+
+    /// Returns the value with the given index, in seconds, bits per second or bytes, or as a
+    /// plain number.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn value(&self, index: usize) -> Float {
+        match self {
+            RemySweepDimension::Rtt { values } => values[index].seconds(),
+            RemySweepDimension::PacketRate { values } => values[index].bits_per_second(),
+            RemySweepDimension::LossRate { values } => values[index],
+            RemySweepDimension::BufferSize { values } => values[index].bytes() as Float,
+            RemySweepDimension::NumSenders { values } => Float::from(values[index]),
+        }
+    }
+
+    /// Sets the field of `network` to the value with the given index.
+    pub fn apply(&self, index: usize, network: &mut RemyNetwork) {
+        match self {
+            RemySweepDimension::Rtt { values } => network.rtt = values[index],
+            RemySweepDimension::PacketRate { values } => network.packet_rate = values[index],
+            RemySweepDimension::LossRate { values } => network.loss_rate = values[index],
+            RemySweepDimension::BufferSize { values } => {
+                network.buffer_size = Some(values[index]);
+            }
+            RemySweepDimension::NumSenders { values } => network.num_senders = values[index],
+        }
+    }
+}
+
+/// A grid over one or more `RemySweepDimension`s. The other fields of each network are sampled
+/// from a `RemyNetworkDistribution`, once for the whole grid.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RemyNetworkSweep {
+    pub dimensions: Vec<RemySweepDimension>,
+}
+
+impl RemyNetworkSweep {
+    /// Returns the points of the grid, as the index of the value of each dimension, with the last
+    /// dimension varying fastest.
+    #[must_use]
+    pub fn points(&self) -> Vec<Vec<usize>> {
+        self.dimensions
+            .iter()
+            .map(|dimension| 0..dimension.num_values())
+            .multi_cartesian_product()
+            .collect()
+    }
+
+    /// Returns the value of each dimension at the given point.
+    #[must_use]
+    pub fn values(&self, point: &[usize]) -> Vec<Float> {
+        self.dimensions
+            .iter()
+            .zip(point)
+            .map(|(dimension, &index)| dimension.value(index))
+            .collect()
+    }
+
+    /// Sets the swept fields of `network` to their values at the given point.
+    pub fn apply(&self, point: &[usize], network: &mut RemyNetwork) {
+        for (dimension, &index) in self.dimensions.iter().zip(point) {
+            dimension.apply(index, network);
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     fmt::Display,
-    ops::{Add, Sub},
+    ops::{Add, Mul, Sub},
 };
 
 use serde::{Deserialize, Serialize};
@@ -52,6 +52,14 @@ impl Sub<Information> for Information {
     }
 }
 
+impl Mul<Information> for u64 {
+    type Output = Information;
+
+    fn mul(self, rhs: Information) -> Self::Output {
+        Information(self * rhs.0)
+    }
+}
+
 impl Wrapper for Information {
     type Underlying = u64;
 
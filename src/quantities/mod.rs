@@ -166,8 +166,10 @@ impl UnitPrefix<Float> for Milli {
         value / 1000.
     }
 
+    /// Only used if converting back gives exactly the same value, so that serializing is lossless.
     fn quantity_to_parseable(&self, value: Float) -> Result<Float, NoMatch> {
-        if value < 1. {
+        #[allow(clippy::float_cmp)]
+        if value < 1. && self.parsed_to_underlying(value * 1000.) == value {
             Ok(value * 1000.)
         } else {
             Err(NoMatch)
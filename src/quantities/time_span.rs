@@ -46,6 +46,11 @@ impl TimeSpan {
     pub fn is_negative(&self) -> bool {
         self.0 < 0.
     }
+
+    #[must_use]
+    pub const fn abs(self) -> TimeSpan {
+        TimeSpan(self.0.abs())
+    }
 }
 
 impl Wrapper for TimeSpan {
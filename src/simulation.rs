@@ -7,7 +7,6 @@ use std::{
     fmt::{self, Debug, Formatter},
     rc::Rc,
 };
-use vec_map::VecMap;
 
 use crate::{quantities::Time, util::logging::Logger};
 
@@ -15,6 +14,11 @@ pub trait HasVariant<P>: From<P> + TryInto<P> {}
 
 impl<E, P> HasVariant<P> for E where E: From<P> + TryInto<P> {}
 
+/// Names the kind of each message, so that simulations can be recorded and compared.
+pub trait EffectKind {
+    fn kind(&self) -> &'static str;
+}
+
 #[derive(Clone)]
 pub struct Clock(Rc<RefCell<Time>>);
 
@@ -46,6 +50,12 @@ impl<'sim> ComponentId<'sim> {
     const fn new(index: usize, sim_id: Id<'sim>) -> ComponentId {
         ComponentId { index, sim_id }
     }
+
+    /// Position of the component in the order it was added to the simulation.
+    #[must_use]
+    pub const fn index(&self) -> usize {
+        self.index
+    }
 }
 
 #[derive_where(Clone)]
@@ -95,6 +105,13 @@ impl<'sim, I, E> Address<'sim, I, E> {
     pub fn create_message(&self, effect: I) -> Message<'sim, E> {
         (self.create_message)(effect)
     }
+
+    /// Returns whether both addresses were cloned from the same address. Addresses created by
+    /// casting are always considered different.
+    #[must_use]
+    pub fn same_as(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.create_message, &other.create_message)
+    }
 }
 
 pub struct Message<'sim, E> {
@@ -160,11 +177,15 @@ where
     }
 }
 
+/// Indexed binary min-heap of pending component ticks.
+///
+/// Each component index appears at most once. Ties between equal times are broken by
+/// component index, so the order in which components tick is deterministic.
 #[derive(Debug)]
-/// TODO: Heapify?
 pub struct TickQueue {
     current_time: Time,
-    waiting: VecMap<Time>,
+    heap: Vec<(Time, usize)>,
+    positions: Vec<Option<usize>>,
 }
 
 impl TickQueue {
@@ -172,38 +193,103 @@ impl TickQueue {
     pub fn with_capacity(capacity: usize) -> TickQueue {
         TickQueue {
             current_time: Time::MIN,
-            waiting: VecMap::with_capacity(capacity),
+            heap: Vec::with_capacity(capacity),
+            positions: Vec::with_capacity(capacity),
         }
     }
 
     pub fn update(&mut self, id: usize, time: Option<Time>) {
         if let Some(time) = time {
             assert!(time >= self.current_time);
-            self.waiting.insert(id, time);
+            self.insert(id, time);
         } else {
-            self.waiting.remove(id);
+            self.remove(id);
         }
     }
 
     #[must_use]
     pub fn next_time(&self) -> Option<Time> {
-        self.waiting.values().min().copied()
+        self.heap.first().map(|&(time, _)| time)
     }
 
     pub fn pop_next(&mut self) -> (Time, Option<usize>) {
-        if let Some((idx, time)) = self
-            .waiting
-            .iter()
-            .min_by_key(|&(_, time)| time)
-            .map(|(x, t)| (x, *t))
-        {
+        if let Some(&(time, idx)) = self.heap.first() {
             self.current_time = time;
-            self.waiting.remove(idx);
+            self.remove(idx);
             (time, Some(idx))
         } else {
             (Time::MAX, None)
         }
     }
+
+    fn insert(&mut self, id: usize, time: Time) {
+        if id >= self.positions.len() {
+            self.positions.resize(id + 1, None);
+        }
+        if let Some(pos) = self.positions[id] {
+            let old = self.heap[pos].0;
+            self.heap[pos].0 = time;
+            if time < old {
+                self.sift_up(pos);
+            } else {
+                self.sift_down(pos);
+            }
+        } else {
+            self.heap.push((time, id));
+            self.positions[id] = Some(self.heap.len() - 1);
+            self.sift_up(self.heap.len() - 1);
+        }
+    }
+
+    fn remove(&mut self, id: usize) {
+        let Some(pos) = self.positions.get(id).copied().flatten() else {
+            return;
+        };
+        let last = self.heap.len() - 1;
+        self.swap(pos, last);
+        self.heap.pop();
+        self.positions[id] = None;
+        if pos < self.heap.len() {
+            self.sift_up(pos);
+            self.sift_down(pos);
+        }
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        self.positions[self.heap[a].1] = Some(a);
+        self.positions[self.heap[b].1] = Some(b);
+    }
+
+    fn sift_up(&mut self, mut pos: usize) {
+        while pos > 0 {
+            let parent = (pos - 1) / 2;
+            if self.heap[pos] >= self.heap[parent] {
+                break;
+            }
+            self.swap(pos, parent);
+            pos = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut pos: usize) {
+        loop {
+            let left = 2 * pos + 1;
+            let right = left + 1;
+            let mut smallest = pos;
+            if left < self.heap.len() && self.heap[left] < self.heap[smallest] {
+                smallest = left;
+            }
+            if right < self.heap.len() && self.heap[right] < self.heap[smallest] {
+                smallest = right;
+            }
+            if smallest == pos {
+                break;
+            }
+            self.swap(pos, smallest);
+            pos = smallest;
+        }
+    }
 }
 
 struct EffectQueue<'sim, E> {
@@ -322,6 +408,7 @@ impl<'sim, 'a, E> SimulatorBuilder<'sim, 'a, E> {
                     logger,
                     clock: self.clock,
                     next_tick,
+                    on_delivery: None,
                 }
             })
             .ok_or(EmptySlot)
@@ -358,6 +445,9 @@ where
     }
 }
 
+/// Called with each message just before it is delivered.
+pub type DeliveryHook<'sim, 'a, E> = Box<dyn FnMut(Time, ComponentId<'sim>, &E) + 'a>;
+
 pub struct Simulator<'sim, 'a, E, L> {
     id: Id<'sim>,
     components: Vec<Box<dyn Component<'sim, E, Receive = E> + 'a>>,
@@ -365,6 +455,7 @@ pub struct Simulator<'sim, 'a, E, L> {
     next_tick: Option<usize>,
     clock: Clock,
     logger: L,
+    on_delivery: Option<DeliveryHook<'sim, 'a, E>>,
 }
 
 impl<'sim, 'a, E, L> Simulator<'sim, 'a, E, L>
@@ -378,6 +469,9 @@ where
         }) = effects.pop_next()
         {
             assert_eq!(component_id.sim_id, self.id);
+            if let Some(on_delivery) = &mut self.on_delivery {
+                on_delivery(time, component_id, &effect);
+            }
             let component = &mut self.components[component_id.index];
             let messages = component.receive(effect, time);
             let next_tick = component.next_tick(time);
@@ -417,4 +511,61 @@ where
     pub fn time(&self) -> Time {
         self.clock.time()
     }
+
+    /// Calls `hook` with each message just before it is delivered, replacing any previous hook.
+    pub fn on_delivery(&mut self, hook: impl FnMut(Time, ComponentId<'sim>, &E) + 'a) {
+        self.on_delivery = Some(Box::new(hook));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        quantities::{seconds, Time},
+        util::rand::{DiscreteDistribution, Rng},
+    };
+
+    use super::TickQueue;
+
+    #[test]
+    fn tick_queue_matches_linear_scan() {
+        let mut rng = Rng::from_seed(983_475);
+        let mut queue = TickQueue::with_capacity(4);
+        let mut expected = BTreeMap::<usize, Time>::new();
+        let mut now = Time::SIM_START;
+        let ids = DiscreteDistribution::Uniform { min: 0, max: 40 };
+        let delays = DiscreteDistribution::Uniform { min: 0, max: 5 };
+        let ops = DiscreteDistribution::Uniform { min: 0, max: 3 };
+        for _ in 0..10_000 {
+            match rng.sample(&ops) {
+                0 => {
+                    let id = rng.sample(&ids) as usize;
+                    queue.update(id, None);
+                    expected.remove(&id);
+                }
+                1 => {
+                    let expected_next = expected
+                        .iter()
+                        .min_by_key(|&(_, time)| time)
+                        .map(|(&id, &time)| (time, id));
+                    let (time, id) = queue.pop_next();
+                    assert_eq!(expected_next.map(|x| x.0), id.map(|_| time));
+                    assert_eq!(expected_next.map(|x| x.1), id);
+                    if let Some(id) = id {
+                        expected.remove(&id);
+                        now = time;
+                    }
+                }
+                _ => {
+                    let id = rng.sample(&ids) as usize;
+                    let time = now + seconds(f64::from(rng.sample(&delays)));
+                    queue.update(id, Some(time));
+                    expected.insert(id, time);
+                }
+            }
+            assert_eq!(queue.next_time(), expected.values().min().copied());
+        }
+    }
 }
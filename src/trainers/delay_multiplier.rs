@@ -1,9 +1,11 @@
 use std::fmt::Debug;
 
+use rand::distributions::Bernoulli;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     ccas::delay_multiplier::DelayMultiplierCca,
+    quantities::Float,
     util::{
         rand::{ContinuousDistribution, Rng},
         OfLifetime,
@@ -15,7 +17,7 @@ use super::genetic::{GeneticConfig, GeneticPolicy, GeneticTrainer};
 
 #[derive(Serialize, Deserialize, Default)]
 pub struct DelayMultiplierTrainer {
-    genetic_config: GeneticConfig,
+    pub genetic_config: GeneticConfig,
 }
 
 #[derive(Debug, Default)]
@@ -34,7 +36,7 @@ impl OfLifetime for DelayMultiplierCcaTemplate {
     type Of<'a> = DelayMultiplierCcaTemplate;
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DelayMultiplierDna {
     pub multiplier: f64,
 }
@@ -52,18 +54,30 @@ impl Dna for DelayMultiplierDna {
 }
 
 impl GeneticPolicy for DelayMultiplierDna {
-    fn new_random(rng: &mut Rng) -> Self {
+    type Parameters = ();
+
+    fn new_random((): &(), rng: &mut Rng) -> Self {
         DelayMultiplierDna {
             multiplier: rng.sample(&ContinuousDistribution::Uniform { min: 0.0, max: 5.0 }),
         }
     }
 
-    fn spawn_child(&self, rng: &mut Rng) -> Self {
+    fn spawn_child(&self, (): &(), mutation_rate: Float, rng: &mut Rng) -> Self {
+        if !rng.sample(&Bernoulli::new(mutation_rate).unwrap()) {
+            return self.clone();
+        }
         DelayMultiplierDna {
             multiplier: self.multiplier
                 * rng.sample(&ContinuousDistribution::Uniform { min: 0.9, max: 1.1 }),
         }
     }
+
+    fn crossover(&self, other: &Self, (): &(), rng: &mut Rng) -> Self {
+        let weight = rng.sample(&ContinuousDistribution::Uniform { min: 0., max: 1. });
+        DelayMultiplierDna {
+            multiplier: weight * self.multiplier + (1. - weight) * other.multiplier,
+        }
+    }
 }
 
 impl GeneticTrainer for DelayMultiplierTrainer {
@@ -71,6 +85,50 @@ impl GeneticTrainer for DelayMultiplierTrainer {
     type CcaTemplate<'a> = DelayMultiplierCcaTemplate;
 
     fn genetic_config(&self) -> GeneticConfig {
-        todo!()
+        self.genetic_config.clone()
+    }
+
+    fn policy_parameters(&self) -> &() {
+        &()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        eval::EvaluationConfig,
+        flow::AlphaFairness,
+        networks::DefaultNetworkConfig,
+        quantities::seconds,
+        trainers::{genetic::GeneticConfig, DefaultEffect},
+        util::rand::Rng,
+        Trainer,
+    };
+
+    use super::{DelayMultiplierDna, DelayMultiplierTrainer};
+
+    #[test]
+    fn determinism() {
+        let mut rng = Rng::from_seed(123_456);
+        let trainer = DelayMultiplierTrainer {
+            genetic_config: GeneticConfig {
+                iters: 3,
+                population_size: 6,
+                elitism: 1,
+                evaluation_config: EvaluationConfig {
+                    network_samples: 4,
+                    run_sim_for: seconds(10.),
+                },
+                ..GeneticConfig::default()
+            },
+        };
+        let result = trainer.train::<DefaultEffect>(
+            &DefaultNetworkConfig::default(),
+            &AlphaFairness::PROPORTIONAL_THROUGHPUT_DELAY_FAIRNESS,
+            &mut |_, _: &DelayMultiplierDna| {},
+            None,
+            &mut rng,
+        );
+        insta::assert_yaml_snapshot!(result);
     }
 }
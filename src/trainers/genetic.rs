@@ -1,38 +1,283 @@
-use std::{cmp::Reverse, iter::repeat};
+use std::cmp::Reverse;
 
+use anyhow::{anyhow, Result};
 use itertools::Itertools;
 use ordered_float::NotNan;
-use serde::{Deserialize, Serialize};
+use rand::distributions::{Bernoulli, Uniform};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::{
     eval::EvaluationConfig,
     flow::UtilityFunction,
+    quantities::Float,
     util::{rand::Rng, OfLifetime},
     CcaTemplate, Dna, NetworkDistribution, ProgressHandler, Trainer,
 };
 
+/// How parents are chosen from a population ranked from best to worst.
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Selection {
+    /// Gives each of the best `fraction` of the population the same number of children, in
+    /// order of rank. The second parent of a crossover is chosen uniformly among them.
+    Truncation { fraction: Float },
+    /// Chooses the best of `size` policies drawn uniformly, with replacement.
+    Tournament { size: u32 },
+}
+
+impl Selection {
+    /// Number of the best policies that truncation selection chooses from.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    fn candidates(ranked: usize, fraction: Float) -> usize {
+        ((ranked as Float * fraction).ceil() as usize).clamp(1, ranked)
+    }
+
+    /// Chooses the first parent of child number `child` out of `children`.
+    fn select_parent<'a, P>(
+        &self,
+        ranked: &[&'a P],
+        child: usize,
+        children: usize,
+        rng: &mut Rng,
+    ) -> &'a P {
+        match self {
+            Selection::Truncation { fraction } => {
+                ranked[child * Self::candidates(ranked.len(), *fraction) / children]
+            }
+            Selection::Tournament { .. } => self.select(ranked, rng),
+        }
+    }
+
+    /// Chooses a parent at random.
+    fn select<'a, P>(&self, ranked: &[&'a P], rng: &mut Rng) -> &'a P {
+        let index = match self {
+            Selection::Truncation { fraction } => {
+                rng.sample(&Uniform::new(0, Self::candidates(ranked.len(), *fraction)))
+            }
+            Selection::Tournament { size } => {
+                let index = Uniform::new(0, ranked.len());
+                (0..(*size).max(1))
+                    .map(|_| rng.sample(&index))
+                    .min()
+                    .unwrap()
+            }
+        };
+        ranked[index]
+    }
+}
+
+/// Probability with which each gene of a child is mutated, over the course of training.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MutationSchedule {
+    Constant {
+        rate: Float,
+    },
+    /// Interpolates linearly from `start` in the first generation towards `end`.
+    Linear {
+        start: Float,
+        end: Float,
+    },
+    /// Interpolates geometrically from `start` in the first generation towards `end`, which
+    /// decays the rate by the same factor every generation.
+    Exponential {
+        start: Float,
+        end: Float,
+    },
+}
+
+impl MutationSchedule {
+    /// Returns the mutation rate once `frac_complete` of the generations have been trained.
+    #[must_use]
+    pub fn rate(&self, frac_complete: Float) -> Float {
+        match self {
+            MutationSchedule::Constant { rate } => *rate,
+            MutationSchedule::Linear { start, end } => start + (end - start) * frac_complete,
+            MutationSchedule::Exponential { start, end } => {
+                start * (end / start).powf(frac_complete)
+            }
+        }
+        .clamp(0., 1.)
+    }
+
+    /// Returns an error unless every rate is a probability, and rates interpolated
+    /// geometrically are positive.
+    fn check(&self) -> Result<()> {
+        let (rates, geometric) = match self {
+            MutationSchedule::Constant { rate } => (vec![*rate], false),
+            MutationSchedule::Linear { start, end } => (vec![*start, *end], false),
+            MutationSchedule::Exponential { start, end } => (vec![*start, *end], true),
+        };
+        for rate in rates {
+            if !(0. ..=1.).contains(&rate) {
+                return Err(anyhow!("Mutation rate {rate} should be between 0 and 1"));
+            }
+            if geometric && rate == 0. {
+                return Err(anyhow!(
+                    "Mutation rates of an exponential schedule should be greater than 0"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(try_from = "GeneticConfigFields")]
 pub struct GeneticConfig {
+    pub iters: u32,
+    pub population_size: u32,
+    pub selection: Selection,
+    /// Number of the best policies carried over unchanged into the next generation.
+    pub elitism: u32,
+    /// Probability that a child is the crossover of two parents, rather than derived from one.
+    pub crossover_rate: Float,
+    pub mutation_rate: MutationSchedule,
+    pub evaluation_config: EvaluationConfig,
+}
+
+/// The fields of a `GeneticConfig` as written in a config file, before they are validated.
+///
+/// Configs without the selection, elitism, crossover and mutation settings breed each generation
+/// as they did before those settings existed: each policy in the best half of an even population
+/// is mutated into two children.
+#[derive(Deserialize)]
+struct GeneticConfigFields {
     iters: u32,
     population_size: u32,
+    #[serde(default = "default_selection")]
+    selection: Selection,
+    #[serde(default)]
+    elitism: u32,
+    #[serde(default)]
+    crossover_rate: Float,
+    #[serde(default = "default_mutation_rate")]
+    mutation_rate: MutationSchedule,
     evaluation_config: EvaluationConfig,
 }
 
+const fn default_selection() -> Selection {
+    Selection::Truncation { fraction: 0.5 }
+}
+
+const fn default_mutation_rate() -> MutationSchedule {
+    MutationSchedule::Constant { rate: 1. }
+}
+
+impl TryFrom<GeneticConfigFields> for GeneticConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(fields: GeneticConfigFields) -> Result<Self> {
+        if fields.population_size == 0 {
+            return Err(anyhow!("The population must have at least one policy"));
+        }
+        if !(0. ..=1.).contains(&fields.crossover_rate) {
+            return Err(anyhow!(
+                "Crossover rate {} should be between 0 and 1",
+                fields.crossover_rate
+            ));
+        }
+        fields.mutation_rate.check()?;
+        if let Selection::Truncation { fraction } = fields.selection {
+            if !(fraction > 0. && fraction <= 1.) {
+                return Err(anyhow!(
+                    "Truncation fraction {fraction} should be greater than 0 and at most 1"
+                ));
+            }
+        }
+        Ok(GeneticConfig {
+            iters: fields.iters,
+            population_size: fields.population_size,
+            selection: fields.selection,
+            elitism: fields.elitism,
+            crossover_rate: fields.crossover_rate,
+            mutation_rate: fields.mutation_rate,
+            evaluation_config: fields.evaluation_config,
+        })
+    }
+}
+
 impl Default for GeneticConfig {
     fn default() -> Self {
         Self {
             iters: 100,
             population_size: 1000,
+            selection: Selection::Tournament { size: 3 },
+            elitism: 10,
+            crossover_rate: 0.5,
+            mutation_rate: MutationSchedule::Exponential {
+                start: 1.,
+                end: 0.1,
+            },
             evaluation_config: EvaluationConfig::default(),
         }
     }
 }
 
-pub trait GeneticPolicy: Dna + Sync {
-    fn new_random(rng: &mut Rng) -> Self;
+impl GeneticConfig {
+    /// Breeds the next generation from policies ranked from best to worst.
+    fn next_generation<P: GeneticPolicy>(
+        &self,
+        ranked: &[&P],
+        parameters: &P::Parameters,
+        frac_complete: Float,
+        rng: &mut Rng,
+    ) -> Vec<P> {
+        let mutation_rate = self.mutation_rate.rate(frac_complete);
+        let crossover = Bernoulli::new(self.crossover_rate).unwrap();
+        let elites = ranked
+            .iter()
+            .take(self.elitism.min(self.population_size) as usize)
+            .map(|&policy| policy.clone())
+            .collect_vec();
+        let num_children = self.population_size as usize - elites.len();
+        elites
+            .into_iter()
+            .chain((0..num_children).map(|child| {
+                let parent = self
+                    .selection
+                    .select_parent(ranked, child, num_children, rng);
+                // Crossover never draws from the RNG when it's disabled, so that configs without
+                // it breed the same children as before it existed
+                if self.crossover_rate > 0. && rng.sample(&crossover) {
+                    let other = self.selection.select(ranked, rng);
+                    parent.crossover(other, parameters, rng).spawn_child(
+                        parameters,
+                        mutation_rate,
+                        rng,
+                    )
+                } else {
+                    parent.spawn_child(parameters, mutation_rate, rng)
+                }
+            }))
+            .collect()
+    }
+}
+
+pub trait GeneticPolicy: Dna + Clone + Serialize + DeserializeOwned {
+    /// Settings shared by every policy in a population, such as the bounds of each gene.
+    type Parameters: Sync;
+
+    fn new_random(parameters: &Self::Parameters, rng: &mut Rng) -> Self;
+
+    /// Returns a mutated copy of this policy, in which each gene is mutated with probability
+    /// `mutation_rate`.
+    #[must_use]
+    fn spawn_child(
+        &self,
+        parameters: &Self::Parameters,
+        mutation_rate: Float,
+        rng: &mut Rng,
+    ) -> Self;
 
+    /// Returns a child that combines the genes of this policy and `other`.
     #[must_use]
-    fn spawn_child(&self, rng: &mut Rng) -> Self;
+    fn crossover(&self, other: &Self, parameters: &Self::Parameters, rng: &mut Rng) -> Self;
 }
 
 pub trait GeneticTrainer {
@@ -40,6 +285,17 @@ pub trait GeneticTrainer {
     type CcaTemplate<'a>: CcaTemplate<'a, Policy = &'a Self::Policy>;
 
     fn genetic_config(&self) -> GeneticConfig;
+
+    fn policy_parameters(&self) -> &<Self::Policy as GeneticPolicy>::Parameters;
+}
+
+/// The population at the start of a generation.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct GeneticCheckpoint<P: GeneticPolicy> {
+    pub generation: u32,
+    pub population: Vec<P>,
+    pub rng: Rng,
 }
 
 impl<T> Trainer for T
@@ -47,27 +303,47 @@ where
     T: GeneticTrainer,
 {
     type Dna = T::Policy;
+    type Checkpoint = GeneticCheckpoint<T::Policy>;
     type CcaTemplate<'a> = T::CcaTemplate<'a>;
 
     fn train<G>(
         &self,
         network_config: &impl NetworkDistribution<G>,
         utility_function: &impl UtilityFunction,
-        progress_handler: &mut impl ProgressHandler<Self::Dna>,
+        progress_handler: &mut impl ProgressHandler<Self::Dna, Self::Checkpoint>,
+        checkpoint: Option<Self::Checkpoint>,
         rng: &mut Rng,
     ) -> T::Policy
     where
         G: OfLifetime,
     {
         let config = self.genetic_config();
-        let mut population = (0..config.population_size)
-            .map(|_| T::Policy::new_random(rng))
-            .collect_vec();
-        for i in 0..config.iters {
+        let parameters = self.policy_parameters();
+        let (first_generation, mut population) = match checkpoint {
+            Some(checkpoint) => {
+                *rng = checkpoint.rng;
+                (checkpoint.generation, checkpoint.population)
+            }
+            None => (
+                0,
+                (0..config.population_size)
+                    .map(|_| T::Policy::new_random(parameters, rng))
+                    .collect_vec(),
+            ),
+        };
+        let mut best = None;
+        for i in first_generation..config.iters {
+            progress_handler.save_checkpoint(&GeneticCheckpoint {
+                generation: i,
+                population: population.clone(),
+                rng: rng.clone(),
+            });
             let frac = f64::from(i) / f64::from(config.iters);
             let mut scores = population
                 .into_iter()
                 .map(|d| (d, rng.create_child()))
+                .collect_vec()
+                .into_par_iter()
                 .filter_map(|(d, mut rng)| {
                     let score = config.evaluation_config.evaluate::<_, G, _>(
                         T::CcaTemplate::default().with(&d),
@@ -77,18 +353,170 @@ where
                     );
                     score.map(|(s, p)| (d, s, p)).ok()
                 })
-                .collect_vec();
+                .collect::<Vec<_>>();
             scores.sort_by_key(|x| Reverse(NotNan::new(x.1).unwrap()));
 
-            println!("Score: {}", scores.first().unwrap().1);
-            progress_handler.update_progress(frac, &scores.first().unwrap().0);
-            scores.truncate(config.population_size as usize / 2);
-            population = scores
-                .iter()
-                .flat_map(|x| repeat(&x.0).take(2))
-                .map(|x| x.spawn_child(rng))
-                .collect();
+            let (best_policy, best_score, _) = scores
+                .first()
+                .expect("Some policy to be evaluated on networks with active flows");
+            println!("Score: {best_score}");
+            progress_handler.update_progress(frac, best_policy);
+            let ranked = scores.iter().map(|x| &x.0).collect_vec();
+            population = config.next_generation(&ranked, parameters, frac, rng);
+            best = scores.into_iter().next().map(|x| x.0);
         }
-        population.into_iter().next().unwrap()
+        best.unwrap_or_else(|| population.into_iter().next().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter::repeat_n;
+
+    use itertools::Itertools;
+    use serde_json::{json, Value};
+
+    use crate::{
+        trainers::delay_multiplier::DelayMultiplierDna,
+        util::rand::{ContinuousDistribution, Rng},
+    };
+
+    use super::{GeneticConfig, MutationSchedule, Selection};
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn next_generation_keeps_elites() {
+        let ranked = (1..=8)
+            .map(|i| DelayMultiplierDna {
+                multiplier: f64::from(i),
+            })
+            .collect_vec();
+        for selection in [
+            Selection::Truncation { fraction: 0.25 },
+            Selection::Tournament { size: 3 },
+        ] {
+            let config = GeneticConfig {
+                population_size: 10,
+                selection,
+                elitism: 2,
+                crossover_rate: 0.,
+                mutation_rate: MutationSchedule::Constant { rate: 0. },
+                ..GeneticConfig::default()
+            };
+            let next = config.next_generation(
+                &ranked.iter().collect_vec(),
+                &(),
+                0.,
+                &mut Rng::from_seed(0),
+            );
+            assert_eq!(next.len(), 10);
+            assert_eq!(next[0].multiplier, 1.);
+            assert_eq!(next[1].multiplier, 2.);
+            assert!(next.iter().all(|dna| dna.multiplier.fract() == 0.));
+        }
+        let truncated = GeneticConfig {
+            population_size: 10,
+            selection: Selection::Truncation { fraction: 0.25 },
+            elitism: 0,
+            crossover_rate: 0.,
+            mutation_rate: MutationSchedule::Constant { rate: 0. },
+            ..GeneticConfig::default()
+        }
+        .next_generation(
+            &ranked.iter().collect_vec(),
+            &(),
+            0.,
+            &mut Rng::from_seed(0),
+        );
+        assert!(truncated.iter().all(|dna| dna.multiplier <= 2.));
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn default_settings_breed_as_before() {
+        let config: GeneticConfig = serde_json::from_value(json!({
+            "iters": 10,
+            "population_size": 6,
+            "evaluation_config": { "network_samples": 4, "run_sim_for": "10s" },
+        }))
+        .unwrap();
+        let ranked = (1..=6)
+            .map(|i| DelayMultiplierDna {
+                multiplier: f64::from(i),
+            })
+            .collect_vec();
+        let next = config.next_generation(
+            &ranked.iter().collect_vec(),
+            &(),
+            0.5,
+            &mut Rng::from_seed(0),
+        );
+        // Each of the best half cloned twice and mutated, as the training loop used to do
+        let mut rng = Rng::from_seed(0);
+        let before = ranked
+            .iter()
+            .take(3)
+            .flat_map(|dna| repeat_n(dna, 2))
+            .map(|dna| {
+                dna.multiplier * rng.sample(&ContinuousDistribution::Uniform { min: 0.9, max: 1.1 })
+            })
+            .collect_vec();
+        assert_eq!(next.iter().map(|dna| dna.multiplier).collect_vec(), before);
+    }
+
+    #[test]
+    fn mutation_schedules() {
+        let linear = MutationSchedule::Linear { start: 1., end: 0. };
+        assert!((linear.rate(0.25) - 0.75).abs() < 1e-9);
+        let exponential = MutationSchedule::Exponential {
+            start: 1.,
+            end: 0.01,
+        };
+        assert!((exponential.rate(0.5) - 0.1).abs() < 1e-9);
+        assert!((MutationSchedule::Constant { rate: 2. }.rate(0.) - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn loading_configs() {
+        let fields = json!({
+            "iters": 10,
+            "population_size": 20,
+            "evaluation_config": { "network_samples": 4, "run_sim_for": "10s" },
+        });
+        let config: GeneticConfig = serde_json::from_value(fields.clone()).unwrap();
+        assert!(matches!(
+            config.selection,
+            Selection::Truncation { fraction } if fraction == 0.5
+        ));
+        assert_eq!(config.elitism, 0);
+        assert_eq!(config.crossover_rate, 0.);
+        assert_eq!(config.mutation_rate.rate(0.5), 1.);
+
+        let with = |key: &str, value: Value| {
+            let mut fields = fields.clone();
+            fields[key] = value;
+            serde_json::from_value::<GeneticConfig>(fields)
+        };
+        assert!(with("crossover_rate", json!(1.5)).is_err());
+        assert!(with("population_size", json!(0)).is_err());
+        assert!(with("selection", json!({ "type": "truncation", "fraction": 0. })).is_err());
+        assert!(with("selection", json!({ "type": "tournament", "size": 2 })).is_ok());
+        assert!(with("mutation_rate", json!({ "type": "constant", "rate": 1.5 })).is_err());
+        assert!(with(
+            "mutation_rate",
+            json!({ "type": "linear", "start": -0.1, "end": 0.5 })
+        )
+        .is_err());
+        assert!(with(
+            "mutation_rate",
+            json!({ "type": "exponential", "start": 0., "end": 0.1 })
+        )
+        .is_err());
+        assert!(with(
+            "mutation_rate",
+            json!({ "type": "exponential", "start": 1., "end": 0.01 })
+        )
+        .is_ok());
     }
 }
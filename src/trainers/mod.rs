@@ -2,16 +2,24 @@ use derive_more::{From, TryInto};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    components::{packet::Packet, senders::lossy::LossySenderEffect, toggler::Toggle},
+    components::{
+        packet::Packet, senders::lossy::LossySenderEffect, toggler::Toggle, workload::FlowCompleted,
+    },
+    simulation::EffectKind,
     util::{never::Never, OfLifetime},
 };
 
-use self::{delay_multiplier::DelayMultiplierTrainer, remy::RemyTrainer, remyr::RemyrTrainer};
+use self::{
+    delay_multiplier::DelayMultiplierTrainer, remy::RemyTrainer, remy_genetic::RemyGeneticTrainer,
+    remyr::RemyrTrainer, remyr_es::RemyrEsTrainer,
+};
 
 pub mod delay_multiplier;
 pub mod genetic;
 pub mod remy;
+pub mod remy_genetic;
 pub mod remyr;
+pub mod remyr_es;
 
 #[derive(Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -19,6 +27,8 @@ pub enum TrainerConfig {
     Remy(RemyTrainer),
     Remyr(RemyrTrainer),
     DelayMultiplier(DelayMultiplierTrainer),
+    RemyGenetic(RemyGeneticTrainer),
+    RemyrEs(RemyrEsTrainer),
 }
 
 #[derive(From, TryInto)]
@@ -26,9 +36,70 @@ pub enum DefaultEffect<'sim> {
     LossySender(LossySenderEffect<'sim, DefaultEffect<'sim>>),
     Packet(Packet<'sim, DefaultEffect<'sim>>),
     Toggle(Toggle),
+    FlowCompleted(FlowCompleted),
     Never(Never),
 }
 
+impl EffectKind for DefaultEffect<'_> {
+    fn kind(&self) -> &'static str {
+        match self {
+            DefaultEffect::LossySender(LossySenderEffect::Packet(_)) => "sender_packet",
+            DefaultEffect::LossySender(LossySenderEffect::Toggle(_)) => "sender_toggle",
+            DefaultEffect::LossySender(LossySenderEffect::Transfer(_)) => "sender_transfer",
+            DefaultEffect::Packet(_) => "packet",
+            DefaultEffect::Toggle(_) => "toggle",
+            DefaultEffect::FlowCompleted(_) => "flow_completed",
+            DefaultEffect::Never(_) => "never",
+        }
+    }
+}
+
 impl<'sim> OfLifetime for DefaultEffect<'sim> {
     type Of<'a> = DefaultEffect<'a>;
 }
+
+/// Trains once, and then again from the checkpoint halfway through, checking that the resumed run
+/// saves the same checkpoints and returns the same DNA.
+#[cfg(test)]
+pub(crate) fn assert_resumes_identically<T: crate::Trainer>(trainer: &T) {
+    use crate::{
+        flow::AlphaFairness, networks::DefaultNetworkConfig, quantities::Float, util::rand::Rng,
+        Dna, ProgressHandler,
+    };
+
+    struct SavedCheckpoints(Vec<String>);
+
+    impl<P, C: Serialize> ProgressHandler<P, C> for SavedCheckpoints {
+        fn update_progress(&mut self, _frac_complete: Float, _current: &P) {}
+
+        fn save_checkpoint(&mut self, checkpoint: &C) {
+            self.0.push(serde_json::to_string(checkpoint).unwrap());
+        }
+    }
+
+    let train = |checkpoint, saved: &mut SavedCheckpoints, seed| {
+        trainer
+            .train::<DefaultEffect>(
+                &DefaultNetworkConfig::default(),
+                &AlphaFairness::PROPORTIONAL_THROUGHPUT_DELAY_FAIRNESS,
+                saved,
+                checkpoint,
+                &mut Rng::from_seed(seed),
+            )
+            .serialize()
+            .unwrap()
+    };
+    let mut original = SavedCheckpoints(Vec::new());
+    let dna = train(None, &mut original, 123_456);
+    assert!(original.0.len() > 1);
+    let halfway = original.0.len() / 2;
+    let mut resumed = SavedCheckpoints(Vec::new());
+    // The RNG is replaced by the one in the checkpoint, so the seed shouldn't matter
+    let resumed_dna = train(
+        Some(serde_json::from_str(&original.0[halfway]).unwrap()),
+        &mut resumed,
+        0,
+    );
+    assert_eq!(resumed.0, original.0[halfway..]);
+    assert_eq!(resumed_dna, dna);
+}
@@ -33,6 +33,9 @@ pub struct RemyTrainer {
     pub change_eval_config: EvaluationConfig,
     pub count_rule_usage_config: EvaluationConfig,
     pub drill_down: bool,
+    /// Also split rules on the marking EWMA, for networks where senders use ECN.
+    #[serde(default)]
+    pub mark_ewma: bool,
 }
 
 impl Default for RemyTrainer {
@@ -72,6 +75,7 @@ impl Default for RemyTrainer {
             },
             count_rule_usage_config: EvaluationConfig::default(),
             drill_down: true,
+            mark_ewma: false,
         }
     }
 }
@@ -148,8 +152,21 @@ where
     f
 }
 
+/// The rule tree, with the rules already optimized in the current round marked, before the next
+/// rule is optimized.
+#[derive(Serialize, Deserialize)]
+pub struct RemyCheckpoint {
+    pub dna: RemyDna,
+    pub split: u32,
+    pub optimization_round: u32,
+    /// Seeds every evaluation used to count rule usage.
+    pub eval_rng: Rng,
+    pub rng: Rng,
+}
+
 impl Trainer for RemyTrainer {
     type Dna = RemyDna;
+    type Checkpoint = RemyCheckpoint;
     type CcaTemplate<'a> = RemyCcaTemplate<&'a RemyDna>;
 
     #[allow(clippy::too_many_lines)]
@@ -157,10 +174,28 @@ impl Trainer for RemyTrainer {
         &self,
         network_config: &impl NetworkDistribution<G>,
         utility_function: &impl UtilityFunction,
-        progress_handler: &mut impl ProgressHandler<Self::Dna>,
+        progress_handler: &mut impl ProgressHandler<Self::Dna, Self::Checkpoint>,
+        checkpoint: Option<Self::Checkpoint>,
         rng: &mut Rng,
     ) -> RemyDna {
-        let new_eval_rng = rng.identical_child_factory();
+        let (mut dna, first_split, resumed_round, eval_rng) = if let Some(checkpoint) = checkpoint {
+            *rng = checkpoint.rng;
+            (
+                checkpoint.dna,
+                checkpoint.split,
+                Some(checkpoint.optimization_round),
+                checkpoint.eval_rng,
+            )
+        } else {
+            let eval_rng = rng.create_child();
+            (
+                RemyDna::default(self.default_action.clone()),
+                0,
+                None,
+                eval_rng,
+            )
+        };
+        let new_eval_rng = || eval_rng.clone();
         let eval_and_count = coerce(|dna: &mut RemyDna| {
             let counting_tree = CountingRuleTree::new(&mut dna.0);
             self.count_rule_usage_config
@@ -183,11 +218,17 @@ impl Trainer for RemyTrainer {
                 )
                 .expect("Simulation to have active flows")
         };
-        let mut dna = RemyDna::default(self.default_action.clone());
-        for i in 0..=self.rule_splits {
+        for i in first_split..=self.rule_splits {
             let frac = f64::from(i) / f64::from(self.rule_splits + 1);
             progress_handler.update_progress(frac, &dna);
-            if i == 0 {
+            let resumed_round = resumed_round.filter(|_| i == first_split);
+            if let Some(optimization_round) = resumed_round {
+                println!(
+                    "Resuming optimization round {}/{}",
+                    optimization_round + 1,
+                    self.optimization_rounds_per_split
+                );
+            } else if i == 0 {
                 println!("Starting optimization");
             } else {
                 let mut counts = eval_and_count(&mut dna);
@@ -199,7 +240,7 @@ impl Trainer for RemyTrainer {
                             leaf.domain(),
                             fraction_used * 100.
                         );
-                        leaf.split();
+                        leaf.split(self.mark_ewma);
                         counts = eval_and_count(&mut dna);
                         if counts.num_used_rules() > 1 {
                             break;
@@ -212,18 +253,29 @@ impl Trainer for RemyTrainer {
                         leaf.domain(),
                         fraction_used * 100.
                     );
-                    leaf.split();
+                    leaf.split(self.mark_ewma);
                 }
             }
-            for optimization_round in 0..self.optimization_rounds_per_split {
+            for optimization_round in resumed_round.unwrap_or(0)..self.optimization_rounds_per_split
+            {
                 println!(
                     "  Starting optimization round {}/{}",
                     optimization_round + 1,
                     self.optimization_rounds_per_split
                 );
-                while let Some((fraction_used, mut leaf)) =
-                    eval_and_count(&mut dna).most_used_unoptimized_rule()
-                {
+                loop {
+                    progress_handler.save_checkpoint(&RemyCheckpoint {
+                        dna: dna.clone(),
+                        split: i,
+                        optimization_round,
+                        eval_rng: eval_rng.clone(),
+                        rng: rng.clone(),
+                    });
+                    let Some((fraction_used, mut leaf)) =
+                        eval_and_count(&mut dna).most_used_unoptimized_rule()
+                    else {
+                        break;
+                    };
                     if fraction_used == 0. {
                         println!("    Skipped remaining rules with 0% usage");
                         break;
@@ -281,7 +333,7 @@ mod tests {
         flow::AlphaFairness,
         networks::DefaultNetworkConfig,
         quantities::seconds,
-        trainers::{remy::RemyDna, DefaultEffect},
+        trainers::{assert_resumes_identically, remy::RemyDna, DefaultEffect},
         util::rand::Rng,
         Trainer,
     };
@@ -306,8 +358,27 @@ mod tests {
             &DefaultNetworkConfig::default(),
             &AlphaFairness::PROPORTIONAL_THROUGHPUT_DELAY_FAIRNESS,
             &mut |_, _: &RemyDna| {},
+            None,
             &mut rng,
         );
         insta::assert_yaml_snapshot!(result);
     }
+
+    #[test]
+    fn resume_from_checkpoint() {
+        let evaluation_config = EvaluationConfig {
+            network_samples: 2,
+            run_sim_for: seconds(2.),
+        };
+        let trainer = RemyTrainer::default();
+        assert_resumes_identically(&RemyTrainer {
+            rule_splits: 0,
+            optimization_rounds_per_split: 1,
+            // Only try the smallest change to each part of the action
+            max_action_change: trainer.initial_action_change.clone(),
+            change_eval_config: evaluation_config.clone(),
+            count_rule_usage_config: evaluation_config,
+            ..trainer
+        });
+    }
 }
@@ -0,0 +1,241 @@
+use rand::distributions::{Bernoulli, Uniform};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ccas::remy::{action::Action, dna::RemyDna, rule_tree::RuleTree, RemyCcaTemplate},
+    eval::EvaluationConfig,
+    quantities::{milliseconds, seconds, Float},
+    util::rand::{ContinuousDistribution, Rng},
+};
+
+use super::{
+    genetic::{GeneticConfig, GeneticPolicy, GeneticTrainer},
+    remy::RemyTrainer,
+};
+
+/// Bounds and mutation sizes for evolving `RemyDna` rule trees.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RemyGeneticParameters {
+    pub min_action: Action,
+    pub max_action: Action,
+    /// Standard deviation of the change to each part of a mutated action.
+    pub action_change: Action,
+    /// Random initial trees have up to this many rules split.
+    pub max_initial_splits: u32,
+    /// Probability, scaled by the mutation rate, that a child also has a rule split or a node
+    /// merged.
+    pub structure_mutation_rate: Float,
+    /// Also split rules on the marking EWMA, for networks where senders use ECN.
+    #[serde(default)]
+    pub mark_ewma: bool,
+}
+
+impl Default for RemyGeneticParameters {
+    fn default() -> Self {
+        let remy = RemyTrainer::default();
+        Self {
+            min_action: remy.min_action,
+            max_action: remy.max_action,
+            action_change: Action {
+                window_multiplier: 0.05,
+                window_increment: 4,
+                intersend_delay: milliseconds(0.1),
+            },
+            max_initial_splits: 2,
+            structure_mutation_rate: 0.1,
+            mark_ewma: remy.mark_ewma,
+        }
+    }
+}
+
+impl RemyGeneticParameters {
+    fn random_action(&self, rng: &mut Rng) -> Action {
+        let (min, max) = (&self.min_action, &self.max_action);
+        Action {
+            window_multiplier: rng.sample(&ContinuousDistribution::Uniform {
+                min: min.window_multiplier,
+                max: max.window_multiplier,
+            }),
+            window_increment: rng.sample(&Uniform::new_inclusive(
+                min.window_increment,
+                max.window_increment,
+            )),
+            intersend_delay: rng.sample(&ContinuousDistribution::Uniform {
+                min: min.intersend_delay,
+                max: max.intersend_delay,
+            }),
+        }
+    }
+
+    /// Adds normally distributed noise to each part of `action`, keeping it within bounds.
+    #[allow(clippy::cast_possible_truncation)]
+    fn perturb(&self, action: &Action, rng: &mut Rng) -> Action {
+        let (min, max, change) = (&self.min_action, &self.max_action, &self.action_change);
+        let mut perturb = |value: Float, std_dev: Float, min: Float, max: Float| {
+            rng.sample(&ContinuousDistribution::Normal {
+                mean: value,
+                std_dev,
+            })
+            .clamp(min, max)
+        };
+        Action {
+            window_multiplier: perturb(
+                action.window_multiplier,
+                change.window_multiplier,
+                min.window_multiplier,
+                max.window_multiplier,
+            ),
+            window_increment: perturb(
+                Float::from(action.window_increment),
+                Float::from(change.window_increment),
+                Float::from(min.window_increment),
+                Float::from(max.window_increment),
+            )
+            .round() as i32,
+            intersend_delay: seconds(perturb(
+                action.intersend_delay.seconds(),
+                change.intersend_delay.seconds(),
+                min.intersend_delay.seconds(),
+                max.intersend_delay.seconds(),
+            )),
+        }
+    }
+}
+
+impl GeneticPolicy for RemyDna {
+    type Parameters = RemyGeneticParameters;
+
+    /// Splits a single rule up to `max_initial_splits` times, then gives every rule a random
+    /// action.
+    fn new_random(parameters: &RemyGeneticParameters, rng: &mut Rng) -> Self {
+        let mut tree = RuleTree::default(parameters.random_action(rng));
+        for _ in 0..rng.sample(&Uniform::new_inclusive(0, parameters.max_initial_splits)) {
+            tree.split_random_leaf(parameters.mark_ewma, rng);
+        }
+        for action in tree.leaf_actions_mut() {
+            *action = parameters.random_action(rng);
+        }
+        RemyDna(tree)
+    }
+
+    /// Perturbs the action of each rule with probability `mutation_rate`, and then may split a
+    /// rule or merge a node, with equal probability.
+    fn spawn_child(
+        &self,
+        parameters: &RemyGeneticParameters,
+        mutation_rate: Float,
+        rng: &mut Rng,
+    ) -> Self {
+        let mut tree = self.0.clone();
+        let mutate = Bernoulli::new(mutation_rate).unwrap();
+        for action in tree.leaf_actions_mut() {
+            if rng.sample(&mutate) {
+                *action = parameters.perturb(action, rng);
+            }
+        }
+        let restructure =
+            Bernoulli::new(mutation_rate * parameters.structure_mutation_rate).unwrap();
+        if rng.sample(&restructure)
+            && (rng.sample(&Bernoulli::new(0.5).unwrap()) || !tree.merge_random_node(rng))
+        {
+            tree.split_random_leaf(parameters.mark_ewma, rng);
+        }
+        RemyDna(tree)
+    }
+
+    /// Exchanges a subtree with the subtree of `other` covering the same domain.
+    fn crossover(&self, other: &Self, _parameters: &RemyGeneticParameters, rng: &mut Rng) -> Self {
+        RemyDna(self.0.crossover(&other.0, rng))
+    }
+}
+
+/// Evolves `RemyDna` rule trees with a genetic algorithm, as an alternative to the greedy
+/// search of `RemyTrainer`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RemyGeneticTrainer {
+    pub genetic_config: GeneticConfig,
+    pub parameters: RemyGeneticParameters,
+}
+
+impl Default for RemyGeneticTrainer {
+    fn default() -> Self {
+        Self {
+            genetic_config: GeneticConfig {
+                iters: 50,
+                population_size: 100,
+                evaluation_config: EvaluationConfig {
+                    network_samples: 50,
+                    run_sim_for: seconds(60.),
+                },
+                ..GeneticConfig::default()
+            },
+            parameters: RemyGeneticParameters::default(),
+        }
+    }
+}
+
+impl GeneticTrainer for RemyGeneticTrainer {
+    type Policy = RemyDna;
+    type CcaTemplate<'a> = RemyCcaTemplate<&'a RemyDna>;
+
+    fn genetic_config(&self) -> GeneticConfig {
+        self.genetic_config.clone()
+    }
+
+    fn policy_parameters(&self) -> &RemyGeneticParameters {
+        &self.parameters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        eval::EvaluationConfig,
+        flow::AlphaFairness,
+        networks::DefaultNetworkConfig,
+        quantities::seconds,
+        trainers::{assert_resumes_identically, genetic::GeneticConfig, DefaultEffect},
+        util::rand::Rng,
+        Trainer,
+    };
+
+    use super::{RemyDna, RemyGeneticParameters, RemyGeneticTrainer};
+
+    fn small_trainer() -> RemyGeneticTrainer {
+        RemyGeneticTrainer {
+            genetic_config: GeneticConfig {
+                iters: 3,
+                population_size: 6,
+                elitism: 1,
+                evaluation_config: EvaluationConfig {
+                    network_samples: 4,
+                    run_sim_for: seconds(3.),
+                },
+                ..GeneticConfig::default()
+            },
+            parameters: RemyGeneticParameters {
+                max_initial_splits: 1,
+                structure_mutation_rate: 0.5,
+                ..RemyGeneticParameters::default()
+            },
+        }
+    }
+
+    #[test]
+    fn determinism() {
+        let mut rng = Rng::from_seed(123_456);
+        let result = small_trainer().train::<DefaultEffect>(
+            &DefaultNetworkConfig::default(),
+            &AlphaFairness::PROPORTIONAL_THROUGHPUT_DELAY_FAIRNESS,
+            &mut |_, _: &RemyDna| {},
+            None,
+            &mut rng,
+        );
+        insta::assert_yaml_snapshot!(result);
+    }
+
+    #[test]
+    fn resume_from_checkpoint() {
+        assert_resumes_identically(&small_trainer());
+    }
+}
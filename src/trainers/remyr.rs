@@ -19,7 +19,7 @@ use crate::{
         remyr::{
             dna::RemyrDna,
             net::{
-                CopyToDevice, HiddenLayers, PolicyNet, PolicyNetwork, ACTION,
+                CopyToDevice, FlatParameters, HiddenLayers, PolicyNet, PolicyNetwork, ACTION,
                 AGENT_SPECIFIC_GLOBAL_STATE, OBSERVATION,
             },
         },
@@ -79,11 +79,13 @@ impl Default for RemyrTrainer {
                 ack_ewma: milliseconds(0.),
                 send_ewma: milliseconds(0.),
                 rtt_ratio: 1.,
+                mark_ewma: 0.,
             },
             max_point: Point {
                 ack_ewma: seconds(0.5),
                 send_ewma: seconds(0.5),
                 rtt_ratio: 5.,
+                mark_ewma: 1.,
             },
             min_action: Action {
                 window_multiplier: 0.,
@@ -361,7 +363,7 @@ fn rollout<G: OfLifetime>(
                 };
                 let cca_template = RemyCcaTemplate::new(repeat_actions.clone());
                 let cca_gen = ManuallyDrop::new(cca_template.with_not_sync(dna));
-                n.populate_sim(&builder, &*cca_gen, &mut rng, new_flow);
+                n.populate_sim(&builder, || &*cca_gen, &mut rng, new_flow);
                 let clock = builder.clock();
                 let mut sim = builder.build(NothingLogger).unwrap();
                 let sim_end = Time::from_sim_start(training_config.run_sim_for);
@@ -375,8 +377,108 @@ fn rollout<G: OfLifetime>(
         .collect()
 }
 
+/// dfdx's Adam, which keeps its moments private, with moments that can be saved in a checkpoint.
+/// Each tensor is updated by dfdx's Adam kernel, in the same way as by dfdx's optimizer.
+struct CheckpointedAdam<D: Device<f32>> {
+    cfg: AdamConfig,
+    t: i32,
+    moment1: Gradients<f32, D>,
+    moment2: Gradients<f32, D>,
+}
+
+impl<D: Device<f32>> CheckpointedAdam<D> {
+    const fn new(cfg: AdamConfig) -> Self {
+        CheckpointedAdam {
+            cfg,
+            t: 0,
+            moment1: Gradients::leaky(),
+            moment2: Gradients::leaky(),
+        }
+    }
+
+    fn restore(cfg: AdamConfig, state: AdamState, module: &impl FlatParameters<D>) -> Self {
+        if state.t == 0 {
+            return Self::new(cfg);
+        }
+        CheckpointedAdam {
+            cfg,
+            t: state.t,
+            moment1: module.gradients_from_flat(&state.moment1),
+            moment2: module.gradients_from_flat(&state.moment2),
+        }
+    }
+
+    fn state(&self, module: &impl FlatParameters<D>) -> AdamState {
+        if self.t == 0 {
+            return AdamState {
+                t: 0,
+                moment1: Vec::new(),
+                moment2: Vec::new(),
+            };
+        }
+        AdamState {
+            t: self.t,
+            moment1: module.flat_gradients(&self.moment1),
+            moment2: module.flat_gradients(&self.moment2),
+        }
+    }
+}
+
+impl<M, D: Device<f32>> Optimizer<M, f32, D> for CheckpointedAdam<D> {
+    fn update_tensor<S: Shape>(
+        &mut self,
+        t: &mut Tensor<S, f32, D>,
+        gradients: &Gradients<f32, D>,
+        missing_tensors: &mut Vec<UniqueId>,
+    ) -> Result<(), Error> {
+        match gradients.get_ref_checked(t) {
+            None => missing_tensors.push(t.id()),
+            Some(g) => {
+                let m_t = self.moment1.get_or_alloc_mut(t)?;
+                let v_t = self.moment2.get_or_alloc_mut(t)?;
+                self.cfg.try_update(self.t, t, m_t, v_t, g)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn update(&mut self, module: &mut M, gradients: &Gradients<f32, D>) -> Result<(), Error>
+    where
+        M: UpdateParams<f32, D>,
+    {
+        self.t = self.t.checked_add(1).unwrap();
+        let mut missing_tensors = Vec::new();
+        module.try_update_params(self, gradients, &mut missing_tensors)?;
+        if missing_tensors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::UnusedTensors(missing_tensors))
+        }
+    }
+}
+
+/// The number of Adam updates so far, and its moments in the same order as the parameters. The
+/// moments are empty before the first update.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AdamState {
+    t: i32,
+    moment1: Vec<f32>,
+    moment2: Vec<f32>,
+}
+
+/// The networks and optimizer at the start of an iteration.
+#[derive(Serialize, Deserialize)]
+pub struct RemyrCheckpoint {
+    pub iteration: u32,
+    /// Parameters of the policy, the standard deviations of its actions and the critic.
+    pub parameters: Vec<f32>,
+    pub optimizer: AdamState,
+    pub rng: Rng,
+}
+
 impl Trainer for RemyrTrainer {
     type Dna = RemyrDna;
+    type Checkpoint = RemyrCheckpoint;
     type CcaTemplate<'a> = RemyCcaTemplate<&'a RemyrDna>;
 
     #[allow(clippy::too_many_lines)]
@@ -384,8 +486,9 @@ impl Trainer for RemyrTrainer {
         &self,
         network_config: &impl NetworkDistribution<G>,
         utility_function: &impl UtilityFunction,
-        progress_handler: &mut impl ProgressHandler<Self::Dna>,
-        rng: &mut crate::util::rand::Rng,
+        progress_handler: &mut impl ProgressHandler<Self::Dna, Self::Checkpoint>,
+        checkpoint: Option<Self::Checkpoint>,
+        rng: &mut Rng,
     ) -> Self::Dna
     where
         G: OfLifetime,
@@ -397,20 +500,32 @@ impl Trainer for RemyrTrainer {
             self.hidden_layers.critic_arch(),
         ));
         theta.1.bias = theta.1.bias + 0.5;
-
-        let mut optimizer = Adam::new(
-            &theta,
-            AdamConfig {
-                lr: self.learning_rate,
-                weight_decay: self.weight_decay.map(WeightDecay::Decoupled),
-                eps: 1e-5,
-                ..Default::default()
-            },
-        );
+        let adam_config = AdamConfig {
+            lr: self.learning_rate,
+            weight_decay: self.weight_decay.map(WeightDecay::Decoupled),
+            eps: 1e-5,
+            ..Default::default()
+        };
+        let (first_iteration, mut optimizer) = match checkpoint {
+            Some(checkpoint) => {
+                theta.set_parameters(&checkpoint.parameters);
+                *rng = checkpoint.rng;
+                let optimizer =
+                    CheckpointedAdam::restore(adam_config, checkpoint.optimizer, &theta);
+                (checkpoint.iteration, optimizer)
+            }
+            None => (0, CheckpointedAdam::new(adam_config)),
+        };
 
         let sim_dev = Cpu::default();
 
-        for i in 0..self.iters {
+        for i in first_iteration..self.iters {
+            progress_handler.save_checkpoint(&RemyrCheckpoint {
+                iteration: i,
+                parameters: theta.parameters(),
+                optimizer: optimizer.state(&theta),
+                rng: rng.clone(),
+            });
             let dna = self.initial_dna(theta.0.copy_to(&sim_dev));
 
             let frac = f64::from(i) / f64::from(self.iters);
@@ -547,7 +662,7 @@ mod tests {
         flow::AlphaFairness,
         networks::DefaultNetworkConfig,
         quantities::{milliseconds, seconds, Float},
-        trainers::DefaultEffect,
+        trainers::{assert_resumes_identically, DefaultEffect},
         util::rand::{ContinuousDistribution, Rng},
         Trainer,
     };
@@ -571,6 +686,7 @@ mod tests {
             &DefaultNetworkConfig::default(),
             &AlphaFairness::PROPORTIONAL_THROUGHPUT_DELAY_FAIRNESS,
             &mut |_: Float, _: &RemyrDna| {},
+            None,
             &mut rng,
         );
         let mut random_point = || Point::<false> {
@@ -583,6 +699,7 @@ mod tests {
                 max: seconds(0.5),
             }),
             rtt_ratio: rng.sample(&ContinuousDistribution::Uniform { min: 0., max: 1. }),
+            mark_ewma: 0.,
         };
         let precision = 10_000.;
         let actions = (0..100)
@@ -604,4 +721,18 @@ mod tests {
 
         insta::assert_yaml_snapshot!(actions);
     }
+
+    #[test]
+    fn resume_from_checkpoint() {
+        assert_resumes_identically(&RemyrTrainer {
+            iters: 4,
+            updates_per_iter: 2,
+            num_minibatches: 2,
+            rollout_config: EvaluationConfig {
+                network_samples: 1,
+                run_sim_for: seconds(10.),
+            },
+            ..RemyrTrainer::default()
+        });
+    }
 }
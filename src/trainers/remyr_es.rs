@@ -0,0 +1,255 @@
+use itertools::Itertools;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ccas::{
+        remy::{action::Action, point::Point, RemyCcaTemplate},
+        remyr::{
+            dna::RemyrDna,
+            net::{FlatParameters, HiddenLayers},
+        },
+    },
+    eval::EvaluationConfig,
+    flow::UtilityFunction,
+    quantities::{seconds, Float},
+    util::{
+        average::IterAverage,
+        rand::{ContinuousDistribution, Rng},
+        OfLifetime,
+    },
+    CcaTemplate, NetworkDistribution, ProgressHandler, Trainer,
+};
+
+use super::remyr::RemyrTrainer;
+
+/// Trains the same policy network as `RemyrTrainer` with OpenAI-style evolution strategies,
+/// which only need the utility of each perturbed policy rather than a reward for every action.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RemyrEsTrainer {
+    pub iters: u32,
+    /// Number of perturbations evaluated each iteration, along with their negations.
+    pub perturbation_pairs: u32,
+    /// Standard deviation of the noise added to each parameter of the policy.
+    pub noise_std_dev: f32,
+    pub learning_rate: f32,
+    pub min_point: Point,
+    pub max_point: Point,
+    pub min_action: Action,
+    pub max_action: Action,
+    pub hidden_layers: HiddenLayers,
+    /// Every perturbation in an iteration is evaluated on the same networks.
+    pub evaluation_config: EvaluationConfig,
+}
+
+impl Default for RemyrEsTrainer {
+    fn default() -> Self {
+        let remyr = RemyrTrainer::default();
+        Self {
+            iters: 300,
+            perturbation_pairs: 24,
+            noise_std_dev: 0.02,
+            learning_rate: 0.01,
+            min_point: remyr.min_point,
+            max_point: remyr.max_point,
+            min_action: remyr.min_action,
+            max_action: remyr.max_action,
+            hidden_layers: remyr.hidden_layers,
+            evaluation_config: EvaluationConfig {
+                network_samples: 16,
+                run_sim_for: seconds(60.),
+            },
+        }
+    }
+}
+
+impl RemyrEsTrainer {
+    fn dna(&self, parameters: &[f32]) -> RemyrDna {
+        let mut policy = self.hidden_layers.build_policy();
+        policy.set_parameters(parameters);
+        RemyrDna {
+            min_point: self.min_point.clone(),
+            max_point: self.max_point.clone(),
+            min_action: self.min_action.clone(),
+            max_action: self.max_action.clone(),
+            policy,
+        }
+    }
+}
+
+/// Replaces each score by its rank, scaled to lie between -0.5 and 0.5, so that updates don't
+/// depend on the scale of the utility function.
+#[allow(clippy::cast_precision_loss)]
+fn centered_ranks(scores: &[Float]) -> Vec<f32> {
+    let mut order = (0..scores.len()).collect_vec();
+    order.sort_by(|&a, &b| scores[a].total_cmp(&scores[b]));
+    let max_rank = (scores.len() - 1).max(1) as f32;
+    let mut ranks = vec![0.; scores.len()];
+    for (rank, index) in order.into_iter().enumerate() {
+        ranks[index] = rank as f32 / max_rank - 0.5;
+    }
+    ranks
+}
+
+/// The parameters of the policy at the start of an iteration.
+#[derive(Serialize, Deserialize)]
+pub struct RemyrEsCheckpoint {
+    pub iteration: u32,
+    pub parameters: Vec<f32>,
+    pub rng: Rng,
+}
+
+impl Trainer for RemyrEsTrainer {
+    type Dna = RemyrDna;
+    type Checkpoint = RemyrEsCheckpoint;
+    type CcaTemplate<'a> = RemyCcaTemplate<&'a RemyrDna>;
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    fn train<G>(
+        &self,
+        network_config: &impl NetworkDistribution<G>,
+        utility_function: &impl UtilityFunction,
+        progress_handler: &mut impl ProgressHandler<Self::Dna, Self::Checkpoint>,
+        checkpoint: Option<Self::Checkpoint>,
+        rng: &mut Rng,
+    ) -> Self::Dna
+    where
+        G: OfLifetime,
+    {
+        let noise = ContinuousDistribution::Normal {
+            mean: 0.,
+            std_dev: 1.,
+        };
+        let (first_iteration, mut parameters) = match checkpoint {
+            Some(checkpoint) => {
+                *rng = checkpoint.rng;
+                (checkpoint.iteration, checkpoint.parameters)
+            }
+            None => (0, self.hidden_layers.build_policy().parameters()),
+        };
+        for i in first_iteration..self.iters {
+            progress_handler.save_checkpoint(&RemyrEsCheckpoint {
+                iteration: i,
+                parameters: parameters.clone(),
+                rng: rng.clone(),
+            });
+            let frac = f64::from(i) / f64::from(self.iters);
+            progress_handler.update_progress(frac, &self.dna(&parameters));
+
+            let perturbations = (0..self.perturbation_pairs)
+                .map(|_| {
+                    (0..parameters.len())
+                        .map(|_| rng.sample(&noise) as f32)
+                        .collect_vec()
+                })
+                .collect_vec();
+            let new_eval_rng = rng.identical_child_factory();
+            let scores = perturbations
+                .iter()
+                .flat_map(|perturbation| [(perturbation, 1.), (perturbation, -1.)])
+                .collect_vec()
+                .into_par_iter()
+                .map(|(perturbation, sign)| {
+                    let candidate = parameters
+                        .iter()
+                        .zip(perturbation)
+                        .map(|(p, e)| p + sign * self.noise_std_dev * e)
+                        .collect_vec();
+                    let dna = self.dna(&candidate);
+                    self.evaluation_config
+                        .evaluate::<_, G, _>(
+                            RemyCcaTemplate::default().with(&dna),
+                            network_config,
+                            utility_function,
+                            &mut new_eval_rng(),
+                        )
+                        .map_or(Float::NEG_INFINITY, |(utility, _)| utility)
+                })
+                .collect::<Vec<_>>();
+            println!(
+                "Mean score: {}",
+                scores
+                    .iter()
+                    .copied()
+                    .filter(|s| s.is_finite())
+                    .average()
+                    .unwrap_or(Float::NAN)
+            );
+
+            let ranks = centered_ranks(&scores);
+            let step =
+                self.learning_rate / (2. * self.perturbation_pairs as f32 * self.noise_std_dev);
+            for (perturbation, pair) in perturbations.iter().zip(ranks.chunks(2)) {
+                let weight = step * (pair[0] - pair[1]);
+                for (p, e) in parameters.iter_mut().zip(perturbation) {
+                    *p += weight * e;
+                }
+            }
+        }
+        let dna = self.dna(&parameters);
+        progress_handler.update_progress(1., &dna);
+        dna
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ccas::remyr::{dna::RemyrDna, net::FlatParameters},
+        eval::EvaluationConfig,
+        flow::AlphaFairness,
+        networks::DefaultNetworkConfig,
+        quantities::{seconds, Float},
+        trainers::{assert_resumes_identically, DefaultEffect},
+        util::rand::Rng,
+        Dna, Trainer,
+    };
+
+    use super::{centered_ranks, RemyrEsTrainer};
+
+    #[test]
+    fn ranks_are_centered() {
+        assert_eq!(
+            centered_ranks(&[3., -1., Float::NEG_INFINITY, 10., 2.]),
+            [0.25, -0.25, -0.5, 0.5, 0.]
+        );
+    }
+
+    fn small_trainer() -> RemyrEsTrainer {
+        RemyrEsTrainer {
+            iters: 3,
+            perturbation_pairs: 3,
+            evaluation_config: EvaluationConfig {
+                network_samples: 2,
+                run_sim_for: seconds(5.),
+            },
+            ..RemyrEsTrainer::default()
+        }
+    }
+
+    #[test]
+    fn determinism() {
+        let trainer = small_trainer();
+        let train = || {
+            trainer
+                .train::<DefaultEffect>(
+                    &DefaultNetworkConfig::default(),
+                    &AlphaFairness::PROPORTIONAL_THROUGHPUT_DELAY_FAIRNESS,
+                    &mut |_: Float, _: &RemyrDna| {},
+                    None,
+                    &mut Rng::from_seed(5_243_533),
+                )
+                .serialize()
+                .unwrap()
+        };
+        let first = train();
+        assert_eq!(first, train());
+        let initial = trainer.dna(&trainer.hidden_layers.build_policy().parameters());
+        assert_ne!(first, initial.serialize().unwrap());
+    }
+
+    #[test]
+    fn resume_from_checkpoint() {
+        assert_resumes_identically(&small_trainer());
+    }
+}
@@ -1,10 +1,18 @@
-use std::{cell::RefCell, fmt::Debug};
+use std::{
+    cell::RefCell,
+    fmt::{self, Debug, Display, Formatter},
+    io::Write,
+};
 
+use derive_where::derive_where;
+use serde::Serialize;
 use tabled::{
     builder::Builder,
     settings::{style::HorizontalLine, Style},
 };
 
+use crate::quantities::{Float, Time, TimeSpan};
+
 macro_rules! log {
     ($logger:expr, $fmt_str:literal) => {
         $logger.log(|| format!($fmt_str))
@@ -14,8 +22,89 @@ macro_rules! log {
     };
 }
 
+/// Why a packet was dropped.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DropReason {
+    /// Lost at random by a lossy link.
+    Loss,
+    /// Turned away by the queue when it arrived, for example because the buffer was full.
+    Enqueue,
+    /// Removed from the front of the queue by an AQM such as `CoDel`.
+    Dequeue,
+}
+
+impl Display for DropReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DropReason::Loss => "loss",
+            DropReason::Enqueue => "rejected by queue",
+            DropReason::Dequeue => "AQM",
+        })
+    }
+}
+
+/// Something that happened in a simulation, emitted by a component through its `Logger`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    PacketEnqueued {
+        seq: u64,
+    },
+    PacketDropped {
+        seq: u64,
+        reason: DropReason,
+    },
+    /// A link passed a packet on to its next hop.
+    PacketDelivered {
+        seq: u64,
+    },
+    CwndChanged {
+        cwnd: u32,
+    },
+    /// A Remy CCA applied an action from its policy.
+    ActionChosen {
+        window_multiplier: Float,
+        window_increment: i32,
+        intersend_delay: TimeSpan,
+    },
+    /// A sender was switched on or off.
+    Toggled {
+        enabled: bool,
+    },
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::PacketEnqueued { seq } => write!(f, "Buffered packet {seq}"),
+            Event::PacketDropped { seq, reason } => write!(f, "Dropped packet {seq} ({reason})"),
+            Event::PacketDelivered { seq } => write!(f, "Delivered packet {seq}"),
+            Event::CwndChanged { cwnd } => write!(f, "Set cwnd to {cwnd}"),
+            Event::ActionChosen {
+                window_multiplier,
+                window_increment,
+                intersend_delay,
+            } => write!(
+                f,
+                "Chose action with window multiplier {window_multiplier}, window increment \
+                 {window_increment} and intersend delay {intersend_delay}"
+            ),
+            Event::Toggled { enabled: true } => write!(f, "Enabled"),
+            Event::Toggled { enabled: false } => write!(f, "Disabled"),
+        }
+    }
+}
+
 pub trait Logger: Debug {
     fn log(&mut self, msg: impl FnOnce() -> String);
+
+    /// Records an event that happened at `time`. `event` is only called by loggers that keep it,
+    /// so emitting events to a `NothingLogger` costs nothing. By default, the event is logged as a
+    /// message.
+    fn event(&mut self, _time: Time, event: impl FnOnce() -> Event) {
+        self.log(|| event().to_string());
+    }
 }
 
 impl<'a, T> Logger for &'a mut T
@@ -25,6 +114,10 @@ where
     fn log(&mut self, msg: impl FnOnce() -> String) {
         T::log(self, msg);
     }
+
+    fn event(&mut self, time: Time, event: impl FnOnce() -> Event) {
+        T::event(self, time, event);
+    }
 }
 
 #[derive(Debug)]
@@ -57,6 +150,8 @@ impl NothingLogger {
 
 impl Logger for NothingLogger {
     fn log(&mut self, _msg: impl FnOnce() -> String) {}
+
+    fn event(&mut self, _time: Time, _event: impl FnOnce() -> Event) {}
 }
 
 pub struct LogTable {
@@ -129,3 +224,158 @@ impl Logger for TableLogger<'_> {
         self.table.write(self.index, msg());
     }
 }
+
+#[derive(Serialize)]
+struct MessageLine {
+    source: usize,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct EventLine {
+    source: usize,
+    /// Seconds since the start of the simulation.
+    time: Float,
+    #[serde(flatten)]
+    event: Event,
+}
+
+/// Writes each event and message as a JSON object on its own line, along with the index of the
+/// logger that emitted it.
+pub struct JsonLines<W> {
+    writer: RefCell<W>,
+}
+
+impl<W> Debug for JsonLines<W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonLines").finish_non_exhaustive()
+    }
+}
+
+impl<W> JsonLines<W>
+where
+    W: Write,
+{
+    pub const fn new(writer: W) -> JsonLines<W> {
+        JsonLines {
+            writer: RefCell::new(writer),
+        }
+    }
+
+    pub const fn logger(&self, index: usize) -> JsonLinesLogger<'_, W> {
+        JsonLinesLogger { index, sink: self }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+
+    fn write(&self, line: &impl Serialize) {
+        let mut writer = self.writer.borrow_mut();
+        serde_json::to_writer(&mut *writer, line).expect("Failed to write log line");
+        writeln!(writer).expect("Failed to write log line");
+    }
+}
+
+#[derive_where(Debug)]
+pub struct JsonLinesLogger<'a, W> {
+    index: usize,
+    sink: &'a JsonLines<W>,
+}
+
+impl<W> Logger for JsonLinesLogger<'_, W>
+where
+    W: Write,
+{
+    fn log(&mut self, msg: impl FnOnce() -> String) {
+        self.sink.write(&MessageLine {
+            source: self.index,
+            message: msg(),
+        });
+    }
+
+    fn event(&mut self, time: Time, event: impl FnOnce() -> Event) {
+        self.sink.write(&EventLine {
+            source: self.index,
+            time: (time - Time::SIM_START).seconds(),
+            event: event(),
+        });
+    }
+}
+
+/// An event kept by an `EventBuffer`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggedEvent {
+    /// Index of the logger that emitted the event.
+    pub source: usize,
+    pub time: Time,
+    pub event: Event,
+}
+
+/// Keeps every event in memory, ignoring messages, so that tests can check what happened.
+#[derive(Debug, Default)]
+pub struct EventBuffer {
+    events: RefCell<Vec<LoggedEvent>>,
+}
+
+impl EventBuffer {
+    #[must_use]
+    pub fn new() -> EventBuffer {
+        EventBuffer::default()
+    }
+
+    pub const fn logger(&self, index: usize) -> BufferLogger<'_> {
+        BufferLogger {
+            index,
+            buffer: self,
+        }
+    }
+
+    #[must_use]
+    pub fn into_events(self) -> Vec<LoggedEvent> {
+        self.events.into_inner()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BufferLogger<'a> {
+    index: usize,
+    buffer: &'a EventBuffer,
+}
+
+impl Logger for BufferLogger<'_> {
+    fn log(&mut self, _msg: impl FnOnce() -> String) {}
+
+    fn event(&mut self, time: Time, event: impl FnOnce() -> Event) {
+        self.buffer.events.borrow_mut().push(LoggedEvent {
+            source: self.index,
+            time,
+            event: event(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::quantities::{milliseconds, Time};
+
+    use super::{DropReason, Event, JsonLines, Logger};
+
+    #[test]
+    fn json_lines_tags_events_with_source_and_time() {
+        let sink = JsonLines::new(Vec::new());
+        sink.logger(3)
+            .event(Time::from_sim_start(milliseconds(250.)), || {
+                Event::PacketDropped {
+                    seq: 7,
+                    reason: DropReason::Enqueue,
+                }
+            });
+        sink.logger(1).log(|| "Probing RTT".to_string());
+        assert_eq!(
+            String::from_utf8(sink.into_inner()).unwrap(),
+            "{\"source\":3,\"time\":0.25,\"type\":\"packet_dropped\",\"seq\":7,\"reason\":\"enqueue\"}\n\
+             {\"source\":1,\"message\":\"Probing RTT\"}\n"
+        );
+    }
+}
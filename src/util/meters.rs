@@ -5,8 +5,11 @@ use std::{
 };
 
 use crate::{
-    flow::{FlowNeverActive, FlowProperties, NoPacketsAcked},
-    quantities::{bits_per_second, Float, Information, InformationRate, Time, TimeSpan},
+    flow::{
+        DelayStats, FctStats, FlowNeverActive, FlowProperties, NoPacketsAcked, NoPacketsSent,
+        NoTransfersCompleted, Summary,
+    },
+    quantities::{bits_per_second, seconds, Float, Information, InformationRate, Time, TimeSpan},
 };
 
 use super::average::Average;
@@ -137,6 +140,169 @@ where
     }
 }
 
+/// Estimates the `p`-quantile of a stream of values in constant space, using the P² algorithm of
+/// Jain and Chlamtac (1985).
+///
+/// The estimate is exact until five values have been recorded.
+#[derive(Clone, Debug)]
+pub struct P2Quantile {
+    p: Float,
+    /// Marker heights, the middle of which is the estimate once five values have been recorded.
+    heights: Vec<Float>,
+    /// Actual marker positions, counting from 1.
+    positions: [Float; 5],
+    desired_positions: [Float; 5],
+    increments: [Float; 5],
+}
+
+impl P2Quantile {
+    #[must_use]
+    pub fn new(p: Float) -> P2Quantile {
+        assert!((0. ..=1.).contains(&p));
+        P2Quantile {
+            p,
+            heights: Vec::with_capacity(5),
+            positions: [1., 2., 3., 4., 5.],
+            desired_positions: [1., 1. + 2. * p, 1. + 4. * p, 3. + 2. * p, 5.],
+            increments: [0., p / 2., p, Float::midpoint(1., p), 1.],
+        }
+    }
+
+    pub fn record(&mut self, value: Float) {
+        if self.heights.len() < 5 {
+            let index = self.heights.partition_point(|&h| h <= value);
+            self.heights.insert(index, value);
+            return;
+        }
+        let q = &mut self.heights;
+        let n = &mut self.positions;
+        let cell = if value < q[0] {
+            q[0] = value;
+            0
+        } else if value >= q[4] {
+            q[4] = value;
+            3
+        } else {
+            (0..4).find(|&i| value < q[i + 1]).unwrap()
+        };
+        for position in &mut n[cell + 1..] {
+            *position += 1.;
+        }
+        for (desired, increment) in self.desired_positions.iter_mut().zip(self.increments) {
+            *desired += increment;
+        }
+        for i in 1..4 {
+            let offset = self.desired_positions[i] - n[i];
+            if (offset >= 1. && n[i + 1] - n[i] > 1.) || (offset <= -1. && n[i - 1] - n[i] < -1.) {
+                let d = offset.signum();
+                let parabolic = q[i]
+                    + d / (n[i + 1] - n[i - 1])
+                        * ((n[i] - n[i - 1] + d) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                            + (n[i + 1] - n[i] - d) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]));
+                q[i] = if q[i - 1] < parabolic && parabolic < q[i + 1] {
+                    parabolic
+                } else {
+                    let j = if d > 0. { i + 1 } else { i - 1 };
+                    q[i] + d * (q[j] - q[i]) / (n[j] - n[i])
+                };
+                n[i] += d;
+            }
+        }
+    }
+
+    /// Returns `None` if no values have been recorded.
+    #[must_use]
+    pub fn value(&self) -> Option<Float> {
+        match self.heights.len() {
+            0 => None,
+            5.. => Some(self.heights[2]),
+            #[allow(
+                clippy::cast_possible_truncation,
+                clippy::cast_precision_loss,
+                clippy::cast_sign_loss
+            )]
+            len => {
+                let rank = (self.p * len as Float).ceil() as usize;
+                Some(self.heights[rank.max(1) - 1])
+            }
+        }
+    }
+}
+
+/// Streaming mean, median, 95th and 99th percentiles of a sequence of time spans.
+#[derive(Clone, Debug)]
+pub struct SummaryMeter {
+    mean: Mean<TimeSpan>,
+    p50: P2Quantile,
+    p95: P2Quantile,
+    p99: P2Quantile,
+}
+
+impl SummaryMeter {
+    #[must_use]
+    pub fn new() -> SummaryMeter {
+        SummaryMeter {
+            mean: Mean::new(),
+            p50: P2Quantile::new(0.5),
+            p95: P2Quantile::new(0.95),
+            p99: P2Quantile::new(0.99),
+        }
+    }
+
+    pub fn record(&mut self, value: TimeSpan) {
+        self.mean.record(value);
+        self.p50.record(value.seconds());
+        self.p95.record(value.seconds());
+        self.p99.record(value.seconds());
+    }
+
+    #[must_use]
+    pub fn summary(&self) -> Option<Summary<TimeSpan>> {
+        Some(Summary {
+            mean: self.mean.value().ok()?,
+            p50: seconds(self.p50.value()?),
+            p95: seconds(self.p95.value()?),
+            p99: seconds(self.p99.value()?),
+        })
+    }
+}
+
+impl Default for SummaryMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Records the distribution of round-trip times, and of queueing delay, estimated as the amount
+/// by which each RTT exceeds the lowest RTT seen before it.
+#[derive(Clone, Debug, Default)]
+pub struct DelayMeter {
+    rtt: SummaryMeter,
+    queueing_delay: SummaryMeter,
+    min_rtt: Option<TimeSpan>,
+}
+
+impl DelayMeter {
+    #[must_use]
+    pub fn new() -> DelayMeter {
+        DelayMeter::default()
+    }
+
+    pub fn record(&mut self, rtt: TimeSpan) {
+        let min_rtt = self.min_rtt.map_or(rtt, |min_rtt| min_rtt.min(rtt));
+        self.min_rtt = Some(min_rtt);
+        self.rtt.record(rtt);
+        self.queueing_delay.record(rtt - min_rtt);
+    }
+
+    pub fn stats(&self) -> Result<DelayStats, NoPacketsAcked> {
+        Ok(DelayStats {
+            rtt: self.rtt.summary().ok_or(NoPacketsAcked)?,
+            queueing_delay: self.queueing_delay.summary().ok_or(NoPacketsAcked)?,
+        })
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Timer {
     total_time: TimeSpan,
@@ -233,7 +399,26 @@ impl InfoRateMeter {
 pub trait FlowMeter: Debug {
     fn set_enabled(&mut self, time: Time);
     fn set_disabled(&mut self, time: Time);
-    fn packet_received(&mut self, data: Information, rtt: TimeSpan, time: Time);
+    /// Called when a data packet carrying `data` is sent, including retransmissions.
+    fn packet_sent(&mut self, data: Information, time: Time);
+    /// `data` counts everything that was delivered, including packets which had already been
+    /// received, whereas `new_data` only counts packets delivered for the first time.
+    fn packet_received(
+        &mut self,
+        data: Information,
+        new_data: Information,
+        rtt: TimeSpan,
+        time: Time,
+    );
+    /// Called when a finite transfer of `size` completes, `duration` after it was requested.
+    /// `ideal_duration` is how long it would have taken on an otherwise idle network.
+    fn transfer_completed(
+        &mut self,
+        size: Information,
+        duration: TimeSpan,
+        ideal_duration: TimeSpan,
+        time: Time,
+    );
 }
 
 impl<T> FlowMeter for &mut T
@@ -248,8 +433,28 @@ where
         (*self).set_disabled(time);
     }
 
-    fn packet_received(&mut self, data: Information, rtt: TimeSpan, time: Time) {
-        (*self).packet_received(data, rtt, time);
+    fn packet_sent(&mut self, data: Information, time: Time) {
+        (*self).packet_sent(data, time);
+    }
+
+    fn packet_received(
+        &mut self,
+        data: Information,
+        new_data: Information,
+        rtt: TimeSpan,
+        time: Time,
+    ) {
+        (*self).packet_received(data, new_data, rtt, time);
+    }
+
+    fn transfer_completed(
+        &mut self,
+        size: Information,
+        duration: TimeSpan,
+        ideal_duration: TimeSpan,
+        time: Time,
+    ) {
+        (*self).transfer_completed(size, duration, ideal_duration, time);
     }
 }
 
@@ -265,8 +470,29 @@ where
         self.borrow_mut().set_disabled(time);
     }
 
-    fn packet_received(&mut self, data: Information, rtt: TimeSpan, time: Time) {
-        self.borrow_mut().packet_received(data, rtt, time);
+    fn packet_sent(&mut self, data: Information, time: Time) {
+        self.borrow_mut().packet_sent(data, time);
+    }
+
+    fn packet_received(
+        &mut self,
+        data: Information,
+        new_data: Information,
+        rtt: TimeSpan,
+        time: Time,
+    ) {
+        self.borrow_mut().packet_received(data, new_data, rtt, time);
+    }
+
+    fn transfer_completed(
+        &mut self,
+        size: Information,
+        duration: TimeSpan,
+        ideal_duration: TimeSpan,
+        time: Time,
+    ) {
+        self.borrow_mut()
+            .transfer_completed(size, duration, ideal_duration, time);
     }
 }
 
@@ -285,9 +511,33 @@ where
         self.1.set_disabled(time);
     }
 
-    fn packet_received(&mut self, data: Information, rtt: TimeSpan, time: Time) {
-        self.0.packet_received(data, rtt, time);
-        self.1.packet_received(data, rtt, time);
+    fn packet_sent(&mut self, data: Information, time: Time) {
+        self.0.packet_sent(data, time);
+        self.1.packet_sent(data, time);
+    }
+
+    fn packet_received(
+        &mut self,
+        data: Information,
+        new_data: Information,
+        rtt: TimeSpan,
+        time: Time,
+    ) {
+        self.0.packet_received(data, new_data, rtt, time);
+        self.1.packet_received(data, new_data, rtt, time);
+    }
+
+    fn transfer_completed(
+        &mut self,
+        size: Information,
+        duration: TimeSpan,
+        ideal_duration: TimeSpan,
+        time: Time,
+    ) {
+        self.0
+            .transfer_completed(size, duration, ideal_duration, time);
+        self.1
+            .transfer_completed(size, duration, ideal_duration, time);
     }
 }
 
@@ -299,13 +549,107 @@ impl FlowMeter for NoFlowMeter {
 
     fn set_disabled(&mut self, _time: Time) {}
 
-    fn packet_received(&mut self, _data: Information, _rtt: TimeSpan, _time: Time) {}
+    fn packet_sent(&mut self, _data: Information, _time: Time) {}
+
+    fn packet_received(
+        &mut self,
+        _data: Information,
+        _new_data: Information,
+        _rtt: TimeSpan,
+        _time: Time,
+    ) {
+    }
+
+    fn transfer_completed(
+        &mut self,
+        _size: Information,
+        _duration: TimeSpan,
+        _ideal_duration: TimeSpan,
+        _time: Time,
+    ) {
+    }
+}
+
+/// Records the completion time and slowdown of each finite transfer.
+#[derive(Debug, Default)]
+pub struct CompletionTimeMeter {
+    completion_times: Vec<TimeSpan>,
+    slowdowns: Vec<Float>,
+}
+
+impl CompletionTimeMeter {
+    #[must_use]
+    pub const fn new() -> CompletionTimeMeter {
+        CompletionTimeMeter {
+            completion_times: Vec::new(),
+            slowdowns: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, duration: TimeSpan, ideal_duration: TimeSpan) {
+        self.completion_times.push(duration);
+        self.slowdowns.push(duration / ideal_duration);
+    }
+
+    pub fn stats(&self) -> Result<FctStats, NoTransfersCompleted> {
+        Ok(FctStats {
+            completion_time: Summary::from_samples(&self.completion_times)
+                .map_err(|_| NoTransfersCompleted)?,
+            slowdown: Summary::from_samples(&self.slowdowns).map_err(|_| NoTransfersCompleted)?,
+        })
+    }
+}
+
+/// Compares the data sent by a flow with the data acknowledged, to find the fraction lost.
+#[derive(Debug)]
+pub struct LossMeter {
+    sent: Information,
+    acknowledged: Information,
+}
+
+impl Default for LossMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LossMeter {
+    #[must_use]
+    pub const fn new() -> LossMeter {
+        LossMeter {
+            sent: Information::ZERO,
+            acknowledged: Information::ZERO,
+        }
+    }
+
+    pub fn record_sent(&mut self, data: Information) {
+        self.sent = self.sent + data;
+    }
+
+    pub fn record_acknowledged(&mut self, data: Information) {
+        self.acknowledged = self.acknowledged + data;
+    }
+
+    /// Fraction of the data sent that hasn't been acknowledged, so data still in flight counts
+    /// as lost.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn loss_rate(&self) -> Result<Float, NoPacketsSent> {
+        if self.sent == Information::ZERO {
+            return Err(NoPacketsSent);
+        }
+        let ratio = self.acknowledged.bytes() as Float / self.sent.bytes() as Float;
+        Ok((1. - ratio).max(0.))
+    }
 }
 
 #[derive(Debug)]
 pub struct AverageFlowMeter {
-    average_throughput: InfoRateMeter,
-    average_rtt: Mean<TimeSpan>,
+    throughput: InfoRateMeter,
+    goodput: InfoRateMeter,
+    rtt: Mean<TimeSpan>,
+    delay: DelayMeter,
+    completion_time: CompletionTimeMeter,
+    loss: LossMeter,
 }
 
 impl AverageFlowMeter {
@@ -319,8 +663,12 @@ impl AverageFlowMeter {
     #[must_use]
     pub fn new_disabled() -> AverageFlowMeter {
         AverageFlowMeter {
-            average_throughput: InfoRateMeter::new_disabled(),
-            average_rtt: Mean::new(),
+            throughput: InfoRateMeter::new_disabled(),
+            goodput: InfoRateMeter::new_disabled(),
+            rtt: Mean::new(),
+            delay: DelayMeter::new(),
+            completion_time: CompletionTimeMeter::new(),
+            loss: LossMeter::new(),
         }
     }
 
@@ -328,35 +676,158 @@ impl AverageFlowMeter {
         &self,
         current_time: Time,
     ) -> Result<FlowProperties, FlowNeverActive> {
-        self.average_throughput
-            .current_value(current_time)
-            .map(|average_throughput| FlowProperties {
-                throughput: average_throughput,
-                rtt: self.average_rtt.value().map_err(|_| NoPacketsAcked),
-            })
-            .map_err(|_| FlowNeverActive)
+        let (Ok(throughput), Ok(goodput)) = (
+            self.throughput.current_value(current_time),
+            self.goodput.current_value(current_time),
+        ) else {
+            return Err(FlowNeverActive);
+        };
+        Ok(FlowProperties {
+            throughput,
+            goodput,
+            rtt: self.rtt.value().map_err(|_| NoPacketsAcked),
+            delay: self.delay.stats(),
+            fct: self.completion_time.stats(),
+            loss_rate: self.loss.loss_rate(),
+        })
     }
 }
 
 impl FlowMeter for AverageFlowMeter {
     fn set_enabled(&mut self, time: Time) {
-        self.average_throughput.enable(time);
+        self.throughput.enable(time);
+        self.goodput.enable(time);
+    }
+
+    fn set_disabled(&mut self, time: Time) {
+        self.throughput.disable(time);
+        self.goodput.disable(time);
+    }
+
+    fn packet_sent(&mut self, data: Information, _time: Time) {
+        self.loss.record_sent(data);
+    }
+
+    fn packet_received(
+        &mut self,
+        data: Information,
+        new_data: Information,
+        rtt: TimeSpan,
+        _time: Time,
+    ) {
+        self.throughput.record_info(data);
+        self.goodput.record_info(new_data);
+        self.rtt.record(rtt);
+        self.delay.record(rtt);
+        self.loss.record_acknowledged(data);
+    }
+
+    fn transfer_completed(
+        &mut self,
+        _size: Information,
+        duration: TimeSpan,
+        ideal_duration: TimeSpan,
+        _time: Time,
+    ) {
+        self.completion_time.record(duration, ideal_duration);
+    }
+}
+
+/// Records how much data a flow received in each of a series of fixed-length intervals, starting
+/// at the start of the simulation, and whether it was enabled throughout each interval.
+#[derive(Debug)]
+pub struct IntervalThroughputMeter {
+    interval: TimeSpan,
+    intervals: Vec<(Information, bool)>,
+    enabled: bool,
+}
+
+impl IntervalThroughputMeter {
+    #[must_use]
+    pub const fn new(interval: TimeSpan) -> IntervalThroughputMeter {
+        IntervalThroughputMeter {
+            interval,
+            intervals: Vec::new(),
+            enabled: false,
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn index(&self, time: Time) -> usize {
+        ((time - Time::SIM_START) / self.interval).floor() as usize
+    }
+
+    fn current(&mut self, time: Time) -> &mut (Information, bool) {
+        let index = self.index(time);
+        while self.intervals.len() <= index {
+            self.intervals.push((Information::ZERO, self.enabled));
+        }
+        &mut self.intervals[index]
+    }
+
+    /// Returns the throughput in each interval that ended by `end`, or `None` for intervals in
+    /// which the flow wasn't enabled throughout.
+    #[must_use]
+    pub fn throughputs(&self, end: Time) -> Vec<Option<InformationRate>> {
+        (0..self.index(end))
+            .map(|i| {
+                let (data, enabled) = self
+                    .intervals
+                    .get(i)
+                    .copied()
+                    .unwrap_or((Information::ZERO, self.enabled));
+                enabled.then(|| data / self.interval)
+            })
+            .collect()
+    }
+}
+
+impl FlowMeter for IntervalThroughputMeter {
+    fn set_enabled(&mut self, time: Time) {
+        self.current(time);
+        self.enabled = true;
     }
 
     fn set_disabled(&mut self, time: Time) {
-        self.average_throughput.disable(time);
+        self.current(time).1 = false;
+        self.enabled = false;
     }
 
-    fn packet_received(&mut self, data: Information, rtt: TimeSpan, _time: Time) {
-        self.average_throughput.record_info(data);
-        self.average_rtt.record(rtt);
+    fn packet_sent(&mut self, _data: Information, _time: Time) {}
+
+    fn packet_received(
+        &mut self,
+        data: Information,
+        _new_data: Information,
+        _rtt: TimeSpan,
+        time: Time,
+    ) {
+        let current = self.current(time);
+        current.0 = current.0 + data;
+    }
+
+    fn transfer_completed(
+        &mut self,
+        _size: Information,
+        _duration: TimeSpan,
+        _ideal_duration: TimeSpan,
+        _time: Time,
+    ) {
     }
 }
 
 #[derive(Debug)]
 pub struct CurrentFlowMeter {
     current_throughput: TimeBasedEWMA<InformationRate>,
+    current_goodput: TimeBasedEWMA<InformationRate>,
     current_rtt: TimeBasedEWMA<TimeSpan>,
+    /// Percentiles of every RTT measured so far, as streaming estimates cannot be decayed.
+    delay: DelayMeter,
+    /// Every transfer completed so far, rather than a decaying average, as transfers complete
+    /// too rarely for an average over recent ones to be meaningful.
+    completion_time: CompletionTimeMeter,
+    /// Every packet sent so far, as packets in flight would make a decaying loss rate too noisy.
+    loss: LossMeter,
     last_received: Time,
     enabled: bool,
 }
@@ -379,7 +850,14 @@ impl CurrentFlowMeter {
                 half_life,
                 Some((current_time, bits_per_second(0.))),
             ),
+            current_goodput: TimeBasedEWMA::new(
+                half_life,
+                Some((current_time, bits_per_second(0.))),
+            ),
             current_rtt: TimeBasedEWMA::new(half_life, None),
+            delay: DelayMeter::new(),
+            completion_time: CompletionTimeMeter::new(),
+            loss: LossMeter::new(),
             last_received: current_time,
             enabled: false,
         }
@@ -398,7 +876,11 @@ impl CurrentFlowMeter {
         if self.enabled {
             Ok(FlowProperties {
                 throughput: self.current_throughput.value(current_time).unwrap(),
+                goodput: self.current_goodput.value(current_time).unwrap(),
                 rtt: self.current_rtt.value(current_time).ok_or(NoPacketsAcked),
+                delay: self.delay.stats(),
+                fct: self.completion_time.stats(),
+                loss_rate: self.loss.loss_rate(),
             })
         } else {
             Err(FlowNotActive)
@@ -420,23 +902,47 @@ impl FlowMeter for CurrentFlowMeter {
         self.enabled = false;
     }
 
-    fn packet_received(&mut self, data: Information, rtt: TimeSpan, time: Time) {
+    fn packet_sent(&mut self, data: Information, _time: Time) {
+        self.loss.record_sent(data);
+    }
+
+    fn packet_received(
+        &mut self,
+        data: Information,
+        new_data: Information,
+        rtt: TimeSpan,
+        time: Time,
+    ) {
         assert!(time > self.last_received);
         self.current_throughput
             .update(data / (time - self.last_received), time);
+        self.current_goodput
+            .update(new_data / (time - self.last_received), time);
         self.current_rtt.update(rtt, time);
+        self.delay.record(rtt);
+        self.loss.record_acknowledged(data);
         self.last_received = time;
     }
+
+    fn transfer_completed(
+        &mut self,
+        _size: Information,
+        duration: TimeSpan,
+        ideal_duration: TimeSpan,
+        _time: Time,
+    ) {
+        self.completion_time.record(duration, ideal_duration);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        quantities::{seconds, Time},
+        quantities::{bytes, seconds, Time},
         util::meters::TimeBasedEWMA,
     };
 
-    use super::{Mean, EWMA};
+    use super::{CurrentFlowMeter, FlowMeter, Mean, P2Quantile, EWMA};
 
     #[test]
     pub fn mean() {
@@ -477,4 +983,80 @@ mod tests {
         assert_eq!(ewma.value(Time::from_sim_start(seconds(2.))), Some(6.5));
         assert_eq!(ewma.value(Time::from_sim_start(seconds(3.))), Some(3.25));
     }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn p2_quantile() {
+        let mut quantiles = [0.5, 0.95, 0.99].map(P2Quantile::new);
+        assert!(quantiles[0].value().is_none());
+        for x in [3., 1., 2.] {
+            for q in &mut quantiles {
+                q.record(x);
+            }
+        }
+        assert_eq!(
+            quantiles.each_ref().map(P2Quantile::value),
+            [Some(2.), Some(3.), Some(3.)]
+        );
+        // A permutation of 3..10_007, visited in a scattered order
+        for i in 3..10_007_u64 {
+            let x = (i * 7919 % 10_007) as f64;
+            for q in &mut quantiles {
+                q.record(x);
+            }
+        }
+        for (q, expected) in quantiles.iter().zip([5003., 9506., 9907.]) {
+            let estimate = q.value().unwrap();
+            assert!(
+                (estimate - expected).abs() < 50.,
+                "{estimate} vs {expected}"
+            );
+        }
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    pub fn current_flow_meter_records_delay() {
+        let mut meter = CurrentFlowMeter::new_enabled(Time::SIM_START, seconds(1.));
+        assert!(meter
+            .current_properties(Time::SIM_START)
+            .unwrap()
+            .delay
+            .is_err());
+        for i in 1..=100 {
+            let rtt = if i % 10 == 0 { 0.5 } else { 0.1 };
+            meter.packet_received(
+                bytes(1000),
+                bytes(1000),
+                seconds(rtt),
+                Time::from_sim_start(seconds(f64::from(i))),
+            );
+        }
+        let time = Time::from_sim_start(seconds(100.));
+        let properties = meter.current_properties(time).unwrap();
+        let delay = properties.delay.unwrap();
+        assert!((delay.rtt.p50 - seconds(0.1)).abs() < seconds(0.001));
+        // Every tenth RTT is a spike, which shows in the tail but not in the median
+        assert!(delay.rtt.p99 > seconds(0.3));
+        assert!(properties.rtt.unwrap() < delay.rtt.p99);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    pub fn current_flow_meter_records_transfers() {
+        let start = Time::from_sim_start(seconds(0.));
+        let mut meter = CurrentFlowMeter::new_enabled(start, seconds(1.));
+        assert!(meter.current_properties(start).unwrap().fct.is_err());
+        for (duration, ideal_duration) in [(1., 0.5), (3., 0.5)] {
+            meter.transfer_completed(
+                bytes(1000),
+                seconds(duration),
+                seconds(ideal_duration),
+                start,
+            );
+        }
+        let fct = meter.current_properties(start).unwrap().fct.unwrap();
+        assert_eq!(fct.completion_time.mean, seconds(2.));
+        assert_eq!(fct.slowdown.p99, 6.);
+    }
 }
@@ -4,6 +4,8 @@ pub mod logging;
 pub mod meters;
 pub mod never;
 pub mod rand;
+pub mod replay;
+pub mod stats;
 
 pub trait OfLifetime {
     type Of<'a>;
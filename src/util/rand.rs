@@ -1,5 +1,8 @@
 use rand::{seq::SliceRandom, SeedableRng};
-use rand_distr::{num_traits::PrimInt, Distribution, Exp, Normal, Uniform};
+use rand_distr::{
+    num_traits::{Bounded, NumCast, PrimInt, ToPrimitive},
+    Distribution, Exp, Normal, Pareto, Uniform,
+};
 use rand_xoshiro::Xoshiro256PlusPlus;
 use serde::{Deserialize, Serialize};
 
@@ -74,6 +77,13 @@ pub enum DiscreteDistribution<T> {
     Always {
         value: T,
     },
+    /// A heavy-tailed distribution whose values are at least `scale`, rounded to the nearest
+    /// integer. Smaller `shape`s give heavier tails, and the mean is infinite if `shape` is at
+    /// most one.
+    Pareto {
+        scale: T,
+        shape: Float,
+    },
 }
 
 impl<T> Distribution<T> for DiscreteDistribution<T>
@@ -89,6 +99,11 @@ where
             )),
 
             DiscreteDistribution::Always { value } => value.to_underlying(),
+            DiscreteDistribution::Pareto { scale, shape } => {
+                let scale = scale.to_underlying().to_f64().unwrap();
+                let value = rng.sample(Pareto::new(scale, *shape).unwrap()).round();
+                NumCast::from(value).unwrap_or_else(T::Underlying::max_value)
+            }
         })
     }
 }
@@ -134,7 +149,7 @@ where
     }
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Rng {
     rng: Xoshiro256PlusPlus,
 }
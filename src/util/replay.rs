@@ -0,0 +1,349 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, Result};
+use generativity::make_guard;
+use serde::Serialize;
+
+use crate::{
+    quantities::{seconds, Time, TimeSpan},
+    simulation::{ComponentId, EffectKind, SimulatorBuilder},
+    Cca, Network,
+};
+
+use super::{logging::NothingLogger, meters::NoFlowMeter, rand::Rng, OfLifetime};
+
+/// Start of every replay file, followed by the version of the format.
+const MAGIC: &[u8; 8] = b"FFREPLAY";
+const VERSION: u8 = 1;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Record {
+    time: Time,
+    destination: u32,
+    /// Index into `ReplayTrace::kinds`.
+    kind: u8,
+}
+
+/// A message delivered during a recorded simulation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Delivery<'t> {
+    pub time: Time,
+    /// Index of the component the message was delivered to.
+    pub destination: usize,
+    pub kind: &'t str,
+}
+
+impl Display for Delivery<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.6}s -> {}: {}",
+            (self.time - Time::SIM_START).seconds(),
+            self.destination,
+            self.kind
+        )
+    }
+}
+
+/// The first delivery at which two traces differ.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Divergence<'t> {
+    pub index: usize,
+    /// `None` if the expected trace ended first.
+    pub expected: Option<Delivery<'t>>,
+    /// `None` if the actual trace ended first.
+    pub actual: Option<Delivery<'t>>,
+}
+
+impl Display for Divergence<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let show = |delivery: Option<Delivery>| {
+            delivery.map_or_else(|| "end of trace".to_string(), |d| d.to_string())
+        };
+        write!(
+            f,
+            "Delivery {} differs: expected {}, got {}",
+            self.index,
+            show(self.expected),
+            show(self.actual)
+        )
+    }
+}
+
+/// Every message delivered during a simulation, in order. Traces can be saved to a compact
+/// binary file, and compared against a later run to check that it behaves identically.
+#[derive(Clone, Debug, Default)]
+pub struct ReplayTrace {
+    kinds: Vec<String>,
+    records: Vec<Record>,
+}
+
+impl ReplayTrace {
+    #[must_use]
+    pub fn new() -> ReplayTrace {
+        ReplayTrace::default()
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn deliveries(&self) -> impl Iterator<Item = Delivery<'_>> + '_ {
+        self.records.iter().map(|record| Delivery {
+            time: record.time,
+            destination: record.destination as usize,
+            kind: &self.kinds[usize::from(record.kind)],
+        })
+    }
+
+    /// Returns a hook for `Simulator::on_delivery` that appends each delivered message to this
+    /// trace.
+    pub fn recorder<'sim, E: EffectKind>(
+        &mut self,
+    ) -> impl FnMut(Time, ComponentId<'sim>, &E) + '_ {
+        |time, destination, effect| self.push(time, destination.index(), effect.kind())
+    }
+
+    fn push(&mut self, time: Time, destination: usize, kind: &str) {
+        let kind = self
+            .kinds
+            .iter()
+            .position(|k| k == kind)
+            .unwrap_or_else(|| {
+                self.kinds.push(kind.to_string());
+                self.kinds.len() - 1
+            });
+        self.records.push(Record {
+            time,
+            destination: u32::try_from(destination).unwrap(),
+            kind: u8::try_from(kind).unwrap(),
+        });
+    }
+
+    /// Returns the 64-bit FNV-1a hash of the deliveries, which changes if any of them do.
+    #[must_use]
+    pub fn digest(&self) -> u64 {
+        self.deliveries()
+            .flat_map(|delivery| {
+                time_bits(delivery.time)
+                    .to_le_bytes()
+                    .into_iter()
+                    .chain((delivery.destination as u64).to_le_bytes())
+                    .chain(delivery.kind.bytes())
+                    .chain([0])
+            })
+            .fold(FNV_OFFSET_BASIS, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+            })
+    }
+
+    /// Returns the first delivery at which `actual` differs from this trace, or `None` if the
+    /// traces are identical.
+    #[must_use]
+    pub fn first_divergence<'t>(&'t self, actual: &'t ReplayTrace) -> Option<Divergence<'t>> {
+        let mut expected_deliveries = self.deliveries();
+        let mut actual_deliveries = actual.deliveries();
+        for index in 0.. {
+            match (expected_deliveries.next(), actual_deliveries.next()) {
+                (None, None) => return None,
+                (expected, actual) if expected != actual => {
+                    return Some(Divergence {
+                        index,
+                        expected,
+                        actual,
+                    });
+                }
+                _ => {}
+            }
+        }
+        unreachable!()
+    }
+
+    /// Returns the number of deliveries, the digest, the number of deliveries of each kind, and
+    /// the first `first` deliveries.
+    #[must_use]
+    pub fn summary(&self, first: usize) -> ReplaySummary {
+        let mut kinds = BTreeMap::new();
+        for delivery in self.deliveries() {
+            *kinds.entry(delivery.kind.to_string()).or_default() += 1;
+        }
+        ReplaySummary {
+            deliveries: self.len(),
+            digest: format!("{:016x}", self.digest()),
+            kinds,
+            first: self
+                .deliveries()
+                .take(first)
+                .map(|delivery| delivery.to_string())
+                .collect(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load(path: &Path) -> Result<ReplayTrace> {
+        ReplayTrace::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Writes the kinds of message, each as a length-prefixed string, followed by the number of
+    /// deliveries and 13 bytes for each.
+    pub fn write(&self, mut writer: impl Write) -> Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION, u8::try_from(self.kinds.len())?])?;
+        for kind in &self.kinds {
+            writer.write_all(&[u8::try_from(kind.len())?])?;
+            writer.write_all(kind.as_bytes())?;
+        }
+        writer.write_all(&(self.records.len() as u64).to_le_bytes())?;
+        for record in &self.records {
+            writer.write_all(&time_bits(record.time).to_le_bytes())?;
+            writer.write_all(&record.destination.to_le_bytes())?;
+            writer.write_all(&[record.kind])?;
+        }
+        Ok(writer.flush()?)
+    }
+
+    pub fn read(mut reader: impl Read) -> Result<ReplayTrace> {
+        let [magic @ .., version, num_kinds] = read_bytes::<10>(&mut reader)?;
+        if &magic != MAGIC || version != VERSION {
+            return Err(anyhow!(
+                "Not a replay file, or from an incompatible version!"
+            ));
+        }
+        let kinds = (0..num_kinds)
+            .map(|_| {
+                let [len] = read_bytes(&mut reader)?;
+                let mut kind = vec![0; usize::from(len)];
+                reader.read_exact(&mut kind)?;
+                Ok(String::from_utf8(kind)?)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let num_records = u64::from_le_bytes(read_bytes(&mut reader)?);
+        let records = (0..num_records)
+            .map(|_| {
+                let record = Record {
+                    time: Time::from_sim_start(seconds(f64::from_bits(u64::from_le_bytes(
+                        read_bytes(&mut reader)?,
+                    )))),
+                    destination: u32::from_le_bytes(read_bytes(&mut reader)?),
+                    kind: read_bytes::<1>(&mut reader)?[0],
+                };
+                if usize::from(record.kind) >= kinds.len() {
+                    return Err(anyhow!("Replay file refers to an unknown kind of message!"));
+                }
+                Ok(record)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ReplayTrace { kinds, records })
+    }
+}
+
+/// Human-readable summary of a `ReplayTrace`.
+#[derive(Serialize, Debug)]
+pub struct ReplaySummary {
+    pub deliveries: usize,
+    /// `ReplayTrace::digest`, in hexadecimal.
+    pub digest: String,
+    /// Number of deliveries of each kind.
+    pub kinds: BTreeMap<String, usize>,
+    pub first: Vec<String>,
+}
+
+fn time_bits(time: Time) -> u64 {
+    (time - Time::SIM_START).seconds().to_bits()
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Simulates a single network for `run_for`, recording every message delivered.
+pub fn record<G, C>(
+    network: &impl Network<G>,
+    new_cca: impl Fn() -> C,
+    rng: &mut Rng,
+    run_for: TimeSpan,
+) -> ReplayTrace
+where
+    G: OfLifetime,
+    for<'sim> G::Of<'sim>: EffectKind,
+    C: Cca,
+{
+    let mut trace = ReplayTrace::new();
+    make_guard!(guard);
+    let builder = SimulatorBuilder::new(guard);
+    network.populate_sim(&builder, || &new_cca, rng, || NoFlowMeter);
+    let clock = builder.clock();
+    let mut sim = builder.build(NothingLogger).unwrap();
+    sim.on_delivery(trace.recorder());
+    let sim_end = Time::from_sim_start(run_for);
+    while clock.time() < sim_end && sim.tick() {}
+    drop(sim);
+    trace
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ccas::remy::{dna::RemyDna, RemyCcaTemplate},
+        networks::remy::{RemyNetwork, RemyNetworkDistribution},
+        quantities::seconds,
+        trainers::{remy::RemyTrainer, DefaultEffect},
+        util::rand::Rng,
+        CcaTemplate,
+    };
+
+    use super::{record, ReplayTrace};
+
+    fn record_remy_network(seed: u64) -> ReplayTrace {
+        let mut rng = Rng::from_seed(seed);
+        let network: RemyNetwork = rng.sample(&RemyNetworkDistribution::default());
+        let dna = RemyDna::default(RemyTrainer::default().default_action);
+        record::<DefaultEffect, _>(
+            &network,
+            RemyCcaTemplate::default().with(&dna),
+            &mut rng,
+            seconds(10.),
+        )
+    }
+
+    #[test]
+    fn remy_network_golden_trace() {
+        insta::assert_yaml_snapshot!(record_remy_network(418_263).summary(40));
+    }
+
+    #[test]
+    fn saved_trace_replays_identically() {
+        let trace = record_remy_network(418_263);
+        let mut file = Vec::new();
+        trace.write(&mut file).unwrap();
+        let loaded = ReplayTrace::read(file.as_slice()).unwrap();
+        assert_eq!(loaded.len(), trace.len());
+        assert_eq!(loaded.digest(), trace.digest());
+        assert_eq!(trace.first_divergence(&record_remy_network(418_263)), None);
+
+        let other = record_remy_network(1);
+        let divergence = trace.first_divergence(&other).unwrap();
+        assert_ne!(divergence.expected, divergence.actual);
+        assert!(ReplayTrace::read(&file[1..]).is_err());
+    }
+}
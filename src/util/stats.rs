@@ -0,0 +1,114 @@
+use rand::distributions::{Bernoulli, Uniform};
+use serde::{Deserialize, Serialize};
+
+use crate::quantities::Float;
+
+use super::{
+    average::{IterAverage, NoItems},
+    rand::Rng,
+};
+
+/// Number of resamples used for bootstrap confidence intervals and permutation tests.
+pub const BOOTSTRAP_RESAMPLES: usize = 10_000;
+
+/// Mean of a set of samples, with a confidence interval for it.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Estimate {
+    pub mean: Float,
+    pub ci_low: Float,
+    pub ci_high: Float,
+}
+
+impl Estimate {
+    /// Estimates the mean of `samples`, with a percentile bootstrap confidence interval at the
+    /// given `confidence` level.
+    pub fn bootstrap(
+        samples: &[Float],
+        confidence: Float,
+        rng: &mut Rng,
+    ) -> Result<Estimate, NoItems> {
+        let mean = samples.iter().copied().average()?;
+        let index = Uniform::new(0, samples.len());
+        let mut means = (0..BOOTSTRAP_RESAMPLES)
+            .map(|_| {
+                (0..samples.len())
+                    .map(|_| samples[rng.sample(&index)])
+                    .average()
+                    .unwrap()
+            })
+            .collect::<Vec<Float>>();
+        means.sort_by(Float::total_cmp);
+        let tail = (1. - confidence) / 2.;
+        Ok(Estimate {
+            mean,
+            ci_low: percentile(&means, tail),
+            ci_high: percentile(&means, 1. - tail),
+        })
+    }
+}
+
+/// Two-sided paired permutation test of whether the mean of `differences` is zero, which flips
+/// the sign of each difference at random. Returns the p-value.
+pub fn paired_permutation_test(differences: &[Float], rng: &mut Rng) -> Result<Float, NoItems> {
+    let observed = differences.iter().copied().average()?.abs();
+    let flip = Bernoulli::new(0.5).unwrap();
+    let at_least_as_extreme = (0..BOOTSTRAP_RESAMPLES)
+        .filter(|_| {
+            let mean = differences
+                .iter()
+                .map(|&x| if rng.sample(&flip) { -x } else { x })
+                .average()
+                .unwrap();
+            mean.abs() >= observed
+        })
+        .count();
+    #[allow(clippy::cast_precision_loss)]
+    Ok((at_least_as_extreme + 1) as Float / (BOOTSTRAP_RESAMPLES + 1) as Float)
+}
+
+/// Returns the `p`-quantile of `sorted` by the nearest-rank method.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn percentile(sorted: &[Float], p: Float) -> Float {
+    sorted[((p * sorted.len() as Float).ceil() as usize).clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::{average::NoItems, rand::Rng};
+
+    use super::{paired_permutation_test, Estimate};
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn bootstrap_interval_contains_mean() {
+        let samples = [3., 1., 4., 1., 5., 9., 2., 6., 5., 3., 5., 7.];
+        let estimate = Estimate::bootstrap(&samples, 0.95, &mut Rng::from_seed(0)).unwrap();
+        assert_eq!(estimate.mean, 4.25);
+        assert!(2.5 < estimate.ci_low && estimate.ci_low < estimate.mean);
+        assert!(estimate.mean < estimate.ci_high && estimate.ci_high < 6.);
+
+        let constant = Estimate::bootstrap(&[2.; 5], 0.95, &mut Rng::from_seed(0)).unwrap();
+        assert_eq!((constant.ci_low, constant.ci_high), (2., 2.));
+        assert_eq!(
+            Estimate::bootstrap(&[], 0.95, &mut Rng::from_seed(0)),
+            Err(NoItems)
+        );
+    }
+
+    #[test]
+    fn permutation_test_detects_consistent_differences() {
+        let improved = [0.5, 1.2, 0.3, 0.8, 1.1, 0.4, 0.9, 0.7, 1.5, 0.6, 0.2, 1.0];
+        let p = paired_permutation_test(&improved, &mut Rng::from_seed(0)).unwrap();
+        assert!(p < 0.01, "p was {p}");
+
+        let noise = [
+            0.5, -1.2, 0.3, -0.8, 1.1, -0.4, 0.9, -0.7, -1.5, 0.6, -0.2, 1.0,
+        ];
+        let p = paired_permutation_test(&noise, &mut Rng::from_seed(0)).unwrap();
+        assert!(p > 0.5, "p was {p}");
+    }
+}